@@ -0,0 +1,458 @@
+pub mod walk_forward {
+    use std::{
+        collections::{BTreeMap, BTreeSet},
+        error::Error,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use build_html::{escape_html, Html, HtmlContainer, HtmlPage, Table};
+    use chrono::NaiveDate;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        calibration::calibration_report::{get_pit, CALIBRATION_PERCENTS},
+        logging::logger::NO_SYMBOL,
+        monte_carlo::simulations::{
+            get_percentile_table, get_symbol_seed, monte_carlo_simulation, simulation_outcome,
+            Percentiles, Prediction, SimulationSettings,
+        },
+        report::run_report::{
+            get_disclaimer_html, get_header_html, Branding, RunMetadata, SCHEMA_VERSION,
+        },
+        stock_simulation::stock_simulator::{
+            get_highest_x, save_results, validate_top_x, SimulationError, Strategy,
+        },
+        utilities::util::{csv_line, log, log_warning},
+    };
+
+    /// Daily returns of one symbol by date
+    pub type History = BTreeMap<NaiveDate, f64>;
+
+    /// How the history is walked and what is picked at every rebalance
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct BacktestSettings {
+        /// trading days of history the simulations draw from at every rebalance
+        pub window: usize,
+        /// trading days between rebalances
+        pub step: usize,
+        pub top_x: usize,
+        pub strategy: Strategy,
+    }
+
+    /// What the simulations said about one symbol at a rebalance and what it then did
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Forecast {
+        pub symbol: String,
+        pub percentiles: Percentiles,
+        /// gain or loss on 100 at each of the calibration percentiles
+        #[serde(default)]
+        pub quantiles: Vec<(u32, i32)>,
+        /// gain or loss on 100 over the days after the rebalance
+        pub realized: f64,
+        /// fraction of the simulated outcomes below the realized one
+        #[serde(default)]
+        pub pit: f64,
+    }
+
+    /// One rebalance date: the symbols picked from the trailing window and the returns that followed
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Rebalance {
+        /// last date of the trailing window, YYYY-MM-DD
+        pub date: String,
+        /// the top x in ranked order
+        pub picks: Vec<String>,
+        /// average gain or loss on 100 of the picks
+        pub portfolio: f64,
+        /// average gain or loss on 100 of every symbol that could be picked
+        pub benchmark: f64,
+        /// every symbol simulated at this rebalance
+        pub forecasts: Vec<Forecast>,
+    }
+
+    /// Everything a backtest produced, the document behind the json output
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct BacktestResults {
+        pub schema_version: u32,
+        pub metadata: RunMetadata,
+        pub backtest: BacktestSettings,
+        pub rebalances: Vec<Rebalance>,
+    }
+
+    /// How the picks did against the benchmark over every rebalance
+    #[derive(Debug, PartialEq)]
+    pub struct BacktestSummary {
+        pub periods: usize,
+        pub mean_portfolio: f64,
+        pub mean_benchmark: f64,
+        pub mean_excess: f64,
+        /// fraction of rebalances where the picks beat the benchmark
+        pub hit_rate: f64,
+        /// gain or loss on 100 compounded over every period, only a real track record when the step equals the days
+        pub portfolio_growth: f64,
+        pub benchmark_growth: f64,
+    }
+
+    /// Walks forward through the dated histories in the directory and outputs how the picks did
+    pub fn run_backtest(
+        dir: &PathBuf,
+        settings: &SimulationSettings,
+        backtest: &BacktestSettings,
+        output: &Path,
+        branding: &Branding,
+    ) -> Result<(), SimulationError> {
+        validate_top_x(backtest.top_x)?;
+        let histories = read_dated_symbols(dir);
+        log(
+            NO_SYMBOL,
+            format!("backtest of {} symbols", histories.len()),
+        );
+
+        let results = BacktestResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: RunMetadata {
+                header: branding.header.clone(),
+                disclaimer: branding.disclaimer.clone(),
+                strategy: backtest.strategy,
+                ..RunMetadata::new(dir, settings, backtest.top_x)
+            },
+            backtest: *backtest,
+            rebalances: get_rebalances(&histories, settings, backtest),
+        };
+
+        let extension = output
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+        let contents = match extension.as_deref() {
+            Some("csv") => get_backtest_csv(&results.rebalances),
+            Some("json") => {
+                serde_json::to_string_pretty(&results).expect("backtest results always serialize")
+            }
+            _ => get_backtest_html(&results),
+        };
+        save_results(output, &contents)?;
+
+        match get_summary(&results.rebalances) {
+            Some(summary) => log(
+                NO_SYMBOL,
+                format!(
+                    "{} rebalances, mean excess return {:.2} on 100, hit rate {:.0}%",
+                    summary.periods,
+                    summary.mean_excess,
+                    summary.hit_rate * 100.0
+                ),
+            ),
+            None => log_warning(NO_SYMBOL, "the history is too short for a single rebalance"),
+        }
+        Ok(())
+    }
+
+    /// Every trading day any symbol has a return for, in order
+    pub(crate) fn get_calendar(histories: &[(String, History)]) -> Vec<NaiveDate> {
+        let dates: BTreeSet<NaiveDate> = histories
+            .iter()
+            .flat_map(|(_, history)| history.keys().copied())
+            .collect();
+        dates.into_iter().collect()
+    }
+
+    /// Gain or loss on 100 over the dates, a day without a return counts as flat
+    pub(crate) fn get_realized_return(history: &History, dates: &[NaiveDate]) -> f64 {
+        let rates: Vec<f64> = dates
+            .iter()
+            .map(|date| history.get(date).copied().unwrap_or(0.0))
+            .collect();
+        simulation_outcome(&rates)
+    }
+
+    /// Every `step` trading days, once a full window lies behind and `days` lie ahead, simulates the symbols with
+    /// a return on every day of the window, picks the top x and measures the days that followed
+    pub(crate) fn get_rebalances(
+        histories: &[(String, History)],
+        settings: &SimulationSettings,
+        backtest: &BacktestSettings,
+    ) -> Vec<Rebalance> {
+        let calendar = get_calendar(histories);
+        let days = settings.periods as usize;
+        let mut rebalances = Vec::new();
+        if backtest.window == 0 || backtest.step == 0 {
+            return rebalances;
+        }
+
+        let mut end = backtest.window;
+        while end + days <= calendar.len() {
+            let window = &calendar[end - backtest.window..end];
+            let forward = &calendar[end..end + days];
+            let date = calendar[end - 1].to_string();
+            end += backtest.step;
+
+            // draws depend on the date so rebalances do not repeat each other's paths
+            let rebalance_settings = SimulationSettings {
+                seed: get_symbol_seed(settings.seed, &date),
+                ..*settings
+            };
+
+            let mut predictions: Vec<Prediction> = Vec::new();
+            let mut realized = BTreeMap::new();
+            for (symbol, history) in histories {
+                let data: Option<Vec<f64>> =
+                    window.iter().map(|day| history.get(day).copied()).collect();
+                let Some(data) = data else {
+                    continue;
+                };
+                match monte_carlo_simulation(symbol.clone(), &data, &rebalance_settings) {
+                    Ok(prediction) => {
+                        realized.insert(symbol.clone(), get_realized_return(history, forward));
+                        predictions.push(prediction);
+                    }
+                    Err(e) => log_warning(symbol, format!("{date}: {e}")),
+                }
+            }
+            if predictions.is_empty() {
+                log_warning(
+                    NO_SYMBOL,
+                    format!("{date}: no symbol covers the whole window"),
+                );
+                continue;
+            }
+
+            let picks: Vec<String> =
+                get_highest_x(backtest.top_x, &predictions, backtest.strategy.ordering())
+                    .into_iter()
+                    .map(|top| top.symbol)
+                    .collect();
+            let portfolio =
+                picks.iter().map(|symbol| realized[symbol]).sum::<f64>() / picks.len() as f64;
+            let benchmark = realized.values().sum::<f64>() / realized.len() as f64;
+            let forecasts = predictions
+                .into_iter()
+                .map(|prediction| {
+                    let realized = realized[&prediction.symbol];
+                    Forecast {
+                        quantiles: get_percentile_table(&prediction.data, &CALIBRATION_PERCENTS),
+                        pit: get_pit(&prediction.data, realized),
+                        realized,
+                        symbol: prediction.symbol,
+                        percentiles: prediction.percentiles,
+                    }
+                })
+                .collect();
+
+            rebalances.push(Rebalance {
+                date,
+                picks,
+                portfolio,
+                benchmark,
+                forecasts,
+            });
+        }
+
+        rebalances
+    }
+
+    pub(crate) fn get_summary(rebalances: &[Rebalance]) -> Option<BacktestSummary> {
+        if rebalances.is_empty() {
+            return None;
+        }
+        let periods = rebalances.len();
+        let mean = |value: fn(&Rebalance) -> f64| {
+            rebalances.iter().map(value).sum::<f64>() / periods as f64
+        };
+        let growth = |value: fn(&Rebalance) -> f64| {
+            let rates: Vec<f64> = rebalances.iter().map(|r| value(r) / 100.0).collect();
+            simulation_outcome(&rates)
+        };
+        let wins = rebalances
+            .iter()
+            .filter(|rebalance| rebalance.portfolio > rebalance.benchmark)
+            .count();
+
+        Some(BacktestSummary {
+            periods,
+            mean_portfolio: mean(|r| r.portfolio),
+            mean_benchmark: mean(|r| r.benchmark),
+            mean_excess: mean(|r| r.portfolio - r.benchmark),
+            hit_rate: wins as f64 / periods as f64,
+            portfolio_growth: growth(|r| r.portfolio),
+            benchmark_growth: growth(|r| r.benchmark),
+        })
+    }
+
+    /// Header, one row per rebalance and the footer of summary rows
+    pub(crate) fn get_backtest_rows(
+        rebalances: &[Rebalance],
+    ) -> (Vec<String>, Vec<Vec<String>>, Vec<Vec<String>>) {
+        let header = ["date", "picks", "top x", "benchmark", "excess", "symbols"]
+            .map(String::from)
+            .to_vec();
+
+        let body = rebalances
+            .iter()
+            .map(|rebalance| {
+                vec![
+                    rebalance.date.clone(),
+                    rebalance.picks.join(" "),
+                    format!("{:.2}", rebalance.portfolio),
+                    format!("{:.2}", rebalance.benchmark),
+                    format!("{:.2}", rebalance.portfolio - rebalance.benchmark),
+                    rebalance.forecasts.len().to_string(),
+                ]
+            })
+            .collect();
+
+        let footer = match get_summary(rebalances) {
+            Some(summary) => vec![
+                vec![
+                    "mean".to_string(),
+                    String::new(),
+                    format!("{:.2}", summary.mean_portfolio),
+                    format!("{:.2}", summary.mean_benchmark),
+                    format!("{:.2}", summary.mean_excess),
+                    String::new(),
+                ],
+                vec![
+                    "compounded".to_string(),
+                    String::new(),
+                    format!("{:.2}", summary.portfolio_growth),
+                    format!("{:.2}", summary.benchmark_growth),
+                    format!("{:.2}", summary.portfolio_growth - summary.benchmark_growth),
+                    String::new(),
+                ],
+                vec![
+                    "beat the benchmark".to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    format!("{:.0}%", summary.hit_rate * 100.0),
+                    String::new(),
+                ],
+            ],
+            None => Vec::new(),
+        };
+
+        (header, body, footer)
+    }
+
+    pub(crate) fn get_backtest_csv(rebalances: &[Rebalance]) -> String {
+        let (header, body, footer) = get_backtest_rows(rebalances);
+        let mut csv = String::new();
+        for row in std::iter::once(&header)
+            .chain(body.iter())
+            .chain(footer.iter())
+        {
+            csv.push_str(&csv_line(row));
+        }
+        csv
+    }
+
+    pub(crate) fn get_backtest_html(results: &BacktestResults) -> String {
+        let (header, body, footer) = get_backtest_rows(&results.rebalances);
+        let mut table = Table::new()
+            .with_attributes(vec![("class", "study")])
+            .with_header_row(header);
+        for row in body {
+            table.add_body_row(row.iter().map(|cell| escape_html(cell)));
+        }
+        for row in footer {
+            table.add_footer_row(row);
+        }
+
+        let metadata = &results.metadata;
+        let backtest = &results.backtest;
+        let description = format!(
+            "Every {} trading days the top {} by {} were picked from {} simulations of the trailing {} trading days \
+             and held for the next {} days, against an equal weight benchmark of every symbol with a full window. \
+             Returns are the gain or loss on 100.",
+            backtest.step,
+            backtest.top_x,
+            backtest.strategy,
+            metadata.simulations,
+            backtest.window,
+            metadata.days
+        );
+        let summary = match get_summary(&results.rebalances) {
+            Some(summary) => format!(
+                "Over {} rebalances the picks returned {:.2} against {:.2} for the benchmark on average and beat it {:.0}% of the time.",
+                summary.periods,
+                summary.mean_portfolio,
+                summary.mean_benchmark,
+                summary.hit_rate * 100.0
+            ),
+            None => "The history is too short for a single rebalance.".to_string(),
+        };
+
+        HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_title("Backtest")
+            .with_style(include_str!("style.css"))
+            .with_raw(get_header_html(metadata.header.as_deref()))
+            .with_header(1, chrono::Local::now().format("Backtest - %B %d, %Y"))
+            .with_paragraph(description)
+            .with_paragraph(summary)
+            .with_table(table)
+            .with_raw(get_disclaimer_html(metadata.disclaimer.as_deref()))
+            .to_html_string()
+    }
+
+    /// Method to read the dated history of every symbol in the directory, files are not moved to the archive
+    pub(crate) fn read_dated_symbols(dir: &PathBuf) -> Vec<(String, History)> {
+        let mut symbols = Vec::new();
+        let contents = match fs::read_dir(dir) {
+            Err(e) => {
+                log_warning(NO_SYMBOL, format!("{}: {e}", dir.display()));
+                return symbols;
+            }
+            Ok(contents) => contents,
+        };
+
+        for entry in contents {
+            let path = match entry {
+                Err(e) => {
+                    log_warning(NO_SYMBOL, e);
+                    continue;
+                }
+                Ok(entry) => entry.path(),
+            };
+            if path.is_dir() {
+                continue;
+            }
+
+            let symbol = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match fs::read_to_string(&path)
+                .map_err(|e| e.into())
+                .and_then(|content| get_dated_returns(&content))
+            {
+                Ok(history) => symbols.push((symbol, history)),
+                Err(e) => log_warning(&symbol, e),
+            }
+        }
+
+        symbols.sort_by(|left, right| left.0.cmp(&right.0));
+        symbols
+    }
+
+    /// Reads one `YYYY-MM-DD,return` pair per line, blank lines are skipped
+    pub(crate) fn get_dated_returns(content: &str) -> Result<History, Box<dyn Error>> {
+        let mut history = History::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (date, value) = line
+                .split_once(',')
+                .ok_or_else(|| format!("line {}: expected <date>,<return>", number + 1))?;
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|e| format!("line {}: {e}", number + 1))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("line {}: {e}", number + 1))?;
+            history.insert(date, value);
+        }
+        Ok(history)
+    }
+}
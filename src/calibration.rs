@@ -0,0 +1,292 @@
+pub mod calibration_report {
+    use std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use build_html::{escape_html, Html, HtmlContainer, HtmlPage, Table};
+
+    use crate::{
+        backtest::walk_forward::{BacktestResults, Forecast},
+        charts::svg_charts::get_pit_svg,
+        logging::logger::NO_SYMBOL,
+        report::run_report::{get_disclaimer_html, get_header_html, Branding},
+        stock_simulation::stock_simulator::{save_results, SimulationError},
+        utilities::util::{csv_line, log},
+    };
+
+    /// Percentiles kept for every forecast of a backtest, the bands and the pinball loss are built from these
+    pub const CALIBRATION_PERCENTS: [u32; 7] = [5, 10, 25, 50, 75, 90, 95];
+
+    /// Central bands whose coverage is checked, a calibrated forecast holds the outcome upper - lower percent
+    /// of the time
+    pub const BANDS: [(u32, u32); 3] = [(25, 75), (10, 90), (5, 95)];
+
+    /// How well one group of forecasts matched what happened
+    #[derive(Debug, PartialEq)]
+    pub struct Calibration {
+        pub label: String,
+        pub forecasts: usize,
+        /// fraction of realized outcomes inside each of the `BANDS`
+        pub coverage: Vec<f64>,
+        /// mean pinball loss over the `CALIBRATION_PERCENTS`, lower is better
+        pub pinball_loss: f64,
+        /// number of forecasts in each equal slice of the probability integral transform, flat when calibrated
+        pub pit: Vec<usize>,
+    }
+
+    /// Reads backtest results and outputs the calibration of each file and of each sampler across the files, an input
+    /// that cannot be read or is not a backtest fails the run rather than dropping out of the report
+    pub fn run_calibration(
+        inputs: &[PathBuf],
+        bins: usize,
+        output: &Path,
+        branding: &Branding,
+    ) -> Result<(), SimulationError> {
+        let mut by_run = Vec::new();
+        let mut by_sampler: BTreeMap<String, Vec<Forecast>> = BTreeMap::new();
+        for input in inputs {
+            let json = fs::read_to_string(input)
+                .map_err(|e| SimulationError::Io(format!("{}: {e}", input.display())))?;
+            let results: BacktestResults = serde_json::from_str(&json)
+                .map_err(|e| SimulationError::Parse(format!("{}: {e}", input.display())))?;
+
+            let forecasts: Vec<Forecast> = results
+                .rebalances
+                .into_iter()
+                .flat_map(|rebalance| rebalance.forecasts)
+                .collect();
+            let name = input
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let label = format!(
+                "{name} ({} sampler, {} quantiles)",
+                results.metadata.sampler, results.metadata.quantiles
+            );
+            by_run.push(get_calibration(&label, &forecasts, bins));
+            by_sampler
+                .entry(results.metadata.sampler)
+                .or_default()
+                .extend(forecasts);
+        }
+        let by_sampler: Vec<Calibration> = by_sampler
+            .iter()
+            .map(|(sampler, forecasts)| get_calibration(sampler, forecasts, bins))
+            .collect();
+
+        let is_csv = output
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let contents = if is_csv {
+            get_calibration_csv(&by_run, &by_sampler)
+        } else {
+            get_calibration_html(&by_run, &by_sampler, branding)
+        };
+        save_results(output, &contents)?;
+
+        for calibration in &by_run {
+            log(
+                NO_SYMBOL,
+                format!(
+                    "{}: 25th to 75th coverage {:.1}% over {} forecasts, pinball loss {:.3}",
+                    calibration.label,
+                    calibration.coverage[0] * 100.0,
+                    calibration.forecasts,
+                    calibration.pinball_loss
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// Fraction of the simulated outcomes below the realized one, half of the outcomes at its whole value count
+    /// as below. 0.5 is returned when there is no distribution.
+    pub(crate) fn get_pit(data: &BTreeMap<i32, u32>, realized: f64) -> f64 {
+        let total: u64 = data.values().map(|count| *count as u64).sum();
+        if total == 0 {
+            return 0.5;
+        }
+        let rounded = realized.round() as i32;
+        let below: u64 = data.range(..rounded).map(|(_, count)| *count as u64).sum();
+        let at = data.get(&rounded).copied().unwrap_or(0) as f64;
+        (below as f64 + at / 2.0) / total as f64
+    }
+
+    fn get_quantile(forecast: &Forecast, percent: u32) -> Option<f64> {
+        forecast
+            .quantiles
+            .iter()
+            .find(|(p, _)| *p == percent)
+            .map(|(_, outcome)| *outcome as f64)
+    }
+
+    /// Fraction of the forecasts whose realized outcome is inside their lower to upper percentile band
+    pub(crate) fn get_coverage(forecasts: &[Forecast], lower: u32, upper: u32) -> f64 {
+        let bands: Vec<(f64, f64, f64)> = forecasts
+            .iter()
+            .filter_map(|forecast| {
+                Some((
+                    get_quantile(forecast, lower)?,
+                    get_quantile(forecast, upper)?,
+                    forecast.realized,
+                ))
+            })
+            .collect();
+        if bands.is_empty() {
+            return 0.0;
+        }
+        let inside = bands
+            .iter()
+            .filter(|(low, high, realized)| low <= realized && realized <= high)
+            .count();
+        inside as f64 / bands.len() as f64
+    }
+
+    /// Mean pinball loss of every kept percentile of every forecast
+    pub(crate) fn get_pinball_loss(forecasts: &[Forecast]) -> f64 {
+        let losses: Vec<f64> = forecasts
+            .iter()
+            .flat_map(|forecast| {
+                forecast.quantiles.iter().map(|(percent, outcome)| {
+                    let level = *percent as f64 / 100.0;
+                    let error = forecast.realized - *outcome as f64;
+                    if error >= 0.0 {
+                        level * error
+                    } else {
+                        (level - 1.0) * error
+                    }
+                })
+            })
+            .collect();
+        if losses.is_empty() {
+            return 0.0;
+        }
+        losses.iter().sum::<f64>() / losses.len() as f64
+    }
+
+    pub(crate) fn get_pit_histogram(forecasts: &[Forecast], bins: usize) -> Vec<usize> {
+        let bins = bins.max(1);
+        let mut counts = vec![0; bins];
+        for forecast in forecasts {
+            let bin = (forecast.pit * bins as f64) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        counts
+    }
+
+    pub(crate) fn get_calibration(label: &str, forecasts: &[Forecast], bins: usize) -> Calibration {
+        Calibration {
+            label: label.to_string(),
+            forecasts: forecasts.len(),
+            coverage: BANDS
+                .iter()
+                .map(|(lower, upper)| get_coverage(forecasts, *lower, *upper))
+                .collect(),
+            pinball_loss: get_pinball_loss(forecasts),
+            pit: get_pit_histogram(forecasts, bins),
+        }
+    }
+
+    /// Header and one row per calibration, the pit columns count the forecasts in each slice
+    pub(crate) fn get_calibration_rows(
+        calibrations: &[Calibration],
+    ) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut header = vec!["forecasts".to_string()];
+        for (lower, upper) in BANDS {
+            header.push(format!("{lower}th to {upper}th ({}%)", upper - lower));
+        }
+        header.push("pinball loss".to_string());
+        let bins = calibrations.first().map_or(0, |c| c.pit.len());
+        for bin in 0..bins {
+            header.push(format!("pit {:.2}", bin as f64 / bins as f64));
+        }
+
+        let body = calibrations
+            .iter()
+            .map(|calibration| {
+                let mut row = vec![calibration.label.clone(), calibration.forecasts.to_string()];
+                row.extend(
+                    calibration
+                        .coverage
+                        .iter()
+                        .map(|coverage| format!("{:.1}%", coverage * 100.0)),
+                );
+                row.push(format!("{:.3}", calibration.pinball_loss));
+                row.extend(calibration.pit.iter().map(|count| count.to_string()));
+                row
+            })
+            .collect();
+
+        (header, body)
+    }
+
+    pub(crate) fn get_calibration_csv(
+        by_run: &[Calibration],
+        by_sampler: &[Calibration],
+    ) -> String {
+        let mut csv = String::new();
+        for (name, calibrations) in [("run", by_run), ("sampler", by_sampler)] {
+            let (header, body) = get_calibration_rows(calibrations);
+            csv.push_str(&csv_line(
+                &std::iter::once(name.to_string())
+                    .chain(header)
+                    .collect::<Vec<String>>(),
+            ));
+            for row in body {
+                csv.push_str(&csv_line(&row));
+            }
+        }
+        csv
+    }
+
+    fn get_table(name: &str, calibrations: &[Calibration]) -> Table {
+        let (header, body) = get_calibration_rows(calibrations);
+        let mut table = Table::new()
+            .with_attributes(vec![("class", "study")])
+            .with_header_row(std::iter::once(name.to_string()).chain(header));
+        for row in body {
+            table.add_body_row(row.iter().map(|cell| escape_html(cell)));
+        }
+        table
+    }
+
+    pub(crate) fn get_calibration_html(
+        by_run: &[Calibration],
+        by_sampler: &[Calibration],
+        branding: &Branding,
+    ) -> String {
+        let mut page = HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_title("Calibration")
+            .with_style(include_str!("style.css"))
+            .with_raw(get_header_html(branding.header.as_deref()))
+            .with_header(1, chrono::Local::now().format("Calibration - %B %d, %Y"))
+            .with_paragraph(
+                "Coverage is how often the realized gain or loss landed inside each percentile band, a calibrated \
+                 forecast matches the percentage in the column header. The pinball loss scores the 5th, 10th, 25th, \
+                 50th, 75th, 90th and 95th percentiles together, lower is better. The PIT histogram counts where \
+                 each realized outcome fell within its forecast distribution: flat is calibrated, a U shape means \
+                 the bands are too narrow and a hump means they are too wide.",
+            )
+            .with_header(2, "By Run")
+            .with_table(get_table("run", by_run));
+
+        let mut charts = String::from("<div class=\"charts\">");
+        for calibration in by_run {
+            charts.push_str(&format!(
+                "<figure>{}<figcaption>{}</figcaption></figure>",
+                get_pit_svg(&calibration.pit),
+                escape_html(&calibration.label)
+            ));
+        }
+        charts.push_str("</div>");
+        page.add_raw(charts);
+
+        page.with_header(2, "By Sampler")
+            .with_table(get_table("sampler", by_sampler))
+            .with_raw(get_disclaimer_html(branding.disclaimer.as_deref()))
+            .to_html_string()
+    }
+}
@@ -0,0 +1,221 @@
+pub mod svg_charts {
+    use std::collections::BTreeMap;
+
+    use crate::monte_carlo::simulations::{FanBand, Percentiles};
+
+    const WIDTH: f64 = 360.0;
+    const PLOT_HEIGHT: f64 = 110.0;
+    const AXIS_HEIGHT: f64 = 30.0;
+
+    /// Outcomes are grouped so the histogram never has more bars than this
+    const MAX_BARS: i64 = 60;
+
+    /// Histogram of the outcomes as a self-contained svg with the 25th, 50th and 75th percentiles marked.
+    /// Returns an empty string when there is no distribution to draw.
+    pub(crate) fn get_histogram_svg(
+        data: &BTreeMap<i32, u32>,
+        percentiles: &Percentiles,
+    ) -> String {
+        let (Some(min), Some(max)) = (data.keys().next(), data.keys().next_back()) else {
+            return String::new();
+        };
+        // outcomes saturate at the ends of i32 on long or volatile horizons, so the span is worked out in i64
+        let (min, max) = (*min as i64, *max as i64);
+
+        let range = max - min + 1;
+        let bin_width = (range + MAX_BARS - 1) / MAX_BARS;
+        let bins = (range + bin_width - 1) / bin_width;
+        let mut counts = vec![0u64; bins as usize];
+        for (outcome, count) in data {
+            counts[((*outcome as i64 - min) / bin_width) as usize] += *count as u64;
+        }
+        let tallest = counts.iter().copied().max().unwrap_or(1).max(1) as f64;
+
+        let scale = WIDTH / range as f64;
+        let x = |outcome: f64| (outcome - min as f64) * scale;
+
+        let mut svg = format!(
+            "<svg class=\"histogram\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {WIDTH} {}\" role=\"img\">",
+            PLOT_HEIGHT + AXIS_HEIGHT
+        );
+        svg.push_str(&format!(
+            "<title>Outcomes from {min} to {max}, 25th {}, 50th {}, 75th {}</title>",
+            percentiles._25th, percentiles._50th, percentiles._75th
+        ));
+
+        for (bin, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let left = x((min + bin as i64 * bin_width) as f64);
+            let right = x(((min + (bin as i64 + 1) * bin_width).min(max + 1)) as f64);
+            let height = *count as f64 / tallest * PLOT_HEIGHT;
+            svg.push_str(&format!(
+                "<rect class=\"bar\" x=\"{left:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{height:.1}\"/>",
+                PLOT_HEIGHT - height,
+                (right - left).max(0.5)
+            ));
+        }
+
+        // a marker sits in the middle of the outcome it marks
+        let markers = [
+            ("p25", percentiles._25th),
+            ("p50", percentiles._50th),
+            ("p75", percentiles._75th),
+        ];
+        for (class, outcome) in markers {
+            let position = x(outcome as f64 + 0.5);
+            svg.push_str(&format!(
+                "<line class=\"marker {class}\" x1=\"{position:.1}\" y1=\"0\" x2=\"{position:.1}\" y2=\"{PLOT_HEIGHT}\"/>"
+            ));
+        }
+
+        let label_y = PLOT_HEIGHT + 14.0;
+        svg.push_str(&format!(
+            "<text x=\"0\" y=\"{label_y}\" text-anchor=\"start\">{min}</text>\
+            <text x=\"{WIDTH}\" y=\"{label_y}\" text-anchor=\"end\">{max}</text>"
+        ));
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">25th {} / 50th {} / 75th {}</text>",
+            WIDTH / 2.0,
+            label_y + 14.0,
+            percentiles._25th,
+            percentiles._50th,
+            percentiles._75th
+        ));
+        svg.push_str("</svg>");
+
+        svg
+    }
+
+    /// Fan chart of the running gain or loss over the horizon as a self-contained svg: the outer band holds the
+    /// 10th to 90th percentiles, the inner band the 25th to 75th and the line is the median. Returns an empty
+    /// string when there are no days to draw.
+    pub(crate) fn get_fan_chart_svg(bands: &[FanBand]) -> String {
+        let Some(last) = bands.last() else {
+            return String::new();
+        };
+
+        // every path starts at no gain or loss on day 0, and the zero line is always in view
+        let low = bands.iter().map(|band| band.p10).fold(0.0, f64::min);
+        let high = bands.iter().map(|band| band.p90).fold(0.0, f64::max);
+        let span = (high - low).max(1.0);
+        let x = |day: u32| day as f64 / last.day as f64 * WIDTH;
+        let y = |value: f64| (high - value) / span * PLOT_HEIGHT;
+
+        let band = |lower: fn(&FanBand) -> f64, upper: fn(&FanBand) -> f64| {
+            let mut points = vec![format!("0.0,{:.1}", y(0.0))];
+            points.extend(
+                bands
+                    .iter()
+                    .map(|band| format!("{:.1},{:.1}", x(band.day), y(upper(band)))),
+            );
+            points.extend(
+                bands
+                    .iter()
+                    .rev()
+                    .map(|band| format!("{:.1},{:.1}", x(band.day), y(lower(band)))),
+            );
+            points.join(" ")
+        };
+        let mut median = vec![format!("0.0,{:.1}", y(0.0))];
+        median.extend(
+            bands
+                .iter()
+                .map(|band| format!("{:.1},{:.1}", x(band.day), y(band.p50))),
+        );
+
+        let mut svg = format!(
+            "<svg class=\"fan-chart\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {WIDTH} {}\" role=\"img\">",
+            PLOT_HEIGHT + AXIS_HEIGHT
+        );
+        svg.push_str(&format!(
+            "<title>Gain or loss on 100 invested over {} days, median {:.1} on the last day</title>",
+            last.day, last.p50
+        ));
+        svg.push_str(&format!(
+            "<polygon class=\"outer\" points=\"{}\"/>",
+            band(|band| band.p10, |band| band.p90)
+        ));
+        svg.push_str(&format!(
+            "<polygon class=\"inner\" points=\"{}\"/>",
+            band(|band| band.p25, |band| band.p75)
+        ));
+        svg.push_str(&format!(
+            "<line class=\"zero\" x1=\"0\" y1=\"{0:.1}\" x2=\"{WIDTH}\" y2=\"{0:.1}\"/>",
+            y(0.0)
+        ));
+        svg.push_str(&format!(
+            "<polyline class=\"median\" points=\"{}\"/>",
+            median.join(" ")
+        ));
+
+        let label_y = PLOT_HEIGHT + 14.0;
+        svg.push_str(&format!(
+            "<text x=\"0\" y=\"{label_y}\" text-anchor=\"start\">day 0</text>\
+            <text x=\"{WIDTH}\" y=\"{label_y}\" text-anchor=\"end\">day {}</text>",
+            last.day
+        ));
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">day {}: 10th {:.0} / 25th {:.0} / 50th {:.0} / 75th {:.0} / 90th {:.0}</text>",
+            WIDTH / 2.0,
+            label_y + 14.0,
+            last.day,
+            last.p10,
+            last.p25,
+            last.p50,
+            last.p75,
+            last.p90
+        ));
+        svg.push_str("</svg>");
+
+        svg
+    }
+
+    /// Histogram of where the realized outcomes fell within their forecast distributions, with a line at the
+    /// height every bar would have if the forecasts were calibrated. Returns an empty string without forecasts.
+    pub(crate) fn get_pit_svg(counts: &[usize]) -> String {
+        let total: usize = counts.iter().sum();
+        if total == 0 {
+            return String::new();
+        }
+        let uniform = total as f64 / counts.len() as f64;
+        let tallest = counts.iter().copied().max().unwrap_or(0) as f64;
+        let scale = PLOT_HEIGHT / tallest.max(uniform);
+        let bar_width = WIDTH / counts.len() as f64;
+
+        let mut svg = format!(
+            "<svg class=\"histogram pit\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {WIDTH} {}\" role=\"img\">",
+            PLOT_HEIGHT + AXIS_HEIGHT
+        );
+        svg.push_str(&format!(
+            "<title>Probability integral transform of {total} forecasts in {} slices</title>",
+            counts.len()
+        ));
+        for (bin, count) in counts.iter().enumerate() {
+            let height = *count as f64 * scale;
+            svg.push_str(&format!(
+                "<rect class=\"bar\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{height:.1}\"/>",
+                bin as f64 * bar_width + 1.0,
+                PLOT_HEIGHT - height,
+                (bar_width - 2.0).max(0.5)
+            ));
+        }
+        svg.push_str(&format!(
+            "<line class=\"uniform\" x1=\"0\" y1=\"{0:.1}\" x2=\"{WIDTH}\" y2=\"{0:.1}\"/>",
+            PLOT_HEIGHT - uniform * scale
+        ));
+
+        let label_y = PLOT_HEIGHT + 14.0;
+        svg.push_str(&format!(
+            "<text x=\"0\" y=\"{label_y}\" text-anchor=\"start\">0</text>\
+            <text x=\"{WIDTH}\" y=\"{label_y}\" text-anchor=\"end\">1</text>\
+            <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{total} forecasts, line is calibrated</text>",
+            WIDTH / 2.0,
+            label_y + 14.0
+        ));
+        svg.push_str("</svg>");
+
+        svg
+    }
+}
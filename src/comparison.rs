@@ -0,0 +1,314 @@
+pub mod run_comparison {
+    use std::path::Path;
+
+    use build_html::{escape_html, Html, HtmlContainer, HtmlPage};
+
+    use crate::{
+        logging::logger::NO_SYMBOL,
+        report::run_report::{get_disclaimer_html, get_header_html, Branding, RunResults},
+        stock_simulation::stock_simulator::{
+            get_highest_x, get_ranks, save_results, MostCommonResult, Rating, SimulationError,
+            Thresholds, TopPredictions,
+        },
+        utilities::util::{csv_line, log},
+    };
+
+    /// How a symbol's place in the top x changed between the runs
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Movement {
+        Entered,
+        Left,
+        Stayed,
+    }
+
+    impl Movement {
+        pub fn name(&self) -> &'static str {
+            match self {
+                Movement::Entered => "entered",
+                Movement::Left => "left",
+                Movement::Stayed => "stayed",
+            }
+        }
+    }
+
+    /// One symbol of either top x and how it moved from the earlier run to the later one
+    #[derive(Debug, PartialEq)]
+    pub struct SymbolChange {
+        pub symbol: String,
+        pub movement: Movement,
+        /// 1 based rank among every symbol of the run, none when the run did not simulate the symbol
+        pub before_rank: Option<usize>,
+        pub after_rank: Option<usize>,
+        /// later metrics minus earlier ones, none unless both runs simulated the symbol
+        pub delta: Option<TopPredictions>,
+    }
+
+    impl SymbolChange {
+        /// places moved up, negative when the symbol fell
+        pub fn rank_move(&self) -> Option<i64> {
+            Some(self.before_rank? as i64 - self.after_rank? as i64)
+        }
+    }
+
+    /// Compares the runs and writes the report, csv when the output ends in .csv and html otherwise
+    pub fn run_comparison(
+        before: &RunResults,
+        after: &RunResults,
+        output: &Path,
+        branding: &Branding,
+    ) -> Result<(), SimulationError> {
+        let changes = get_changes(before, after);
+        let is_csv = output
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let contents = if is_csv {
+            get_comparison_csv(&changes)
+        } else {
+            get_comparison_html(before, after, &changes, branding)
+        };
+        save_results(output, &contents)?;
+
+        let count = |movement: Movement| changes.iter().filter(|c| c.movement == movement).count();
+        log(
+            NO_SYMBOL,
+            format!(
+                "{} entered and {} left the top x",
+                count(Movement::Entered),
+                count(Movement::Left)
+            ),
+        );
+        Ok(())
+    }
+
+    fn get_metrics(results: &RunResults) -> Vec<TopPredictions> {
+        get_highest_x(
+            results.predictions.len(),
+            &results.predictions,
+            Box::new(MostCommonResult {}),
+        )
+    }
+
+    /// Every symbol in the top x of either run, those in the later top x first in its order, then those that
+    /// left in their earlier order
+    pub(crate) fn get_changes(before: &RunResults, after: &RunResults) -> Vec<SymbolChange> {
+        let before_ranks = get_ranks(&before.predictions, Box::new(MostCommonResult {}));
+        let after_ranks = get_ranks(&after.predictions, Box::new(MostCommonResult {}));
+        let before_metrics = get_metrics(before);
+        let after_metrics = get_metrics(after);
+        let in_top = |results: &RunResults, symbol: &str| {
+            results
+                .top_predictions
+                .iter()
+                .any(|top| top.symbol == symbol)
+        };
+
+        let symbols = after
+            .top_predictions
+            .iter()
+            .chain(before.top_predictions.iter())
+            .map(|top| top.symbol.as_str());
+        let mut changes: Vec<SymbolChange> = Vec::new();
+        for symbol in symbols {
+            if changes.iter().any(|change| change.symbol == symbol) {
+                continue;
+            }
+            let movement = match (in_top(before, symbol), in_top(after, symbol)) {
+                (true, true) => Movement::Stayed,
+                (false, _) => Movement::Entered,
+                (true, false) => Movement::Left,
+            };
+            let earlier = before_metrics.iter().find(|m| m.symbol == symbol);
+            let later = after_metrics.iter().find(|m| m.symbol == symbol);
+            let delta = match (earlier, later) {
+                (Some(earlier), Some(later)) => Some(TopPredictions {
+                    symbol: symbol.to_string(),
+                    most_common: later.most_common - earlier.most_common,
+                    highest_low: later.highest_low - earlier.highest_low,
+                    total_span: later.total_span - earlier.total_span,
+                    weighted_span: later.weighted_span - earlier.weighted_span,
+                    simulations: later.simulations,
+                }),
+                _ => None,
+            };
+            changes.push(SymbolChange {
+                symbol: symbol.to_string(),
+                movement,
+                before_rank: before_ranks.get(symbol).copied(),
+                after_rank: after_ranks.get(symbol).copied(),
+                delta,
+            });
+        }
+
+        changes
+    }
+
+    /// Thresholds of the deltas, anchored at zero so any improvement is green, no change yellow and any setback
+    /// red, whatever the other symbols did. A smaller 25th to 75th span is the improvement there.
+    pub(crate) fn get_delta_thresholds() -> Thresholds {
+        Thresholds {
+            most_common_green: 1,
+            most_common_yellow: 0,
+            highest_low_green: 1,
+            highest_low_yellow: 0,
+            total_span_green: -1,
+            total_span_yellow: 0,
+        }
+    }
+
+    fn optional<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    fn signed(value: i64) -> String {
+        if value == 0 {
+            "0".to_string()
+        } else {
+            format!("{value:+}")
+        }
+    }
+
+    /// The text of a cell and the rating it is colored by
+    type Cell = (String, Option<Rating>);
+
+    /// Header and one row per symbol, each delta paired with its rating
+    pub(crate) fn get_comparison_rows(
+        changes: &[SymbolChange],
+    ) -> (Vec<&'static str>, Vec<Vec<Cell>>) {
+        let header = vec![
+            "symbol",
+            "top x",
+            "before rank",
+            "after rank",
+            "rank move",
+            "most common change",
+            "bottom 25th change",
+            "25th to 75th span change",
+            "weighted span change",
+        ];
+
+        let thresholds = get_delta_thresholds();
+        let rows = changes
+            .iter()
+            .map(|change| {
+                let mut row = vec![
+                    (change.symbol.clone(), None),
+                    (change.movement.name().to_string(), None),
+                    (optional(change.before_rank), None),
+                    (optional(change.after_rank), None),
+                    (optional(change.rank_move().map(signed)), None),
+                ];
+                match &change.delta {
+                    Some(delta) => row.extend([
+                        (
+                            signed(delta.most_common as i64),
+                            Some(thresholds.most_common(delta.most_common)),
+                        ),
+                        (
+                            signed(delta.highest_low as i64),
+                            Some(thresholds.highest_low(delta.highest_low)),
+                        ),
+                        (
+                            signed(delta.total_span as i64),
+                            Some(thresholds.total_span(delta.total_span)),
+                        ),
+                        (
+                            signed(delta.weighted_span as i64),
+                            Some(thresholds.weighted_span(delta.weighted_span)),
+                        ),
+                    ]),
+                    None => row.extend(vec![(String::new(), None); 4]),
+                }
+                row
+            })
+            .collect();
+
+        (header, rows)
+    }
+
+    pub(crate) fn get_comparison_csv(changes: &[SymbolChange]) -> String {
+        let (header, rows) = get_comparison_rows(changes);
+        let mut csv = csv_line(&header);
+        for row in rows {
+            let cells: Vec<String> = row.into_iter().map(|(text, _)| text).collect();
+            csv.push_str(&csv_line(&cells));
+        }
+        csv
+    }
+
+    fn describe(results: &RunResults) -> String {
+        let metadata = &results.metadata;
+        format!(
+            "{}: {} symbols, {} days, {} simulations, seed {}",
+            metadata.timestamp,
+            results.predictions.len(),
+            metadata.days,
+            metadata.simulations,
+            metadata.seed
+        )
+    }
+
+    pub(crate) fn get_comparison_html(
+        before: &RunResults,
+        after: &RunResults,
+        changes: &[SymbolChange],
+        branding: &Branding,
+    ) -> String {
+        let (header, rows) = get_comparison_rows(changes);
+        let mut table = String::from("<table class=\"study comparison\"><thead><tr>");
+        for title in header {
+            table.push_str(&format!("<th>{title}</th>"));
+        }
+        table.push_str("</tr></thead><tbody>");
+        for (row, change) in rows.iter().zip(changes) {
+            table.push_str(&format!("<tr class=\"{}\">", change.movement.name()));
+            for (text, rating) in row {
+                match rating {
+                    Some(rating) => table.push_str(&format!(
+                        "<td class=\"{}\">{}</td>",
+                        rating.class(),
+                        escape_html(text)
+                    )),
+                    None => table.push_str(&format!("<td>{}</td>", escape_html(text))),
+                }
+            }
+            table.push_str("</tr>");
+        }
+        table.push_str("</tbody></table>");
+
+        let names = |movement: Movement| {
+            let symbols: Vec<&str> = changes
+                .iter()
+                .filter(|change| change.movement == movement)
+                .map(|change| change.symbol.as_str())
+                .collect();
+            if symbols.is_empty() {
+                "none".to_string()
+            } else {
+                symbols.join(", ")
+            }
+        };
+
+        HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_title("Run Comparison")
+            .with_style(include_str!("style.css"))
+            .with_raw(get_header_html(branding.header.as_deref()))
+            .with_header(1, chrono::Local::now().format("Run Comparison - %B %d, %Y"))
+            .with_paragraph(format!("Before {}", escape_html(&describe(before))))
+            .with_paragraph(format!("After {}", escape_html(&describe(after))))
+            .with_paragraph(format!(
+                "Entered the top {}: {}. Left the top {}: {}.",
+                after.metadata.top_x,
+                escape_html(&names(Movement::Entered)),
+                before.metadata.top_x,
+                escape_html(&names(Movement::Left))
+            ))
+            .with_paragraph(
+                "Ranks are among every symbol of the run. A change is the later value minus the earlier one, \
+                 colored green when it is an improvement, yellow when nothing changed and red when it is a setback.",
+            )
+            .with_raw(table)
+            .with_raw(get_disclaimer_html(branding.disclaimer.as_deref()))
+            .to_html_string()
+    }
+}
@@ -0,0 +1,230 @@
+pub mod run_config {
+    use std::{fs, path::Path, path::PathBuf};
+
+    use serde::{Deserialize, Serialize};
+    use structopt::StructOpt;
+
+    use crate::{
+        monte_carlo::simulations::Sampler, quantiles::distributions::QuantileMethod,
+        report::run_report::OutputFormat, stock_simulation::stock_simulator::Strategy,
+    };
+
+    /// Every option of the simulate subcommand. Each can come from the command line, a profile of the config
+    /// file or the top of the config file, in that order; the required ones must come from one of them.
+    #[derive(Debug, Default, Clone, PartialEq, StructOpt, Serialize, Deserialize)]
+    #[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+    pub struct SimulateConfig {
+        /// input directory, required
+        #[structopt(short, parse(from_os_str))]
+        pub source_dir: Option<PathBuf>,
+        /// output file, required
+        #[structopt(short, parse(from_os_str))]
+        pub output_file: Option<PathBuf>,
+        /// number of days to simulate, required
+        #[structopt(short)]
+        pub days: Option<u32>,
+        /// simulations per symbol, required
+        #[structopt(short)]
+        #[serde(rename = "simulations")]
+        pub number_of_simulations: Option<u32>,
+        /// number of symbols in the report, required
+        #[structopt(short)]
+        pub top_x: Option<usize>,
+        /// log file, required
+        #[structopt(short, parse(from_os_str))]
+        pub log_file: Option<PathBuf>,
+        /// how outcomes are kept for the percentiles: exact (default), sketch or sketch:<k> (KLL sketch, constant memory)
+        #[structopt(long)]
+        #[serde(with = "text")]
+        pub quantiles: Option<QuantileMethod>,
+        /// simulate in batches and stop once no percentile moves more than this between batches, -n becomes the maximum
+        #[structopt(long)]
+        pub adaptive: Option<f64>,
+        /// number of simulations per batch in adaptive mode, 10000 by default
+        #[structopt(long)]
+        pub batch_size: Option<u32>,
+        /// how returns are drawn: independent (default), antithetic or stratified
+        #[structopt(long)]
+        #[serde(with = "text")]
+        pub sampler: Option<Sampler>,
+        /// seed for the random draws, a random seed is picked and reported when left out
+        #[structopt(long)]
+        pub seed: Option<u64>,
+        /// metric the top x is picked by: most-common (default), highest-low, total-span or weighted-span
+        #[structopt(long)]
+        #[serde(with = "text")]
+        pub strategy: Option<Strategy>,
+        /// comma separated output formats: html (default), json, csv, xlsx, markdown, html-table, table. table prints to the terminal. With more than one file format the output file's extension is replaced per format
+        #[structopt(long, use_delimiter = true)]
+        #[serde(with = "text_list")]
+        pub format: Option<Vec<OutputFormat>>,
+        /// stylesheet for the html outputs in place of the built-in look
+        #[structopt(long, parse(from_os_str))]
+        pub css: Option<PathBuf>,
+        /// mustache style template rendered in place of the built-in html index page
+        #[structopt(long, parse(from_os_str))]
+        pub template: Option<PathBuf>,
+        /// company header shown at the top of every report
+        #[structopt(long)]
+        pub header: Option<String>,
+        /// disclaimer shown at the foot of every report
+        #[structopt(long)]
+        pub disclaimer: Option<String>,
+        /// directory every run is also saved to, see the history subcommand
+        #[structopt(long, parse(from_os_str))]
+        pub history: Option<PathBuf>,
+        /// stop the run and mark it failed once more than this many symbols fail
+        #[structopt(long)]
+        pub max_failures: Option<usize>,
+    }
+
+    impl SimulateConfig {
+        /// Every option set here, and from the fallback the ones that are not
+        pub fn or(self, fallback: SimulateConfig) -> SimulateConfig {
+            SimulateConfig {
+                source_dir: self.source_dir.or(fallback.source_dir),
+                output_file: self.output_file.or(fallback.output_file),
+                days: self.days.or(fallback.days),
+                number_of_simulations: self
+                    .number_of_simulations
+                    .or(fallback.number_of_simulations),
+                top_x: self.top_x.or(fallback.top_x),
+                log_file: self.log_file.or(fallback.log_file),
+                quantiles: self.quantiles.or(fallback.quantiles),
+                adaptive: self.adaptive.or(fallback.adaptive),
+                batch_size: self.batch_size.or(fallback.batch_size),
+                sampler: self.sampler.or(fallback.sampler),
+                seed: self.seed.or(fallback.seed),
+                strategy: self.strategy.or(fallback.strategy),
+                format: self.format.or(fallback.format),
+                css: self.css.or(fallback.css),
+                template: self.template.or(fallback.template),
+                header: self.header.or(fallback.header),
+                disclaimer: self.disclaimer.or(fallback.disclaimer),
+                history: self.history.or(fallback.history),
+                max_failures: self.max_failures.or(fallback.max_failures),
+            }
+        }
+
+        /// The required options that are not set, by their config file names
+        pub fn missing(&self) -> Vec<&'static str> {
+            let required = [
+                ("source-dir", self.source_dir.is_none()),
+                ("output-file", self.output_file.is_none()),
+                ("days", self.days.is_none()),
+                ("simulations", self.number_of_simulations.is_none()),
+                ("top-x", self.top_x.is_none()),
+                ("log-file", self.log_file.is_none()),
+            ];
+            required
+                .into_iter()
+                .filter(|(_, missing)| *missing)
+                .map(|(name, _)| name)
+                .collect()
+        }
+
+        /// The options as a config file would hold them
+        pub fn to_toml(&self) -> String {
+            toml::to_string(self).expect("simulate options always serialize")
+        }
+    }
+
+    /// Reads the options at the top of the config file with those of the profile on top of them. Profiles are
+    /// the tables under `[profiles.<name>]`.
+    pub fn read_config(path: &Path, profile: Option<&str>) -> Result<SimulateConfig, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        get_config(&contents, profile).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub(crate) fn get_config(
+        contents: &str,
+        profile: Option<&str>,
+    ) -> Result<SimulateConfig, String> {
+        let mut table: toml::Table = toml::from_str(contents).map_err(|e| e.to_string())?;
+        let profiles = match table.remove("profiles") {
+            None => toml::Table::new(),
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => return Err("profiles must be a table of named profiles".to_string()),
+        };
+        let base: SimulateConfig = table.try_into().map_err(|e| e.to_string())?;
+
+        let Some(name) = profile else {
+            return Ok(base);
+        };
+        let Some(selected) = profiles.get(name) else {
+            let names: Vec<&str> = profiles.keys().map(|name| name.as_str()).collect();
+            return Err(format!(
+                "unknown profile {name}, the file has {}",
+                if names.is_empty() {
+                    "no profiles".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ));
+        };
+        let selected: SimulateConfig = selected
+            .clone()
+            .try_into()
+            .map_err(|e| format!("profile {name}: {e}"))?;
+        Ok(selected.or(base))
+    }
+
+    /// Options written as the text the command line takes, e.g. `sampler = "stratified"`
+    mod text {
+        use std::{fmt::Display, str::FromStr};
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<T: Display, S: Serializer>(
+            value: &Option<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => serializer.collect_str(value),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            T: FromStr<Err = String>,
+            D: Deserializer<'de>,
+        {
+            let text = String::deserialize(deserializer)?;
+            text.parse().map(Some).map_err(serde::de::Error::custom)
+        }
+    }
+
+    /// Lists of options written as the text the command line takes, e.g. `format = ["html", "json"]`
+    mod text_list {
+        use std::{fmt::Display, str::FromStr};
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<T: Display, S: Serializer>(
+            values: &Option<Vec<T>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match values {
+                Some(values) => {
+                    serializer.collect_seq(values.iter().map(|value| value.to_string()))
+                }
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+        where
+            T: FromStr<Err = String>,
+            D: Deserializer<'de>,
+        {
+            let texts = Vec::<String>::deserialize(deserializer)?;
+            texts
+                .iter()
+                .map(|text| text.parse())
+                .collect::<Result<Vec<T>, String>>()
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
@@ -0,0 +1,325 @@
+pub mod study {
+    use std::{
+        collections::{HashMap, HashSet},
+        path::{Path, PathBuf},
+    };
+
+    use build_html::{escape_html, Html, HtmlContainer, HtmlPage, Table};
+
+    use crate::{
+        logging::logger::NO_SYMBOL,
+        monte_carlo::simulations::{monte_carlo_simulation, Prediction, SimulationSettings},
+        report::run_report::{get_disclaimer_html, get_header_html, Branding},
+        stock_simulation::stock_simulator::{
+            get_ranks, read_symbols, save_results, validate_top_x, MostCommonResult,
+            SimulationError,
+        },
+        utilities::util::{csv_line, log, log_warning},
+    };
+
+    /// The predictions of every symbol at one simulation count
+    #[derive(Debug)]
+    pub struct StudyStep {
+        pub simulations: u32,
+        pub predictions: Vec<Prediction>,
+        /// 1 based rank of every symbol under the most common result ordering used for the top x
+        pub ranks: HashMap<String, usize>,
+    }
+
+    /// How much the ranking moved between a simulation count and the one before it
+    #[derive(Debug, PartialEq)]
+    pub struct StepStability {
+        pub rank_correlation: f64,
+        pub top_x_overlap: usize,
+        pub largest_rank_move: usize,
+    }
+
+    /// Runs the symbols in the directory at every simulation count and outputs the study table
+    pub fn run_study(
+        dir: &PathBuf,
+        settings: &SimulationSettings,
+        counts: &[u32],
+        top_x: usize,
+        output: &Path,
+        branding: &Branding,
+    ) -> Result<(), SimulationError> {
+        validate_top_x(top_x)?;
+        let symbols = read_symbols(dir);
+        log(NO_SYMBOL, format!("study of {} symbols", symbols.len()));
+
+        let mut steps = Vec::new();
+        for count in counts {
+            log(NO_SYMBOL, format!("study {count} simulations begin"));
+            let step_settings = SimulationSettings {
+                number_of_simulations: *count,
+                ..*settings
+            };
+            let predictions: Vec<Prediction> = symbols
+                .iter()
+                .filter_map(|(symbol, data)| {
+                    match monte_carlo_simulation(symbol.clone(), data, &step_settings) {
+                        Ok(prediction) => Some(prediction),
+                        Err(e) => {
+                            log_warning(symbol, e);
+                            None
+                        }
+                    }
+                })
+                .collect();
+            let ranks = get_ranks(&predictions, Box::new(MostCommonResult {}));
+            steps.push(StudyStep {
+                simulations: *count,
+                predictions,
+                ranks,
+            });
+            log(NO_SYMBOL, format!("study {count} simulations end"));
+        }
+
+        let is_csv = output
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        let contents = if is_csv {
+            get_study_csv(&steps, top_x)
+        } else {
+            get_study_html(&steps, top_x, branding)
+        };
+        save_results(output, &contents)?;
+
+        log(NO_SYMBOL, get_stability_text(&steps, top_x));
+        Ok(())
+    }
+
+    /// Whether the top x settled, with its own message when no symbol was simulated at any count
+    pub(crate) fn get_stability_text(steps: &[StudyStep], top_x: usize) -> String {
+        if steps.iter().all(|step| step.predictions.is_empty()) {
+            return format!("No symbol was simulated, so there is no top {top_x} to compare.");
+        }
+        match get_stable_count(steps, top_x) {
+            Some(count) => format!("The top {top_x} does not change from {count} simulations on."),
+            None => format!("The top {top_x} changed between the two largest simulation counts."),
+        }
+    }
+
+    pub(crate) fn get_top_x(ranks: &HashMap<String, usize>, top_x: usize) -> HashSet<&str> {
+        ranks
+            .iter()
+            .filter(|(_, rank)| **rank <= top_x)
+            .map(|(symbol, _)| symbol.as_str())
+            .collect()
+    }
+
+    pub(crate) fn get_stability(
+        previous: &StudyStep,
+        current: &StudyStep,
+        top_x: usize,
+    ) -> StepStability {
+        let previous_top = get_top_x(&previous.ranks, top_x);
+        let current_top = get_top_x(&current.ranks, top_x);
+
+        let largest_rank_move = current
+            .ranks
+            .iter()
+            .filter_map(|(symbol, rank)| previous.ranks.get(symbol).map(|p| p.abs_diff(*rank)))
+            .max()
+            .unwrap_or(0);
+
+        StepStability {
+            rank_correlation: get_rank_correlation(&previous.ranks, &current.ranks),
+            top_x_overlap: previous_top.intersection(&current_top).count(),
+            largest_rank_move,
+        }
+    }
+
+    /// Spearman rank correlation over the symbols ranked in both, 1 means the order did not change
+    pub(crate) fn get_rank_correlation(
+        left: &HashMap<String, usize>,
+        right: &HashMap<String, usize>,
+    ) -> f64 {
+        let pairs: Vec<(usize, usize)> = left
+            .iter()
+            .filter_map(|(symbol, rank)| right.get(symbol).map(|other| (*rank, *other)))
+            .collect();
+        let n = pairs.len() as f64;
+        if pairs.len() < 2 {
+            return 1.0;
+        }
+
+        let squared_differences: f64 = pairs
+            .iter()
+            .map(|(l, r)| (*l as f64 - *r as f64).powi(2))
+            .sum();
+        1.0 - (6.0 * squared_differences) / (n * (n * n - 1.0))
+    }
+
+    /// The simulation counts of a study smallest first and each once, the stability of the top x is read from
+    /// the largest count down
+    pub(crate) fn get_study_counts(counts: &[u32]) -> Vec<u32> {
+        let mut counts = counts.to_vec();
+        counts.sort_unstable();
+        counts.dedup();
+        counts
+    }
+
+    /// The smallest simulation count from which the top x no longer changes in any larger count of the study
+    pub(crate) fn get_stable_count(steps: &[StudyStep], top_x: usize) -> Option<u32> {
+        let last = steps.last()?;
+        let final_top = get_top_x(&last.ranks, top_x);
+
+        let mut stable = last.simulations;
+        for step in steps.iter().rev().skip(1) {
+            if get_top_x(&step.ranks, top_x) != final_top {
+                break;
+            }
+            stable = step.simulations;
+        }
+
+        if steps.len() > 1 && stable == last.simulations {
+            return None;
+        }
+        Some(stable)
+    }
+
+    /// Largest rank move and largest percentile shift of one symbol between consecutive counts
+    pub(crate) fn get_symbol_shifts(steps: &[StudyStep], symbol: &str) -> (usize, i32) {
+        let mut largest_rank_move = 0;
+        let mut largest_percentile_shift = 0;
+        for pair in steps.windows(2) {
+            if let (Some(before), Some(after)) =
+                (pair[0].ranks.get(symbol), pair[1].ranks.get(symbol))
+            {
+                largest_rank_move = largest_rank_move.max(before.abs_diff(*after));
+            }
+            let before = pair[0].predictions.iter().find(|p| p.symbol == symbol);
+            let after = pair[1].predictions.iter().find(|p| p.symbol == symbol);
+            if let (Some(before), Some(after)) = (before, after) {
+                let shift = [
+                    before.percentiles._25th - after.percentiles._25th,
+                    before.percentiles._50th - after.percentiles._50th,
+                    before.percentiles._75th - after.percentiles._75th,
+                ]
+                .iter()
+                .map(|change| change.abs())
+                .max()
+                .unwrap_or(0);
+                largest_percentile_shift = largest_percentile_shift.max(shift);
+            }
+        }
+
+        (largest_rank_move, largest_percentile_shift)
+    }
+
+    /// Header, one row per symbol ordered by its rank at the largest count, and the footer of stability rows
+    pub(crate) fn get_study_rows(
+        steps: &[StudyStep],
+        top_x: usize,
+    ) -> (Vec<String>, Vec<Vec<String>>, Vec<Vec<String>>) {
+        let mut header = vec!["symbol".to_string()];
+        for step in steps {
+            let n = step.simulations;
+            header.push(format!("{n} 25th"));
+            header.push(format!("{n} 50th"));
+            header.push(format!("{n} 75th"));
+            header.push(format!("{n} rank"));
+        }
+        header.push("largest rank move".to_string());
+        header.push("largest percentile shift".to_string());
+
+        let mut symbols: Vec<(&String, usize)> = match steps.last() {
+            Some(last) => last.ranks.iter().map(|(s, r)| (s, *r)).collect(),
+            None => Vec::new(),
+        };
+        symbols.sort_by_key(|(symbol, rank)| (*rank, symbol.to_string()));
+
+        let mut body = Vec::new();
+        for (symbol, _) in symbols {
+            let mut row = vec![symbol.clone()];
+            for step in steps {
+                match step.predictions.iter().find(|p| &p.symbol == symbol) {
+                    Some(prediction) => {
+                        row.push(prediction.percentiles._25th.to_string());
+                        row.push(prediction.percentiles._50th.to_string());
+                        row.push(prediction.percentiles._75th.to_string());
+                        row.push(step.ranks[symbol].to_string());
+                    }
+                    None => row.extend(vec![String::new(); 4]),
+                }
+            }
+            let (rank_move, percentile_shift) = get_symbol_shifts(steps, symbol);
+            row.push(rank_move.to_string());
+            row.push(percentile_shift.to_string());
+            body.push(row);
+        }
+
+        let stable_count = get_stable_count(steps, top_x);
+        let mut correlation = vec!["rank correlation".to_string()];
+        let mut overlap = vec![format!("top {top_x} overlap")];
+        let mut rank_move = vec!["largest rank move".to_string()];
+        let mut stable = vec![format!("top {top_x} stable")];
+        for (index, step) in steps.iter().enumerate() {
+            // the statistics sit in the rank column of their simulation count
+            for row in [&mut correlation, &mut overlap, &mut rank_move, &mut stable] {
+                row.extend(vec![String::new(); 3]);
+            }
+            match index.checked_sub(1).map(|previous| &steps[previous]) {
+                Some(previous) => {
+                    let stability = get_stability(previous, step, top_x);
+                    correlation.push(format!("{:.4}", stability.rank_correlation));
+                    overlap.push(format!("{}/{}", stability.top_x_overlap, top_x));
+                    rank_move.push(stability.largest_rank_move.to_string());
+                }
+                None => {
+                    correlation.push(String::new());
+                    overlap.push(String::new());
+                    rank_move.push(String::new());
+                }
+            }
+            let is_stable = stable_count.is_some_and(|count| step.simulations >= count);
+            stable.push(if is_stable { "yes" } else { "no" }.to_string());
+        }
+        for row in [&mut correlation, &mut overlap, &mut rank_move, &mut stable] {
+            row.extend(vec![String::new(); 2]);
+        }
+        let footer = vec![correlation, overlap, rank_move, stable];
+
+        (header, body, footer)
+    }
+
+    pub(crate) fn get_study_csv(steps: &[StudyStep], top_x: usize) -> String {
+        let (header, body, footer) = get_study_rows(steps, top_x);
+        let mut csv = String::new();
+        for row in std::iter::once(&header)
+            .chain(body.iter())
+            .chain(footer.iter())
+        {
+            csv.push_str(&csv_line(row));
+        }
+        csv
+    }
+
+    pub(crate) fn get_study_html(steps: &[StudyStep], top_x: usize, branding: &Branding) -> String {
+        let (header, body, footer) = get_study_rows(steps, top_x);
+        let mut table = Table::new()
+            .with_attributes(vec![("class", "study")])
+            .with_header_row(header);
+        for row in body {
+            table.add_body_row(row.iter().map(|cell| escape_html(cell)));
+        }
+        for row in footer {
+            table.add_footer_row(row);
+        }
+
+        HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_title("Convergence Study")
+            .with_style(include_str!("style.css"))
+            .with_raw(get_header_html(branding.header.as_deref()))
+            .with_header(
+                1,
+                chrono::Local::now().format("Convergence Study - %B %d, %Y"),
+            )
+            .with_paragraph(get_stability_text(steps, top_x))
+            .with_table(table)
+            .with_raw(get_disclaimer_html(branding.disclaimer.as_deref()))
+            .to_html_string()
+    }
+}
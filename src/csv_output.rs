@@ -0,0 +1,48 @@
+pub mod csv_report {
+    use crate::{
+        monte_carlo::simulations::Prediction, report::run_report::get_symbol_rows,
+        utilities::util::csv_line,
+    };
+
+    const HEADER: [&str; 14] = [
+        "symbol",
+        "observations",
+        "simulations",
+        "25th",
+        "50th",
+        "75th",
+        "most_common",
+        "highest_low",
+        "total_span",
+        "weighted_span",
+        "most_common_rank",
+        "highest_low_rank",
+        "total_span_rank",
+        "weighted_span_rank",
+    ];
+
+    /// One row per simulated symbol in most common result order, with its rank under every ordering
+    pub(crate) fn get_csv(predictions: &Vec<Prediction>) -> String {
+        let mut csv = csv_line(&HEADER);
+        for row in get_symbol_rows(predictions) {
+            let cells = [
+                row.symbol,
+                row.observations.to_string(),
+                row.simulations.to_string(),
+                row.percentiles._25th.to_string(),
+                row.percentiles._50th.to_string(),
+                row.percentiles._75th.to_string(),
+                row.most_common.to_string(),
+                row.highest_low.to_string(),
+                row.total_span.to_string(),
+                row.weighted_span.to_string(),
+                row.most_common_rank.to_string(),
+                row.highest_low_rank.to_string(),
+                row.total_span_rank.to_string(),
+                row.weighted_span_rank.to_string(),
+            ];
+            csv.push_str(&csv_line(&cells));
+        }
+        csv
+    }
+}
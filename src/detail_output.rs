@@ -0,0 +1,158 @@
+pub mod detail_report {
+    use build_html::{escape_html, Container, ContainerType, Html, HtmlContainer, HtmlPage, Table};
+
+    use crate::{
+        charts::svg_charts::{get_fan_chart_svg, get_histogram_svg},
+        monte_carlo::simulations::{get_percentile_table, Prediction},
+        report::run_report::{
+            get_disclaimer_html, get_header_html, PredictionView, RatedValue, RunMetadata,
+        },
+    };
+
+    /// Every fifth percent from the 5th to the 95th
+    const PERCENTS: [u32; 19] = [
+        5, 10, 15, 20, 25, 30, 35, 40, 45, 50, 55, 60, 65, 70, 75, 80, 85, 90, 95,
+    ];
+
+    fn get_rated_cell(rated: &RatedValue) -> String {
+        format!(
+            "<span class=\"{}\">{}</span>",
+            rated.rating.class(),
+            rated.value
+        )
+    }
+
+    fn get_percent(fraction: f64) -> String {
+        format!("{:.2}%", fraction * 100.0)
+    }
+
+    fn get_section(title: &str, rows: Vec<(String, String)>) -> Container {
+        let mut table = Table::new().with_attributes(vec![("class", "details")]);
+        for (name, value) in rows {
+            table.add_body_row([name, value]);
+        }
+        Container::new(ContainerType::Div)
+            .with_attributes(vec![("class", "detail-section")])
+            .with_header(2, title)
+            .with_table(table)
+    }
+
+    /// The page behind one card of the index: the prediction, the charts, the full percentile table, drawdowns,
+    /// the historical returns and the run parameters. `index` is the link back to the index page.
+    pub(crate) fn get_detail_html(
+        view: &PredictionView,
+        prediction: &Prediction,
+        metadata: &RunMetadata,
+        index: &str,
+        style: &str,
+    ) -> String {
+        let summary = get_section(
+            "Prediction",
+            vec![
+                (
+                    "rank".to_string(),
+                    format!("{} of {}", view.rank, metadata.top_x),
+                ),
+                (
+                    "most common result".to_string(),
+                    get_rated_cell(&view.most_common),
+                ),
+                ("bottom 25th".to_string(), get_rated_cell(&view.highest_low)),
+                (
+                    "25th to 75th span".to_string(),
+                    get_rated_cell(&view.total_span),
+                ),
+                (
+                    "weighted span".to_string(),
+                    get_rated_cell(&view.weighted_span),
+                ),
+                ("simulations".to_string(), view.simulations.to_string()),
+            ],
+        );
+
+        let mut charts =
+            Container::new(ContainerType::Div).with_attributes(vec![("class", "charts")]);
+        charts.add_html(get_histogram_svg(&prediction.data, &prediction.percentiles));
+        charts.add_html(get_fan_chart_svg(&prediction.fan_chart));
+
+        let mut percentile_rows = Vec::new();
+        if let (Some(min), Some(max)) = (
+            prediction.data.keys().next(),
+            prediction.data.keys().next_back(),
+        ) {
+            percentile_rows.push(("lowest".to_string(), min.to_string()));
+            for (percent, outcome) in get_percentile_table(&prediction.data, &PERCENTS) {
+                percentile_rows.push((format!("{percent}th"), outcome.to_string()));
+            }
+            percentile_rows.push(("highest".to_string(), max.to_string()));
+        }
+        let percentiles = get_section("Percentiles of the Gain or Loss on 100", percentile_rows);
+
+        let drawdown = &prediction.drawdown;
+        let drawdowns = get_section(
+            "Largest Drawdown per Path",
+            vec![
+                ("median".to_string(), format!("{:.2}%", drawdown.p50)),
+                ("75th".to_string(), format!("{:.2}%", drawdown.p75)),
+                ("90th".to_string(), format!("{:.2}%", drawdown.p90)),
+                ("worst".to_string(), format!("{:.2}%", drawdown.worst)),
+            ],
+        );
+
+        let input = &prediction.input;
+        let returns = get_section(
+            "Historical Daily Returns",
+            vec![
+                (
+                    "observations".to_string(),
+                    prediction.observations.to_string(),
+                ),
+                ("mean".to_string(), get_percent(input.mean)),
+                ("standard deviation".to_string(), get_percent(input.std_dev)),
+                ("lowest".to_string(), get_percent(input.min)),
+                ("median".to_string(), get_percent(input.median)),
+                ("highest".to_string(), get_percent(input.max)),
+                ("up days".to_string(), get_percent(input.positive)),
+            ],
+        );
+
+        let parameters = get_section(
+            "Run Parameters",
+            metadata
+                .parameters()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), escape_html(&value)))
+                .collect(),
+        );
+
+        let mut notes = Container::new(ContainerType::UnorderedList)
+            .with_attributes(vec![("class", "detail-notes")]);
+        for note in &view.notes {
+            notes.add_html(note.as_str());
+        }
+
+        HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_meta(vec![
+                ("name", "viewport"),
+                ("content", "width=device-width, initial-scale=1.0"),
+            ])
+            .with_title(format!("{} - Stock Predictions", escape_html(&view.symbol)))
+            .with_style(style)
+            .with_raw(get_header_html(metadata.header.as_deref()))
+            .with_raw(format!(
+                "<a class=\"back\" href=\"{}\">&larr; all predictions</a>",
+                escape_html(index)
+            ))
+            .with_header(1, escape_html(&view.symbol))
+            .with_container(summary)
+            .with_container(notes)
+            .with_container(charts)
+            .with_container(percentiles)
+            .with_container(drawdowns)
+            .with_container(returns)
+            .with_container(parameters)
+            .with_raw(get_disclaimer_html(metadata.disclaimer.as_deref()))
+            .to_html_string()
+    }
+}
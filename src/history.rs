@@ -0,0 +1,199 @@
+pub mod run_history {
+    use std::{
+        error::Error,
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use chrono::DateTime;
+
+    use crate::{
+        logging::logger::NO_SYMBOL,
+        monte_carlo::simulations::Percentiles,
+        report::run_report::RunResults,
+        stock_simulation::stock_simulator::{get_ranks, MostCommonResult, SimulationError},
+        utilities::util::{log_warning, text_table},
+    };
+
+    /// One stored run as the list shows it
+    #[derive(Debug, PartialEq)]
+    pub struct RunEntry {
+        pub id: String,
+        /// RFC 3339 time the results were written
+        pub timestamp: String,
+        pub symbols: usize,
+        pub days: u32,
+        pub simulations: u32,
+        /// the top x in ranked order
+        pub top: Vec<String>,
+    }
+
+    /// What one stored run predicted for a symbol
+    #[derive(Debug)]
+    pub struct SymbolForecast {
+        pub id: String,
+        pub timestamp: String,
+        pub days: u32,
+        pub simulations: u32,
+        pub percentiles: Percentiles,
+        /// 1 based rank among every symbol of the run under the most common result ordering
+        pub rank: usize,
+        pub symbols: usize,
+    }
+
+    /// Id of a run written at the RFC 3339 timestamp, e.g. 20250106-173005, runs sort by id in time order
+    pub(crate) fn get_run_id(timestamp: &str) -> Result<String, Box<dyn Error>> {
+        let time = DateTime::parse_from_rfc3339(timestamp)?;
+        Ok(time.format("%Y%m%d-%H%M%S").to_string())
+    }
+
+    fn get_run_path(store: &Path, id: &str) -> PathBuf {
+        store.join(format!("{id}.json"))
+    }
+
+    /// Saves the results in the store as `<run id>.json`, a second run in the same second gets a numbered id
+    pub(crate) fn save_run(store: &Path, results: &RunResults) -> Result<String, Box<dyn Error>> {
+        fs::create_dir_all(store)?;
+        let base = get_run_id(&results.metadata.timestamp)?;
+        let mut id = base.clone();
+        let mut number = 1;
+        while get_run_path(store, &id).exists() {
+            number += 1;
+            id = format!("{base}-{number}");
+        }
+        fs::write(
+            get_run_path(store, &id),
+            serde_json::to_string(results).expect("run results always serialize"),
+        )?;
+        Ok(id)
+    }
+
+    pub(crate) fn load_run(store: &Path, id: &str) -> Result<RunResults, SimulationError> {
+        let json = fs::read_to_string(get_run_path(store, id))
+            .map_err(|e| SimulationError::Io(format!("run {id}: {e}")))?;
+        serde_json::from_str(&json).map_err(|e| SimulationError::Parse(format!("run {id}: {e}")))
+    }
+
+    /// Every run in the store oldest first, with the id it was saved under. Files that are not runs are skipped.
+    pub(crate) fn load_runs(store: &Path) -> Result<Vec<(String, RunResults)>, SimulationError> {
+        let mut ids: Vec<String> = fs::read_dir(store)
+            .map_err(|e| SimulationError::Io(format!("{}: {e}", store.display())))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect();
+        ids.sort();
+
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| match load_run(store, &id) {
+                Ok(results) => Some((id, results)),
+                Err(e) => {
+                    log_warning(NO_SYMBOL, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// The runs saved on the date, YYYY-MM-DD, or every run without one
+    pub(crate) fn get_run_entries(
+        runs: &[(String, RunResults)],
+        date: Option<&str>,
+    ) -> Vec<RunEntry> {
+        runs.iter()
+            .filter(|(_, results)| {
+                date.is_none_or(|date| results.metadata.timestamp.starts_with(date))
+            })
+            .map(|(id, results)| RunEntry {
+                id: id.clone(),
+                timestamp: results.metadata.timestamp.clone(),
+                symbols: results.predictions.len(),
+                days: results.metadata.days,
+                simulations: results.metadata.simulations,
+                top: results
+                    .top_predictions
+                    .iter()
+                    .map(|top| top.symbol.clone())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// What every run predicted for the symbol, oldest first
+    pub(crate) fn get_symbol_forecasts(
+        runs: &[(String, RunResults)],
+        symbol: &str,
+    ) -> Vec<SymbolForecast> {
+        runs.iter()
+            .filter_map(|(id, results)| {
+                let prediction = results.predictions.iter().find(|p| p.symbol == symbol)?;
+                let ranks = get_ranks(&results.predictions, Box::new(MostCommonResult {}));
+                Some(SymbolForecast {
+                    id: id.clone(),
+                    timestamp: results.metadata.timestamp.clone(),
+                    days: results.metadata.days,
+                    simulations: prediction.simulations,
+                    percentiles: prediction.percentiles.clone(),
+                    rank: ranks[symbol],
+                    symbols: results.predictions.len(),
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn get_run_list(entries: &[RunEntry]) -> String {
+        let rows: Vec<Vec<String>> = entries
+            .iter()
+            .map(|entry| {
+                vec![
+                    entry.id.clone(),
+                    entry.timestamp.clone(),
+                    entry.symbols.to_string(),
+                    entry.days.to_string(),
+                    entry.simulations.to_string(),
+                    entry.top.join(" "),
+                ]
+            })
+            .collect();
+        text_table(
+            &["run", "saved", "symbols", "days", "simulations", "top x"],
+            &rows,
+        )
+    }
+
+    pub(crate) fn get_symbol_history(forecasts: &[SymbolForecast]) -> String {
+        let rows: Vec<Vec<String>> = forecasts
+            .iter()
+            .map(|forecast| {
+                vec![
+                    forecast.id.clone(),
+                    forecast.timestamp.clone(),
+                    forecast.days.to_string(),
+                    forecast.simulations.to_string(),
+                    forecast.percentiles._25th.to_string(),
+                    forecast.percentiles._50th.to_string(),
+                    forecast.percentiles._75th.to_string(),
+                    format!("{} of {}", forecast.rank, forecast.symbols),
+                ]
+            })
+            .collect();
+        text_table(
+            &[
+                "run",
+                "saved",
+                "days",
+                "simulations",
+                "25th",
+                "50th",
+                "75th",
+                "rank",
+            ],
+            &rows,
+        )
+    }
+}
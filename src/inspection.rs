@@ -0,0 +1,171 @@
+pub mod input_inspection {
+    use std::{fs, path::Path};
+
+    use crate::{
+        monte_carlo::simulations::{get_input_stats, InputStats},
+        stock_simulation::stock_simulator::{get_returns, SimulationError},
+        utilities::util::text_table,
+    };
+
+    /// A daily move larger than this either way is reported as suspicious
+    const LARGEST_PLAUSIBLE_RETURN: f64 = 0.5;
+
+    /// What validate found in one input file
+    #[derive(Debug, PartialEq)]
+    pub struct FileCheck {
+        pub symbol: String,
+        pub observations: usize,
+        /// empty when the file is fine
+        pub problems: Vec<String>,
+    }
+
+    /// Everything about the returns that would make the simulations of the symbol misleading
+    pub(crate) fn get_data_problems(data: &[f64], min_observations: usize) -> Vec<String> {
+        let mut problems = Vec::new();
+        if data.len() < min_observations {
+            problems.push(format!(
+                "only {} observations, at least {min_observations} expected",
+                data.len()
+            ));
+        }
+        for (index, value) in data.iter().enumerate() {
+            let item = index + 1;
+            if !value.is_finite() {
+                problems.push(format!("item {item}: {value} is not a number"));
+            } else if *value <= -1.0 {
+                problems.push(format!(
+                    "item {item}: {value} loses everything or more in a day"
+                ));
+            } else if value.abs() > LARGEST_PLAUSIBLE_RETURN {
+                problems.push(format!(
+                    "item {item}: {value} moves more than {:.0}% in a day, percentages instead of fractions?",
+                    LARGEST_PLAUSIBLE_RETURN * 100.0
+                ));
+            }
+        }
+        if data.len() > 1 && data.iter().all(|value| *value == data[0]) {
+            problems.push("every return is the same".to_string());
+        }
+        problems
+    }
+
+    /// Reads every file in the directory the way simulate would and lists what is wrong with each, without
+    /// simulating or moving anything to the archive
+    pub(crate) fn check_dir(
+        dir: &Path,
+        min_observations: usize,
+    ) -> Result<Vec<FileCheck>, SimulationError> {
+        let io_error = |e: std::io::Error| SimulationError::Io(format!("{}: {e}", dir.display()));
+        let mut checks = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.is_dir() {
+                continue;
+            }
+            let symbol = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            checks.push(check_file(
+                &symbol,
+                fs::read_to_string(&path),
+                min_observations,
+            ));
+        }
+        checks.sort_by(|left, right| left.symbol.cmp(&right.symbol));
+        Ok(checks)
+    }
+
+    pub(crate) fn check_file(
+        symbol: &str,
+        content: std::io::Result<String>,
+        min_observations: usize,
+    ) -> FileCheck {
+        let parsed = content
+            .map_err(|e| e.to_string())
+            .and_then(|content| get_returns(&content));
+        match parsed {
+            Ok(data) => FileCheck {
+                symbol: symbol.to_string(),
+                observations: data.len(),
+                problems: get_data_problems(&data, min_observations),
+            },
+            Err(e) => FileCheck {
+                symbol: symbol.to_string(),
+                observations: 0,
+                problems: vec![e],
+            },
+        }
+    }
+
+    /// One line per problem and a closing count, files without problems are only counted
+    pub(crate) fn get_validation_text(checks: &[FileCheck]) -> String {
+        let rows: Vec<Vec<String>> = checks
+            .iter()
+            .flat_map(|check| {
+                check.problems.iter().map(|problem| {
+                    vec![
+                        check.symbol.clone(),
+                        check.observations.to_string(),
+                        problem.clone(),
+                    ]
+                })
+            })
+            .collect();
+        let failed = checks
+            .iter()
+            .filter(|check| !check.problems.is_empty())
+            .count();
+
+        let mut text = String::new();
+        if !rows.is_empty() {
+            text.push_str(&text_table(&["symbol", "observations", "problem"], &rows));
+            text.push('\n');
+        }
+        text.push_str(&format!(
+            "{} files checked, {failed} with problems\n",
+            checks.len()
+        ));
+        text
+    }
+
+    fn get_percent(fraction: f64) -> String {
+        format!("{:.2}%", fraction * 100.0)
+    }
+
+    /// Summary statistics of one symbol's returns and anything validate would report about them
+    pub(crate) fn get_inspection_text(
+        symbol: &str,
+        data: &[f64],
+        min_observations: usize,
+    ) -> String {
+        let InputStats {
+            mean,
+            std_dev,
+            min,
+            median,
+            max,
+            positive,
+        } = get_input_stats(data);
+        let rows = vec![
+            vec!["observations".to_string(), data.len().to_string()],
+            vec!["mean".to_string(), get_percent(mean)],
+            vec!["standard deviation".to_string(), get_percent(std_dev)],
+            vec!["lowest".to_string(), get_percent(min)],
+            vec!["median".to_string(), get_percent(median)],
+            vec!["highest".to_string(), get_percent(max)],
+            vec!["up days".to_string(), get_percent(positive)],
+        ];
+
+        let mut text = format!("{symbol}\n\n");
+        text.push_str(&text_table(&["daily returns", "value"], &rows));
+        let problems = get_data_problems(data, min_observations);
+        if !problems.is_empty() {
+            text.push_str("\nproblems\n");
+            for problem in problems {
+                text.push_str(&format!("- {problem}\n"));
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,103 @@
+document.addEventListener("DOMContentLoaded", function () {
+    var table = document.getElementById("results");
+    var body = table.tBodies[0];
+    var rows = Array.prototype.slice.call(body.rows);
+    var headers = Array.prototype.slice.call(table.tHead.rows[0].cells);
+    var search = document.getElementById("search");
+    var filter = document.getElementById("filter");
+    var status = document.getElementById("status");
+    var sorted = { column: -1, ascending: true };
+
+    function cellValue(row, column) {
+        var cell = row.cells[column];
+        var value = cell.getAttribute("data-value");
+        return value === null ? cell.textContent : Number(value);
+    }
+
+    function sortBy(column) {
+        sorted.ascending = sorted.column === column ? !sorted.ascending : true;
+        sorted.column = column;
+        rows.sort(function (left, right) {
+            var a = cellValue(left, column);
+            var b = cellValue(right, column);
+            var order = a < b ? -1 : a > b ? 1 : 0;
+            return sorted.ascending ? order : -order;
+        });
+        rows.forEach(function (row) {
+            body.appendChild(row);
+        });
+        headers.forEach(function (header, index) {
+            header.classList.remove("ascending", "descending");
+            if (index === column) {
+                header.classList.add(sorted.ascending ? "ascending" : "descending");
+            }
+        });
+    }
+
+    // a filter is a comma separated list of conditions such as "25th > 0" or "total_span <= 20"
+    function parseFilter(text) {
+        var conditions = [];
+        var parts = text.split(",");
+        for (var i = 0; i < parts.length; i++) {
+            var part = parts[i].trim();
+            if (part === "") {
+                continue;
+            }
+            var match = /^([\w ]+?)\s*(>=|<=|!=|=|>|<)\s*(-?\d+(\.\d+)?)$/.exec(part);
+            if (match === null) {
+                return { error: "cannot read \"" + part + "\", expected <column> <operator> <number>" };
+            }
+            var name = match[1].trim().replace(/ /g, "_");
+            var column = headers.findIndex(function (header) {
+                return header.getAttribute("data-key") === name;
+            });
+            if (column < 0) {
+                return { error: "unknown column \"" + match[1].trim() + "\"" };
+            }
+            conditions.push({ column: column, operator: match[2], value: Number(match[3]) });
+        }
+        return { conditions: conditions };
+    }
+
+    function holds(row, condition) {
+        var value = cellValue(row, condition.column);
+        switch (condition.operator) {
+            case ">": return value > condition.value;
+            case ">=": return value >= condition.value;
+            case "<": return value < condition.value;
+            case "<=": return value <= condition.value;
+            case "=": return value === condition.value;
+            default: return value !== condition.value;
+        }
+    }
+
+    function apply() {
+        var term = search.value.trim().toUpperCase();
+        var parsed = parseFilter(filter.value);
+        filter.classList.toggle("invalid", parsed.error !== undefined);
+        var conditions = parsed.conditions || [];
+        var shown = 0;
+        rows.forEach(function (row) {
+            var visible = row.cells[0].textContent.toUpperCase().indexOf(term) >= 0
+                && conditions.every(function (condition) {
+                    return holds(row, condition);
+                });
+            row.hidden = !visible;
+            if (visible) {
+                shown++;
+            }
+        });
+        status.textContent = parsed.error !== undefined
+            ? parsed.error
+            : "showing " + shown + " of " + rows.length + " symbols";
+    }
+
+    headers.forEach(function (header, index) {
+        header.addEventListener("click", function () {
+            sortBy(index);
+        });
+    });
+    search.addEventListener("input", apply);
+    filter.addEventListener("input", apply);
+    apply();
+});
@@ -0,0 +1,133 @@
+pub mod interactive_report {
+    use build_html::{escape_html, Html, HtmlContainer, HtmlPage};
+
+    use crate::{
+        report::run_report::{
+            get_disclaimer_html, get_header_html, get_summary_html, get_symbol_rows, RunResults,
+        },
+        stock_simulation::stock_simulator::{
+            get_highest_x, get_thresholds, MostCommonResult, Rating,
+        },
+    };
+
+    /// Column keys, also the names the filter box accepts with spaces or underscores
+    const COLUMNS: [&str; 14] = [
+        "symbol",
+        "observations",
+        "simulations",
+        "25th",
+        "50th",
+        "75th",
+        "most_common",
+        "highest_low",
+        "total_span",
+        "weighted_span",
+        "most_common_rank",
+        "highest_low_rank",
+        "total_span_rank",
+        "weighted_span_rank",
+    ];
+
+    fn get_cell(value: i64, rating: Option<Rating>) -> String {
+        match rating {
+            Some(rating) => format!(
+                "<td class=\"{}\" data-value=\"{value}\">{value}</td>",
+                rating.class()
+            ),
+            None => format!("<td data-value=\"{value}\">{value}</td>"),
+        }
+    }
+
+    /// One table of every simulated symbol that sorts on any column, searches by symbol and filters on
+    /// conditions such as "25th > 0", all with inline script so the page works offline. The metrics are rated
+    /// against every symbol rather than the top x.
+    pub(crate) fn get_interactive_html(results: &RunResults, style: &str) -> String {
+        let metadata = &results.metadata;
+        let all = get_highest_x(
+            results.predictions.len(),
+            &results.predictions,
+            Box::new(MostCommonResult {}),
+        );
+        let thresholds = get_thresholds(&all);
+
+        let mut table = String::from("<table id=\"results\" class=\"results\"><thead><tr>");
+        for key in COLUMNS {
+            table.push_str(&format!(
+                "<th data-key=\"{key}\">{}</th>",
+                key.replace('_', " ")
+            ));
+        }
+        table.push_str("</tr></thead><tbody>");
+        for row in get_symbol_rows(&results.predictions) {
+            table.push_str(&format!("<tr><td>{}</td>", escape_html(&row.symbol)));
+            let cells = [
+                (row.observations as i64, None),
+                (row.simulations as i64, None),
+                (row.percentiles._25th as i64, None),
+                (row.percentiles._50th as i64, None),
+                (row.percentiles._75th as i64, None),
+                (
+                    row.most_common as i64,
+                    Some(thresholds.most_common(row.most_common)),
+                ),
+                (
+                    row.highest_low as i64,
+                    Some(thresholds.highest_low(row.highest_low)),
+                ),
+                (
+                    row.total_span as i64,
+                    Some(thresholds.total_span(row.total_span)),
+                ),
+                (
+                    row.weighted_span as i64,
+                    Some(thresholds.weighted_span(row.weighted_span)),
+                ),
+                (row.most_common_rank as i64, None),
+                (row.highest_low_rank as i64, None),
+                (row.total_span_rank as i64, None),
+                (row.weighted_span_rank as i64, None),
+            ];
+            for (value, rating) in cells {
+                table.push_str(&get_cell(value, rating));
+            }
+            table.push_str("</tr>");
+        }
+        table.push_str("</tbody></table>");
+
+        let controls = "<div class=\"controls\">\
+            <input id=\"search\" type=\"search\" placeholder=\"symbol\">\
+            <input id=\"filter\" type=\"text\" placeholder=\"filter, e.g. 25th > 0, total span <= 20\">\
+            <span id=\"status\"></span></div>";
+
+        HtmlPage::new()
+            .with_meta(vec![("charset", "uft-8")])
+            .with_meta(vec![
+                ("name", "viewport"),
+                ("content", "width=device-width, initial-scale=1.0"),
+            ])
+            .with_title("Stock Predictions")
+            .with_style(style)
+            .with_script_literal(include_str!("interactive.js"))
+            .with_raw(get_header_html(metadata.header.as_deref()))
+            .with_header(
+                1,
+                chrono::Local::now().format("Stock Predictions - %B %d, %Y"),
+            )
+            .with_paragraph_attr(
+                format!(
+                    "{} symbols, {} days, {} simulations, {} sampling, seed {}",
+                    results.predictions.len(),
+                    metadata.days,
+                    metadata.simulations,
+                    metadata.sampler,
+                    metadata.seed
+                ),
+                [("class", "run-info")],
+            )
+            .with_raw(controls)
+            .with_raw(table)
+            .with_raw(get_summary_html(&results.summary))
+            .with_raw(get_disclaimer_html(metadata.disclaimer.as_deref()))
+            .to_html_string()
+    }
+}
@@ -0,0 +1,313 @@
+pub mod logger {
+    use std::{
+        fmt,
+        fs::{File, OpenOptions},
+        io::{self, BufWriter, Write},
+        path::Path,
+        str::FromStr,
+        sync::{Mutex, MutexGuard},
+        time::Duration,
+    };
+
+    use chrono::{SecondsFormat, Utc};
+    use serde_json::{json, Map, Value};
+
+    /// Symbol of the records that are about the whole run rather than one symbol
+    pub const NO_SYMBOL: &str = "N/A";
+
+    /// How much is logged, every level includes the ones before it
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Level {
+        Error,
+        Warn,
+        Info,
+        Debug,
+        Trace,
+    }
+
+    impl Level {
+        pub fn name(&self) -> &'static str {
+            match self {
+                Level::Error => "error",
+                Level::Warn => "warn",
+                Level::Info => "info",
+                Level::Debug => "debug",
+                Level::Trace => "trace",
+            }
+        }
+    }
+
+    impl FromStr for Level {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_ascii_lowercase().as_str() {
+                "error" => Ok(Level::Error),
+                "warn" | "warning" => Ok(Level::Warn),
+                "info" => Ok(Level::Info),
+                "debug" => Ok(Level::Debug),
+                "trace" => Ok(Level::Trace),
+                other => Err(format!(
+                    "unknown log level {other}, expected error, warn, info, debug or trace"
+                )),
+            }
+        }
+    }
+
+    impl fmt::Display for Level {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.name())
+        }
+    }
+
+    /// How every record is written
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub enum LogFormat {
+        /// one readable line per record
+        #[default]
+        Text,
+        /// one json object per line
+        Json,
+    }
+
+    impl FromStr for LogFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.trim().to_ascii_lowercase().as_str() {
+                "text" => Ok(LogFormat::Text),
+                "json" => Ok(LogFormat::Json),
+                other => Err(format!("unknown log format {other}, expected text or json")),
+            }
+        }
+    }
+
+    impl fmt::Display for LogFormat {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                LogFormat::Text => write!(f, "text"),
+                LogFormat::Json => write!(f, "json"),
+            }
+        }
+    }
+
+    /// A level for every module and overrides for single modules, written `warn,monte_carlo=debug`. Modules are
+    /// named after their source file: backtest, monte_carlo, stock_simulation and so on.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct LogFilter {
+        pub level: Level,
+        pub modules: Vec<(String, Level)>,
+    }
+
+    impl LogFilter {
+        pub const fn new(level: Level) -> LogFilter {
+            LogFilter {
+                level,
+                modules: Vec::new(),
+            }
+        }
+
+        /// The most detailed level logged for the module
+        pub fn level_for(&self, module: &str) -> Level {
+            self.modules
+                .iter()
+                .rev()
+                .find(|(name, _)| name == module)
+                .map_or(self.level, |(_, level)| *level)
+        }
+
+        pub fn enabled(&self, level: Level, module: &str) -> bool {
+            level <= self.level_for(module)
+        }
+    }
+
+    impl Default for LogFilter {
+        fn default() -> Self {
+            LogFilter::new(Level::Info)
+        }
+    }
+
+    impl FromStr for LogFilter {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let mut filter = LogFilter::default();
+            for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+                match item.split_once('=') {
+                    None => filter.level = item.parse()?,
+                    Some((module, level)) => {
+                        let module = module.trim();
+                        if module.is_empty() {
+                            return Err(format!("no module before = in {item}"));
+                        }
+                        filter.modules.push((module.to_string(), level.parse()?));
+                    }
+                }
+            }
+            Ok(filter)
+        }
+    }
+
+    impl fmt::Display for LogFilter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.level)?;
+            for (module, level) in &self.modules {
+                write!(f, ",{module}={level}")?;
+            }
+            Ok(())
+        }
+    }
+
+    /// One thing worth logging, with the phase of the run it timed when it is a timing
+    #[derive(Debug)]
+    pub struct Record<'a> {
+        pub level: Level,
+        pub module: &'a str,
+        pub symbol: &'a str,
+        pub message: &'a str,
+        pub phase: Option<&'a str>,
+        pub duration: Option<Duration>,
+    }
+
+    /// The line of the record without its line break
+    pub(crate) fn format_record(record: &Record, format: LogFormat, timestamp: &str) -> String {
+        let duration_ms = record
+            .duration
+            .map(|duration| duration.as_secs_f64() * 1000.0);
+        match format {
+            LogFormat::Text => {
+                let mut line = format!(
+                    "{timestamp} {:<5} {}",
+                    record.level.name().to_ascii_uppercase(),
+                    record.module
+                );
+                if record.symbol != NO_SYMBOL {
+                    line.push_str(&format!(" [{}]", record.symbol));
+                }
+                line.push_str(&format!(": {}", record.message));
+                if let Some(phase) = record.phase {
+                    line.push_str(&format!(" phase={phase}"));
+                }
+                if let Some(duration_ms) = duration_ms {
+                    line.push_str(&format!(" duration_ms={duration_ms:.3}"));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("timestamp".to_string(), json!(timestamp));
+                object.insert("level".to_string(), json!(record.level.name()));
+                object.insert("module".to_string(), json!(record.module));
+                let symbol = (record.symbol != NO_SYMBOL).then_some(record.symbol);
+                object.insert("symbol".to_string(), json!(symbol));
+                object.insert("message".to_string(), json!(record.message));
+                if let Some(phase) = record.phase {
+                    object.insert("phase".to_string(), json!(phase));
+                }
+                if let Some(duration_ms) = duration_ms {
+                    object.insert("duration_ms".to_string(), json!(duration_ms));
+                }
+                Value::Object(object).to_string()
+            }
+        }
+    }
+
+    enum Target {
+        Stderr,
+        File(BufWriter<File>),
+    }
+
+    struct Logger {
+        filter: LogFilter,
+        format: LogFormat,
+        target: Target,
+    }
+
+    impl Logger {
+        /// Writes to stderr from here on, after the file failed
+        fn fall_back(&mut self, error: io::Error) {
+            self.target = Target::Stderr;
+            eprintln!("log file: {error}, logging to stderr");
+        }
+
+        fn flush(&mut self) {
+            if let Target::File(file) = &mut self.target {
+                if let Err(e) = file.flush() {
+                    self.fall_back(e);
+                }
+            }
+        }
+    }
+
+    /// The one logger of the process, every thread writes through it. Records go to stderr until `init` names a
+    /// file.
+    static LOGGER: Mutex<Logger> = Mutex::new(Logger {
+        filter: LogFilter::new(Level::Info),
+        format: LogFormat::Text,
+        target: Target::Stderr,
+    });
+
+    fn lock() -> MutexGuard<'static, Logger> {
+        // a thread that panicked while logging leaves nothing half done worth giving up over
+        LOGGER
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Sends every later record that passes the filter to the file, appending, or to stderr without one
+    pub fn init(path: Option<&Path>, filter: LogFilter, format: LogFormat) -> io::Result<()> {
+        let target = match path {
+            Some(path) => Target::File(BufWriter::new(
+                OpenOptions::new().append(true).create(true).open(path)?,
+            )),
+            None => Target::Stderr,
+        };
+        let mut logger = lock();
+        logger.flush();
+        *logger = Logger {
+            filter,
+            format,
+            target,
+        };
+        Ok(())
+    }
+
+    pub fn enabled(level: Level, module: &str) -> bool {
+        lock().filter.enabled(level, module)
+    }
+
+    /// Whether records go to a log file rather than stderr
+    pub fn has_file() -> bool {
+        matches!(lock().target, Target::File(_))
+    }
+
+    pub fn write(record: &Record) {
+        let mut logger = lock();
+        if !logger.filter.enabled(record.level, record.module) {
+            return;
+        }
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+        let line = format_record(record, logger.format, &timestamp);
+        let result = match &mut logger.target {
+            Target::Stderr => {
+                eprintln!("{line}");
+                Ok(())
+            }
+            Target::File(file) => writeln!(file, "{line}"),
+        };
+        match result {
+            Err(e) => {
+                logger.fall_back(e);
+                eprintln!("{line}");
+            }
+            // errors are flushed straight away so they survive a crash that follows them
+            Ok(()) if record.level == Level::Error => logger.flush(),
+            Ok(()) => {}
+        }
+    }
+
+    /// Writes out everything buffered, the process calls this before it exits
+    pub fn flush() {
+        lock().flush();
+    }
+}
@@ -1,29 +1,142 @@
-use core::cell::RefCell;
 use std::{
     fs,
     path::{Path, PathBuf},
+    process,
 };
 
-use stock_simulation::stock_simulator::run_simulator;
+use backtest::walk_forward::{run_backtest, BacktestSettings};
+use calibration::calibration_report::run_calibration;
+use comparison::run_comparison::run_comparison;
+use config::run_config::{read_config, SimulateConfig};
+use convergence_study::study::{get_study_counts, run_study};
+use history::run_history::{
+    get_run_entries, get_run_list, get_symbol_forecasts, get_symbol_history, load_run, load_runs,
+};
+use inspection::input_inspection::{check_dir, get_inspection_text, get_validation_text};
+use logging::logger::{self, LogFilter, LogFormat, NO_SYMBOL};
+use monte_carlo::simulations::{get_seed, Convergence, Sampler, SimulationSettings};
+use quantiles::distributions::QuantileMethod;
+use report::run_report::{read_results, Branding, OutputFormat, OutputSettings, RunResults};
+use stock_simulation::stock_simulator::{
+    get_simulation_data, run_simulator, write_outputs, SimulationError, Strategy,
+};
 use structopt::StructOpt;
-use utilities::util::log;
+use utilities::util::{log, log_error};
 
+mod backtest;
+mod calibration;
+mod charts;
+mod comparison;
+mod config;
+mod convergence_study;
+mod csv_output;
+mod detail_output;
+mod history;
+mod inspection;
+mod interactive_output;
+mod logging;
+mod markdown_output;
 mod monte_carlo;
+mod progress;
+mod quantiles;
+mod report;
 mod stock_simulation;
+mod table_output;
+mod template;
 mod tests;
 mod utilities;
+mod xlsx_output;
 
 #[derive(StructOpt)]
 #[structopt(
     name = "stock_simulator",
     about = "reads historical stock data from the supplied source directory, performs passed number of simulations for the passed number of days and outputs the predictions of the top symbols to the output directory in html form."
 )]
-struct Opt {
-    /// input file
+// parsed once at start up, so the size of the simulate options does not matter
+#[allow(clippy::large_enum_variant)]
+enum Opt {
+    /// simulates every symbol in the source directory and outputs the top symbols
+    Simulate(SimulateOpt),
+    /// simulates the same symbols at a series of simulation counts and reports how percentiles and ranks shift
+    Study(StudyOpt),
+    /// walks forward through dated histories, picks the top symbols from a trailing window at every rebalance and compares what they returned with an equal weight benchmark
+    Backtest(BacktestOpt),
+    /// scores the forecasts of backtest json outputs against what happened: band coverage, pit histogram and pinball loss
+    Calibrate(CalibrateOpt),
+    /// lists the runs saved with --history, shows the forecasts of one symbol across them or exports a run again
+    History(HistoryOpt),
+    /// compares two runs: symbols entering and leaving the top x, rank moves and the change of every metric
+    Compare(CompareOpt),
+    /// reads every file of the input directory and reports data problems without simulating or archiving
+    Validate(ValidateOpt),
+    /// renders the outputs again from a saved json results file without simulating
+    Report(ReportOpt),
+    /// prints summary statistics of one symbol's input returns
+    Inspect(InspectOpt),
+}
+
+#[derive(StructOpt)]
+struct SimulateOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// toml file holding any of the options below, the command line overrides it
+    #[structopt(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// named profile of the config file whose options override the ones at the top of the file
+    #[structopt(long)]
+    profile: Option<String>,
+    #[structopt(flatten)]
+    options: SimulateConfig,
+}
+
+#[derive(StructOpt)]
+struct StudyOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// input directory, files are read but not moved to the archive
+    #[structopt(short, parse(from_os_str), required(true))]
+    source_dir: PathBuf,
+    /// study table, written as csv when the file ends in .csv and as html otherwise
+    #[structopt(short, parse(from_os_str), required(true))]
+    output_file: PathBuf,
+    #[structopt(short, required(true))]
+    days: u32,
+    #[structopt(short, required(true))]
+    top_x: usize,
+    #[structopt(short, parse(from_os_str), required(true))]
+    log_file: PathBuf,
+    /// comma separated simulation counts to compare
+    #[structopt(
+        short = "c",
+        long,
+        use_delimiter = true,
+        default_value = "100000,250000,500000,750000,1000000"
+    )]
+    counts: Vec<u32>,
+    /// how outcomes are kept for the percentiles: exact, sketch or sketch:<k>
+    #[structopt(long, default_value = "exact")]
+    quantiles: QuantileMethod,
+    /// how returns are drawn: independent, antithetic or stratified
+    #[structopt(long, default_value = "independent")]
+    sampler: Sampler,
+    /// seed for the random draws, a random seed is picked and logged when left out
+    #[structopt(long)]
+    seed: Option<u64>,
+    #[structopt(flatten)]
+    branding: Branding,
+}
+
+#[derive(StructOpt)]
+struct BacktestOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// input directory of dated histories, one <YYYY-MM-DD>,<return> line per day, files are read but not moved to the archive
     #[structopt(short, parse(from_os_str), required(true))]
     source_dir: PathBuf,
+    /// backtest table, written as csv when the file ends in .csv, as json when it ends in .json and as html otherwise
     #[structopt(short, parse(from_os_str), required(true))]
     output_file: PathBuf,
+    /// trading days each pick is held for
     #[structopt(short, required(true))]
     days: u32,
     #[structopt(short, required(true))]
@@ -32,96 +145,571 @@ struct Opt {
     top_x: usize,
     #[structopt(short, parse(from_os_str), required(true))]
     log_file: PathBuf,
+    /// trading days of history the simulations draw from at every rebalance
+    #[structopt(long, default_value = "250")]
+    window: usize,
+    /// trading days between rebalances, the holding period when left out
+    #[structopt(long)]
+    step: Option<usize>,
+    /// what the top x is picked by: most-common, highest-low, total-span or weighted-span
+    #[structopt(long, default_value = "most-common")]
+    strategy: Strategy,
+    /// how outcomes are kept for the percentiles: exact, sketch or sketch:<k>
+    #[structopt(long, default_value = "exact")]
+    quantiles: QuantileMethod,
+    /// how returns are drawn: independent, antithetic or stratified
+    #[structopt(long, default_value = "independent")]
+    sampler: Sampler,
+    /// seed for the random draws, a random seed is picked and logged when left out
+    #[structopt(long)]
+    seed: Option<u64>,
+    #[structopt(flatten)]
+    branding: Branding,
+}
+
+#[derive(StructOpt)]
+struct CalibrateOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// backtest results written as json, one per model or sampler to compare
+    #[structopt(short, parse(from_os_str), required(true))]
+    input_files: Vec<PathBuf>,
+    /// calibration report, written as csv when the file ends in .csv and as html otherwise
+    #[structopt(short, parse(from_os_str), required(true))]
+    output_file: PathBuf,
+    #[structopt(short, parse(from_os_str), required(true))]
+    log_file: PathBuf,
+    /// number of slices in the pit histogram
+    #[structopt(long, default_value = "10")]
+    bins: usize,
+    #[structopt(flatten)]
+    branding: Branding,
+}
+
+#[derive(StructOpt)]
+struct HistoryOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// directory the runs were saved to with --history
+    #[structopt(long, parse(from_os_str), required(true))]
+    store: PathBuf,
+    /// log file, messages are printed when left out
+    #[structopt(short, parse(from_os_str))]
+    log_file: Option<PathBuf>,
+    #[structopt(subcommand)]
+    query: HistoryQuery,
+}
+
+#[derive(StructOpt)]
+enum HistoryQuery {
+    /// lists the saved runs, oldest first
+    List {
+        /// only the runs saved on this date, YYYY-MM-DD
+        #[structopt(long)]
+        date: Option<String>,
+    },
+    /// shows what every saved run predicted for the symbol
+    Symbol { symbol: String },
+    /// writes a saved run again in any output format, the same way report does
+    Export {
+        run: String,
+        #[structopt(short, parse(from_os_str), required(true))]
+        output_file: PathBuf,
+        #[structopt(flatten)]
+        render: RenderOpt,
+    },
+}
+
+#[derive(StructOpt)]
+struct CompareOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// earlier run, a json output or with --store a run id
+    before: String,
+    /// later run, a json output or with --store a run id
+    after: String,
+    /// directory the runs were saved to with --history
+    #[structopt(long, parse(from_os_str))]
+    store: Option<PathBuf>,
+    /// comparison report, written as csv when the file ends in .csv and as html otherwise
+    #[structopt(short, parse(from_os_str), required(true))]
+    output_file: PathBuf,
+    #[structopt(short, parse(from_os_str), required(true))]
+    log_file: PathBuf,
+    /// company header and disclaimer, the ones of the later run when left out
+    #[structopt(flatten)]
+    branding: Branding,
+}
+
+#[derive(StructOpt)]
+struct ValidateOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// input directory, files are read but not moved to the archive
+    #[structopt(short, parse(from_os_str), required(true))]
+    source_dir: PathBuf,
+    /// log file, messages are printed when left out
+    #[structopt(short, parse(from_os_str))]
+    log_file: Option<PathBuf>,
+    /// fewest returns a file should hold
+    #[structopt(long, default_value = "20")]
+    min_observations: usize,
+}
+
+#[derive(StructOpt)]
+struct ReportOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// results written by simulate with the json format or saved with --history
+    #[structopt(short, parse(from_os_str), required(true))]
+    input_file: PathBuf,
+    #[structopt(short, parse(from_os_str), required(true))]
+    output_file: PathBuf,
+    /// log file, messages are printed when left out
+    #[structopt(short, parse(from_os_str))]
+    log_file: Option<PathBuf>,
+    #[structopt(flatten)]
+    render: RenderOpt,
+}
+
+/// How report and history export write the outputs of a saved run
+#[derive(StructOpt)]
+struct RenderOpt {
+    /// comma separated output formats: html, json, csv, xlsx, markdown, html-table, table
+    #[structopt(long, use_delimiter = true, default_value = "html")]
+    format: Vec<OutputFormat>,
+    /// stylesheet for the html outputs in place of the built-in look
+    #[structopt(long, parse(from_os_str))]
+    css: Option<PathBuf>,
+    /// mustache style template rendered in place of the built-in html index page
+    #[structopt(long, parse(from_os_str))]
+    template: Option<PathBuf>,
+    /// company header, the one of the saved run when left out
+    #[structopt(long)]
+    header: Option<String>,
+    /// disclaimer, the one of the saved run when left out
+    #[structopt(long)]
+    disclaimer: Option<String>,
+}
+
+#[derive(StructOpt)]
+struct InspectOpt {
+    #[structopt(flatten)]
+    log: LogOpt,
+    /// input directory holding the symbol's file
+    #[structopt(short, parse(from_os_str), required(true))]
+    source_dir: PathBuf,
+    /// symbol, the name of its file in the input directory
+    symbol: String,
+    /// log file, messages are printed when left out
+    #[structopt(short, parse(from_os_str))]
+    log_file: Option<PathBuf>,
+    /// fewest returns the file should hold
+    #[structopt(long, default_value = "20")]
+    min_observations: usize,
 }
 
-thread_local! {static LOG_FILE_PATH:RefCell<Option<PathBuf>> = RefCell::new(None::<PathBuf>)}
+/// How much every subcommand logs and in which format
+#[derive(StructOpt)]
+struct LogOpt {
+    /// error, warn, info, debug or trace, optionally per module, e.g. warn,monte_carlo=debug
+    #[structopt(long, default_value = "info")]
+    log_level: LogFilter,
+    /// text, or json for one object per line with timestamp, level, module, symbol, message, phase and duration_ms
+    #[structopt(long, default_value = "text")]
+    log_format: LogFormat,
+}
 
 fn main() {
-    let opt = Opt::from_args_safe();
-    match opt {
-        Err(e) => {
-            println!("{}", e);
-            return;
+    let opt = Opt::from_args_safe().unwrap_or_else(|e| e.exit());
+    let result = match opt {
+        Opt::Simulate(args) => simulate(args),
+        Opt::Study(args) => study(args),
+        Opt::Backtest(args) => backtest(args),
+        Opt::Calibrate(args) => calibrate(args),
+        Opt::History(args) => history(args),
+        Opt::Compare(args) => compare(args),
+        Opt::Validate(args) => validate(args),
+        Opt::Report(args) => report(args),
+        Opt::Inspect(args) => inspect(args),
+    };
+
+    // the message goes to the log file when there is one and always to stderr, the exit code tells a scheduler
+    // what kind of failure it was
+    if let Err(e) = result {
+        if logger::has_file() {
+            log_error(NO_SYMBOL, &e);
         }
-        Ok(args) => {
-            let source_dir = args.source_dir;
-            let periods = args.days;
-            let number_of_simulations = args.number_of_simulations;
-            let output_html = args.output_file;
-            let top_x = args.top_x;
-            let log_path = args.log_file;
-
-            validate_log_file(&log_path);
-            LOG_FILE_PATH.with(|path| *path.borrow_mut() = Some(log_path));
-
-            validate_args(&source_dir, &output_html);
-
-            log("N/A", "process begin");
-            run_simulator(
-                &source_dir,
-                periods,
-                number_of_simulations,
-                top_x,
-                &output_html,
-            );
-            log("N/A", "process end");
+        logger::flush();
+        eprintln!("{e}");
+        process::exit(e.exit_code());
+    }
+    logger::flush();
+}
+
+fn simulate(args: SimulateOpt) -> Result<(), SimulationError> {
+    let options = match &args.config {
+        None if args.profile.is_some() => {
+            return Err(SimulationError::Config(
+                "--profile needs a --config file".to_string(),
+            ));
         }
+        None => args.options,
+        Some(path) => args
+            .options
+            .or(read_config(path, args.profile.as_deref()).map_err(SimulationError::Config)?),
+    };
+    let missing = options.missing();
+    if !missing.is_empty() {
+        return Err(SimulationError::Config(format!(
+            "missing {}, give them on the command line or in the config file",
+            missing.join(", ")
+        )));
     }
+    let resolved = options.to_toml().trim_end().replace('\n', "; ");
+
+    // every required option was checked above
+    let (
+        Some(source_dir),
+        Some(output_html),
+        Some(days),
+        Some(number_of_simulations),
+        Some(top_x),
+        Some(log_path),
+    ) = (
+        options.source_dir,
+        options.output_file,
+        options.days,
+        options.number_of_simulations,
+        options.top_x,
+        options.log_file,
+    )
+    else {
+        unreachable!("missing options are reported above");
+    };
+    let convergence = options
+        .adaptive
+        .map(|tolerance| Convergence::new(options.batch_size.unwrap_or(10_000), tolerance))
+        .transpose()
+        .map_err(SimulationError::Config)?;
+    let settings = SimulationSettings {
+        periods: days,
+        number_of_simulations,
+        quantile_method: options.quantiles.unwrap_or_default(),
+        convergence,
+        sampler: options.sampler.unwrap_or_default(),
+        seed: get_seed(options.seed).map_err(SimulationError::Config)?,
+    };
+
+    start_logging(Some(&log_path), args.log)?;
+
+    validate_args(&source_dir, &output_html)?;
+    let output_settings = OutputSettings {
+        formats: options.format.unwrap_or_else(|| vec![OutputFormat::Html]),
+        css: read_theme_file(options.css.as_deref())?,
+        template: read_theme_file(options.template.as_deref())?,
+        header: options.header,
+        disclaimer: options.disclaimer,
+        history: options.history,
+        config: args.config.map(|path| match &args.profile {
+            Some(profile) => format!("{} [{profile}]", path.display()),
+            None => path.display().to_string(),
+        }),
+        options: Some(resolved.clone()),
+    };
+
+    let strategy = options.strategy.unwrap_or_default();
+
+    log(
+        NO_SYMBOL,
+        format!("process begin, seed {}, strategy {strategy}", settings.seed),
+    );
+    log(NO_SYMBOL, format!("resolved configuration: {resolved}"));
+    let result = run_simulator(
+        &source_dir,
+        &settings,
+        top_x,
+        strategy,
+        &output_html,
+        &output_settings,
+        options.max_failures,
+    );
+    log(NO_SYMBOL, "process end");
+    result
+}
+
+fn study(args: StudyOpt) -> Result<(), SimulationError> {
+    let settings = SimulationSettings {
+        periods: args.days,
+        number_of_simulations: 0,
+        quantile_method: args.quantiles,
+        convergence: None,
+        sampler: args.sampler,
+        seed: get_seed(args.seed).map_err(SimulationError::Config)?,
+    };
+
+    start_logging(Some(&args.log_file), args.log)?;
+
+    validate_args(&args.source_dir, &args.output_file)?;
+
+    log(NO_SYMBOL, format!("study begin, seed {}", settings.seed));
+    let result = run_study(
+        &args.source_dir,
+        &settings,
+        &get_study_counts(&args.counts),
+        args.top_x,
+        &args.output_file,
+        &args.branding,
+    );
+    log(NO_SYMBOL, "study end");
+    result
+}
+
+fn backtest(args: BacktestOpt) -> Result<(), SimulationError> {
+    let settings = SimulationSettings {
+        periods: args.days,
+        number_of_simulations: args.number_of_simulations,
+        quantile_method: args.quantiles,
+        convergence: None,
+        sampler: args.sampler,
+        seed: get_seed(args.seed).map_err(SimulationError::Config)?,
+    };
+    let backtest = BacktestSettings {
+        window: args.window,
+        step: args.step.unwrap_or(args.days as usize),
+        top_x: args.top_x,
+        strategy: args.strategy,
+    };
+
+    start_logging(Some(&args.log_file), args.log)?;
+
+    validate_args(&args.source_dir, &args.output_file)?;
+
+    log(NO_SYMBOL, format!("backtest begin, seed {}", settings.seed));
+    let result = run_backtest(
+        &args.source_dir,
+        &settings,
+        &backtest,
+        &args.output_file,
+        &args.branding,
+    );
+    log(NO_SYMBOL, "backtest end");
+    result
 }
 
-fn validate_log_file(log_path: &PathBuf) {
-    let mut log = log_path.clone();
-    let mut log_exists = Path::exists(&log);
-    if !log_exists {
-        log.pop();
-        log_exists = Path::exists(&log);
+fn calibrate(args: CalibrateOpt) -> Result<(), SimulationError> {
+    start_logging(Some(&args.log_file), args.log)?;
+
+    for input in &args.input_files {
+        validate_args(input, &args.output_file)?;
     }
-    if !log_exists {
-        panic!("log directory does not exist");
+
+    log(NO_SYMBOL, "calibration begin");
+    let result = run_calibration(
+        &args.input_files,
+        args.bins,
+        &args.output_file,
+        &args.branding,
+    );
+    log(NO_SYMBOL, "calibration end");
+    result
+}
+
+/// Sends the messages to the log file when one is given, to stderr otherwise
+fn start_logging(log_file: Option<&Path>, log: LogOpt) -> Result<(), SimulationError> {
+    if let Some(log_path) = log_file {
+        validate_log_file(log_path)?;
     }
+    logger::init(log_file, log.log_level, log.log_format).map_err(|e| {
+        SimulationError::Io(format!(
+            "{}: {e}",
+            log_file
+                .map(|path| path.display().to_string())
+                .unwrap_or_default()
+        ))
+    })
+}
+
+fn history(args: HistoryOpt) -> Result<(), SimulationError> {
+    start_logging(args.log_file.as_deref(), args.log)?;
 
-    match fs::metadata(log) {
-        Ok(md) => {
-            if md.permissions().readonly() {
-                panic!("you do not have permission to the log file")
-            }
+    match args.query {
+        HistoryQuery::List { date } => {
+            let runs = load_runs(&args.store)?;
+            print!("{}", get_run_list(&get_run_entries(&runs, date.as_deref())));
+        }
+        HistoryQuery::Symbol { symbol } => {
+            let runs = load_runs(&args.store)?;
+            print!(
+                "{}",
+                get_symbol_history(&get_symbol_forecasts(&runs, &symbol))
+            );
+        }
+        HistoryQuery::Export {
+            run,
+            output_file,
+            render,
+        } => {
+            let results = load_run(&args.store, &run)?;
+            validate_args(&args.store, &output_file)?;
+            render_results(results, &output_file, render)?;
         }
-        Err(e) => panic!("{e}"),
     }
+    Ok(())
+}
+
+fn compare(args: CompareOpt) -> Result<(), SimulationError> {
+    start_logging(Some(&args.log_file), args.log)?;
+
+    let load = |run: &str| match &args.store {
+        Some(store) => load_run(store, run),
+        None => read_results(Path::new(run)),
+    };
+    // with a store the runs are ids, otherwise the later run is the input checked alongside the output
+    let input = args.store.as_deref().unwrap_or(Path::new(&args.after));
+    validate_args(input, &args.output_file)?;
+    let before = load(&args.before)?;
+    let after = load(&args.after)?;
+
+    let branding = Branding {
+        header: args
+            .branding
+            .header
+            .or_else(|| after.metadata.header.clone()),
+        disclaimer: args
+            .branding
+            .disclaimer
+            .or_else(|| after.metadata.disclaimer.clone()),
+    };
+
+    log(NO_SYMBOL, "comparison begin");
+    let result = run_comparison(&before, &after, &args.output_file, &branding);
+    log(NO_SYMBOL, "comparison end");
+    result
 }
 
-fn validate_args(source_dir: &PathBuf, output_dir: &PathBuf) {
-    let file_exists = Path::exists(source_dir);
-    if !file_exists {
-        let error = "file_name does not exist";
-        log("N/A", error);
-        panic!("{error}");
+fn validate(args: ValidateOpt) -> Result<(), SimulationError> {
+    start_logging(args.log_file.as_deref(), args.log)?;
+
+    let checks = check_dir(&args.source_dir, args.min_observations)?;
+    print!("{}", get_validation_text(&checks));
+    let failed = checks
+        .iter()
+        .filter(|check| !check.problems.is_empty())
+        .count();
+    if checks.is_empty() {
+        Err(SimulationError::NoInput(format!(
+            "no symbol files in {}",
+            args.source_dir.display()
+        )))
+    } else if failed > 0 {
+        Err(SimulationError::PartialFailure {
+            failed,
+            skipped: 0,
+            total: checks.len(),
+        })
+    } else {
+        Ok(())
     }
+}
+
+fn report(args: ReportOpt) -> Result<(), SimulationError> {
+    start_logging(args.log_file.as_deref(), args.log)?;
+
+    validate_args(&args.input_file, &args.output_file)?;
+    let results = read_results(&args.input_file)?;
+    render_results(results, &args.output_file, args.render)
+}
 
-    let mut output = output_dir.clone();
-    let mut output_exists = Path::exists(&output);
-    if !output_exists {
-        output.pop();
-        output_exists = Path::exists(&output);
+/// Writes the outputs of saved results without simulating, with the header and disclaimer of the run unless
+/// others are given
+fn render_results(
+    mut results: RunResults,
+    output_file: &Path,
+    render: RenderOpt,
+) -> Result<(), SimulationError> {
+    // the reports read the header and disclaimer from the metadata
+    if render.header.is_some() {
+        results.metadata.header = render.header;
     }
-    if !output_exists {
-        let error = "output directory does not exist";
-        log("N/A", error);
-        panic!("{error}");
+    if render.disclaimer.is_some() {
+        results.metadata.disclaimer = render.disclaimer;
     }
+    let output_settings = OutputSettings {
+        formats: render.format,
+        css: read_theme_file(render.css.as_deref())?,
+        template: read_theme_file(render.template.as_deref())?,
+        header: results.metadata.header.clone(),
+        disclaimer: results.metadata.disclaimer.clone(),
+        ..OutputSettings::default()
+    };
+    write_outputs(output_file, &output_settings, &results)?;
+    Ok(())
+}
 
-    match fs::metadata(output) {
-        Ok(md) => {
-            if md.permissions().readonly() {
-                let error = "you do not have permission to the html file";
-                log("N/A", error);
-                panic!("{error}");
-            }
-        }
-        Err(e) => {
-            let error = e.to_string();
-            log("N/A", error);
-            panic!("{e}");
-        }
+fn inspect(args: InspectOpt) -> Result<(), SimulationError> {
+    start_logging(args.log_file.as_deref(), args.log)?;
+
+    let data = get_simulation_data(&args.source_dir.join(&args.symbol))?;
+    print!(
+        "{}",
+        get_inspection_text(&args.symbol, &data, args.min_observations)
+    );
+    Ok(())
+}
+
+fn read_theme_file(path: Option<&Path>) -> Result<Option<String>, SimulationError> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())))
+}
+
+/// The directory a file would be created in, the current directory for a bare file name
+fn get_parent(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Checks that the file, or the directory it would be created in, exists and can be written
+fn check_writable(path: &Path, name: &str) -> Result<(), SimulationError> {
+    let target = if path.exists() {
+        path
+    } else {
+        get_parent(path)
+    };
+    if !target.exists() {
+        return Err(SimulationError::Validation(format!(
+            "{name} directory {} does not exist",
+            target.display()
+        )));
+    }
+
+    let metadata = fs::metadata(target)
+        .map_err(|e| SimulationError::Io(format!("{}: {e}", target.display())))?;
+    if metadata.permissions().readonly() {
+        return Err(SimulationError::Validation(format!(
+            "you do not have permission to the {name} {}",
+            target.display()
+        )));
+    }
+    Ok(())
+}
+
+fn validate_log_file(log_path: &Path) -> Result<(), SimulationError> {
+    check_writable(log_path, "log")
+}
+
+fn validate_args(source_dir: &Path, output_dir: &Path) -> Result<(), SimulationError> {
+    if !source_dir.exists() {
+        return Err(SimulationError::Validation(format!(
+            "{} does not exist",
+            source_dir.display()
+        )));
     }
+    check_writable(output_dir, "output")
 }
@@ -0,0 +1,107 @@
+pub mod markdown_report {
+    use crate::{
+        report::run_report::{get_prediction_views, RatedValue, RunMetadata, RunSummary},
+        stock_simulation::stock_simulator::{Rating, TopPredictions},
+    };
+
+    fn get_marker(rating: Rating) -> &'static str {
+        match rating {
+            Rating::Green => "🟢",
+            Rating::Yellow => "🟡",
+            Rating::Red => "🔴",
+        }
+    }
+
+    fn get_rated_cell(rated: &RatedValue) -> String {
+        format!("{} {}", get_marker(rated.rating), rated.value)
+    }
+
+    /// Pipes would end a table cell early, everything else is left to the markdown renderer
+    fn escape(text: &str) -> String {
+        text.replace('|', "\\|")
+    }
+
+    /// The ranked top x, the run parameters, notes on every symbol and the run summary as markdown for a wiki or
+    /// pull request. The ratings are the same ones the html cards color, shown as colored circles.
+    pub(crate) fn get_markdown(
+        calcs: &[TopPredictions],
+        metadata: &RunMetadata,
+        summary: &RunSummary,
+    ) -> String {
+        let views = get_prediction_views(calcs, metadata);
+
+        let mut markdown = String::new();
+        if let Some(header) = &metadata.header {
+            markdown.push_str(&format!("**{header}**\n\n"));
+        }
+        markdown.push_str(&format!(
+            "# {}\n\n",
+            chrono::Local::now().format("Stock Predictions - %B %d, %Y")
+        ));
+
+        markdown.push_str(&format!("## Top {}\n\n", metadata.top_x));
+        markdown.push_str(
+            "| rank | symbol | most common | bottom 25th | 25th to 75th span | weighted span | simulations |\n",
+        );
+        markdown.push_str("| ---: | --- | ---: | ---: | ---: | ---: | ---: |\n");
+        for pred in &views {
+            markdown.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                pred.rank,
+                escape(&pred.symbol),
+                get_rated_cell(&pred.most_common),
+                get_rated_cell(&pred.highest_low),
+                get_rated_cell(&pred.total_span),
+                get_rated_cell(&pred.weighted_span),
+                pred.simulations
+            ));
+        }
+        markdown.push_str(&format!(
+            "\n{} top third, {} middle third, {} bottom third of the top {}. The weighted span is {} above zero, {} at zero and {} below zero.\n",
+            get_marker(Rating::Green),
+            get_marker(Rating::Yellow),
+            get_marker(Rating::Red),
+            metadata.top_x,
+            get_marker(Rating::Green),
+            get_marker(Rating::Yellow),
+            get_marker(Rating::Red),
+        ));
+
+        markdown.push_str("\n## Run Parameters\n\n| parameter | value |\n| --- | --- |\n");
+        for (name, value) in metadata.parameters() {
+            markdown.push_str(&format!("| {name} | {} |\n", escape(&value)));
+        }
+
+        markdown.push_str("\n## Notes\n");
+        for pred in &views {
+            markdown.push_str(&format!("\n### {}\n\n", pred.symbol));
+            for note in &pred.notes {
+                markdown.push_str(&format!("- {note}\n"));
+            }
+        }
+
+        markdown.push_str(&format!(
+            "\n## Run Summary\n\nRun {}: {}.\n",
+            summary.status.name(),
+            summary.counts()
+        ));
+        if let Some(reason) = &summary.aborted {
+            markdown.push_str(&format!("\nAborted: {reason}\n"));
+        }
+        for (title, issues) in [("Skipped", &summary.skipped), ("Failed", &summary.failed)] {
+            if issues.is_empty() {
+                continue;
+            }
+            markdown.push_str(&format!("\n{title}:\n\n"));
+            for issue in issues {
+                markdown.push_str(&format!("- **{}**: {}\n", issue.symbol, issue.reason));
+            }
+        }
+
+        if let Some(disclaimer) = &metadata.disclaimer {
+            markdown.push_str(&format!("\n---\n\n*{disclaimer}*\n"));
+        }
+
+        markdown
+    }
+}
@@ -1,78 +1,704 @@
 pub mod simulations {
-    use std::collections::BTreeMap;
+    use std::{collections::BTreeMap, fmt, str::FromStr, thread};
 
-    use rand::random_range;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use serde::{Deserialize, Serialize};
 
-    use crate::utilities::util::log;
+    use crate::{
+        quantiles::distributions::{
+            KllSketch, OutcomeDistribution, QuantileMethod, DEFAULT_SKETCH_K,
+        },
+        utilities::util::{log, log_error},
+    };
 
-    #[derive(Debug)]
+    #[derive(Debug, Default, Clone, Serialize, Deserialize)]
     pub struct Percentiles {
+        #[serde(rename = "p25")]
         pub _25th: i32,
+        #[serde(rename = "p50")]
         pub _50th: i32,
+        #[serde(rename = "p75")]
         pub _75th: i32,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Default, Serialize, Deserialize)]
     pub struct Prediction {
         pub symbol: String,
         pub percentiles: Percentiles,
-        // pub data: BTreeMap<i32, u32>,
+        /// number of simulations the percentiles were calculated from
+        pub simulations: u32,
+        /// number of historical returns the simulations drew from, 0 in documents written before it was added
+        #[serde(default)]
+        pub observations: usize,
+        /// number of simulations ending at each whole outcome
+        #[serde(default)]
+        pub data: BTreeMap<i32, u32>,
+        /// spread of the running gain or loss on every day of the horizon, only drawn for the top x
+        #[serde(default)]
+        pub fan_chart: Vec<FanBand>,
+        /// largest fall from a peak along the simulated paths, only drawn for the top x
+        #[serde(default)]
+        pub drawdown: DrawdownStats,
+        /// the historical returns the simulations drew from
+        #[serde(default)]
+        pub input: InputStats,
     }
 
-    /// Method that will run a number of monte carlo simulations on the data passed in for the number of periods pass in
-    pub(crate) fn monte_carlo_simulation(
-        symbol: String,
-        data: &Vec<f64>,
-        periods: u32,
-        number_of_simulations: u32,
-    ) -> Option<Prediction> {
-        static mut SANITY_CHECK: bool = false;
-        let mut results: BTreeMap<i32, u32> = BTreeMap::new();
+    /// Percentiles of the largest fall from a running peak along each path, as a percent of the peak
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct DrawdownStats {
+        pub p50: f64,
+        pub p75: f64,
+        pub p90: f64,
+        pub worst: f64,
+    }
+
+    /// Daily return statistics of the historical data, returns are fractions so 0.01 is 1%
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct InputStats {
+        pub mean: f64,
+        pub std_dev: f64,
+        pub min: f64,
+        pub median: f64,
+        pub max: f64,
+        /// share of days with a positive return
+        pub positive: f64,
+    }
+
+    /// Percentiles of the gain or loss on 100 invested at the end of one day of the horizon
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct FanBand {
+        pub day: u32,
+        pub p10: f64,
+        pub p25: f64,
+        pub p50: f64,
+        pub p75: f64,
+        pub p90: f64,
+    }
 
-        for _ in 1..number_of_simulations {
-            let simulation = simulate_period(&data, periods);
-            let calc = perform_simulation_calculation(&simulation);
+    /// Stopping rule for adaptive runs: simulate in batches until no percentile moves more than the tolerance
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Convergence {
+        pub batch_size: u32,
+        pub tolerance: f64,
+    }
 
-            *results.entry(calc).or_insert(0) += 1;
+    impl Convergence {
+        /// a tolerance that is not a positive number could never be met, so it is refused rather than running to
+        /// the maximum
+        pub fn new(batch_size: u32, tolerance: f64) -> Result<Convergence, String> {
+            if !tolerance.is_finite() || tolerance <= 0.0 {
+                return Err(format!(
+                    "adaptive tolerance must be a positive number, got {tolerance}"
+                ));
+            }
+            Ok(Convergence {
+                batch_size,
+                tolerance,
+            })
         }
+    }
 
-        if results.len() == 0 {
-            log(&symbol, "simulation results file is empty!");
-            return None;
+    /// How the historical returns are drawn for each simulated path
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum Sampler {
+        /// every day of every path is an independent draw
+        #[default]
+        Independent,
+        /// paths come in pairs, the second path mirrors every draw of the first around the median of the sorted returns
+        Antithetic,
+        /// latin hypercube: within each block of paths every day draws exactly once from each equal sized slice of the sorted returns
+        Stratified,
+    }
+
+    impl FromStr for Sampler {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "independent" => Ok(Sampler::Independent),
+                "antithetic" => Ok(Sampler::Antithetic),
+                "stratified" => Ok(Sampler::Stratified),
+                _ => Err(format!(
+                    "unknown sampler {s}, expected independent, antithetic or stratified"
+                )),
+            }
         }
+    }
+
+    impl fmt::Display for Sampler {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                Sampler::Independent => "independent",
+                Sampler::Antithetic => "antithetic",
+                Sampler::Stratified => "stratified",
+            };
+            write!(f, "{name}")
+        }
+    }
+
+    /// Everything that decides how the simulations of a single symbol are run
+    #[derive(Debug, Clone, Copy)]
+    pub struct SimulationSettings {
+        pub periods: u32,
+        /// the exact number of simulations, or the most an adaptive run may use
+        pub number_of_simulations: u32,
+        pub quantile_method: QuantileMethod,
+        pub convergence: Option<Convergence>,
+        pub sampler: Sampler,
+        /// root of every random draw in the run, the same seed and settings give the same predictions
+        pub seed: u64,
+    }
+
+    /// Largest seed a json reader that keeps numbers as doubles, javascript among them, reads back exactly
+    pub const MAX_SEED: u64 = (1 << 53) - 1;
+
+    /// The seed given, or a random one when left out. Seeds stay at or below `MAX_SEED` so the one in the json
+    /// output can be read back and the run reproduced.
+    pub fn get_seed(seed: Option<u64>) -> Result<u64, String> {
+        match seed {
+            None => Ok(rand::random_range(0..=MAX_SEED)),
+            Some(seed) if seed > MAX_SEED => Err(format!(
+                "seed {seed} is larger than {MAX_SEED}, json readers would round it"
+            )),
+            Some(seed) => Ok(seed),
+        }
+    }
+
+    /// Fewest simulations worth handing to a thread of their own
+    const MIN_SIMULATIONS_PER_CHUNK: u32 = 10_000;
+
+    /// Most chunks a run of simulations is split into, fixed so the chunks and their seeds do not depend on the machine
+    const MAX_CHUNKS: u32 = 64;
+
+    /// Number of paths that share one latin hypercube when the stratified sampler is used
+    const STRATIFIED_BLOCK_SIZE: u32 = 1_000;
+
+    /// Method that will run a number of monte carlo simulations on the data passed in for the number of periods pass in.
+    /// The error says why the symbol has no prediction, so the caller can record it as failed.
+    pub(crate) fn monte_carlo_simulation(
+        symbol: String,
+        data: &[f64],
+        settings: &SimulationSettings,
+    ) -> Result<Prediction, String> {
+        let symbol_seed = get_symbol_seed(settings.seed, &symbol);
+        let (results, requested) = match settings.convergence {
+            None => (
+                run_simulations(data, settings, settings.number_of_simulations, symbol_seed),
+                settings.number_of_simulations,
+            ),
+            Some(convergence) => {
+                run_until_converged(&symbol, data, settings, &convergence, symbol_seed)
+            }
+        };
 
-        if !unsafe { SANITY_CHECK } {
-            unsafe { SANITY_CHECK = true };
-            let total_sims = get_total_sim_count(&results);
-            log(&symbol, format!("total simulations: {total_sims}"));
+        if results.is_empty() {
+            return Err("no returns to simulate".to_string());
         }
 
-        let percentiles = get_percentiles(&results, number_of_simulations).unwrap();
+        let simulations = check_simulation_count(&symbol, requested, &results)?;
+        let percentiles = results
+            .percentiles()
+            .ok_or_else(|| "no percentiles in the simulated outcomes".to_string())?;
         let prediction = Prediction {
             symbol,
             percentiles,
-            // data: results,
+            simulations,
+            observations: data.len(),
+            data: results.histogram(),
+            fan_chart: Vec::new(),
+            drawdown: DrawdownStats::default(),
+            input: get_input_stats(data),
+        };
+
+        Ok(prediction)
+    }
+
+    /// Integrity check that every requested simulation made it into the distribution the percentiles come from,
+    /// a mismatch means the percentiles are wrong so the symbol gets no prediction
+    pub(crate) fn check_simulation_count(
+        symbol: &str,
+        requested: u32,
+        results: &OutcomeDistribution,
+    ) -> Result<u32, String> {
+        let recorded = results.count();
+        if recorded != requested as u64 {
+            let error = format!("{requested} simulations requested but {recorded} were recorded");
+            log_error(symbol, &error);
+            return Err(error);
+        }
+        Ok(requested)
+    }
+
+    /// Runs batches of simulations until the percentiles settle or the maximum number of simulations is used up
+    fn run_until_converged(
+        symbol: &str,
+        data: &[f64],
+        settings: &SimulationSettings,
+        convergence: &Convergence,
+        symbol_seed: u64,
+    ) -> (OutcomeDistribution, u32) {
+        let mut results = OutcomeDistribution::new(settings.quantile_method, symbol_seed);
+        let mut previous: Option<Percentiles> = None;
+        let mut used: u32 = 0;
+        let mut batch_number: u64 = 0;
+
+        while used < settings.number_of_simulations {
+            let batch = get_batch_size(convergence, settings.number_of_simulations, used);
+            let batch_seed = mix_seed(symbol_seed ^ batch_number);
+            results.merge(run_simulations(data, settings, batch, batch_seed));
+            used += batch;
+            batch_number += 1;
+
+            let current = results.percentiles();
+            if let (Some(before), Some(after)) = (&previous, &current) {
+                if percentiles_converged(before, after, convergence.tolerance) {
+                    log(symbol, format!("converged after {used} simulations"));
+                    return (results, used);
+                }
+            }
+            previous = current;
+        }
+
+        log(
+            symbol,
+            format!("did not converge, stopped at the maximum of {used} simulations"),
+        );
+        (results, used)
+    }
+
+    /// True when none of the percentiles moved by more than the tolerance between two batches
+    pub(crate) fn percentiles_converged(
+        before: &Percentiles,
+        after: &Percentiles,
+        tolerance: f64,
+    ) -> bool {
+        let largest_move = [
+            before._25th - after._25th,
+            before._50th - after._50th,
+            before._75th - after._75th,
+        ]
+        .iter()
+        .map(|change| change.abs())
+        .max()
+        .unwrap_or(0);
+
+        largest_move as f64 <= tolerance
+    }
+
+    // the next batch of an adaptive run, never past the maximum
+    fn get_batch_size(convergence: &Convergence, maximum: u32, used: u32) -> u32 {
+        convergence.batch_size.max(1).min(maximum - used)
+    }
+
+    /// Runs the simulations in chunks spread over the available threads, each chunk fills its own distribution from its
+    /// own seed and the chunks are merged in order once all are done
+    fn run_simulations(
+        data: &[f64],
+        settings: &SimulationSettings,
+        number_of_simulations: u32,
+        seed: u64,
+    ) -> OutcomeDistribution {
+        let chunks = run_chunks(number_of_simulations, seed, |chunk, chunk_seed| {
+            simulate_chunk(
+                data,
+                settings.periods,
+                chunk,
+                settings.quantile_method,
+                settings.sampler,
+                chunk_seed,
+            )
+        });
+        let mut results = OutcomeDistribution::new(settings.quantile_method, seed);
+        for chunk in chunks {
+            results.merge(chunk);
+        }
+        results
+    }
+
+    /// `run_simulations` again with every day of every path kept, the paths are the very ones the percentiles
+    /// came from
+    fn trace_simulations(
+        data: &[f64],
+        settings: &SimulationSettings,
+        number_of_simulations: u32,
+        seed: u64,
+    ) -> PathTrace {
+        let chunks = run_chunks(number_of_simulations, seed, |chunk, chunk_seed| {
+            let mut trace = PathTrace::new(settings.periods, settings.quantile_method, chunk_seed);
+            draw_paths(
+                data,
+                settings.periods,
+                chunk,
+                settings.sampler,
+                chunk_seed,
+                |simulation| trace.record(simulation),
+            );
+            trace
+        });
+        let mut trace = PathTrace::new(settings.periods, settings.quantile_method, seed);
+        for chunk in chunks {
+            trace.merge(chunk);
+        }
+        trace
+    }
+
+    /// Splits the simulations into chunks, runs them over the available threads with a seed per chunk and gives
+    /// what each chunk returned in chunk order
+    fn run_chunks<T, F>(number_of_simulations: u32, seed: u64, run: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(u32, u64) -> T + Sync,
+    {
+        let chunks = get_chunk_sizes(number_of_simulations, MAX_CHUNKS);
+        let workers = available_threads().min(chunks.len() as u32) as usize;
+        let chunks = &chunks;
+        let run = &run;
+
+        let mut finished: Vec<(usize, T)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|worker| {
+                    scope.spawn(move || {
+                        chunks
+                            .iter()
+                            .enumerate()
+                            .skip(worker)
+                            .step_by(workers)
+                            .map(|(index, chunk)| {
+                                (index, run(*chunk, mix_seed(seed ^ index as u64)))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("simulation thread panicked"))
+                .collect()
+        });
+
+        finished.sort_by_key(|(index, _)| *index);
+        finished.into_iter().map(|(_, chunk)| chunk).collect()
+    }
+
+    /// Per day percentiles of the running gain or loss on 100 invested and the largest drawdown of every path,
+    /// for a prediction `monte_carlo_simulation` made from `simulations` paths. The chunks, or the batches of an
+    /// adaptive run, are drawn again from the same seeds, so the last day matches the prediction's percentiles.
+    /// Every day is kept the way the quantile method keeps outcomes, so memory does not grow with the paths.
+    pub(crate) fn get_path_summary(
+        symbol: &str,
+        data: &[f64],
+        settings: &SimulationSettings,
+        simulations: u32,
+    ) -> (Vec<FanBand>, DrawdownStats) {
+        let symbol_seed = get_symbol_seed(settings.seed, symbol);
+        let trace = match &settings.convergence {
+            None => trace_simulations(data, settings, simulations, symbol_seed),
+            Some(convergence) => {
+                let mut trace =
+                    PathTrace::new(settings.periods, settings.quantile_method, symbol_seed);
+                let mut used: u32 = 0;
+                let mut batch_number: u64 = 0;
+                while used < simulations {
+                    let batch = get_batch_size(convergence, settings.number_of_simulations, used);
+                    let batch_seed = mix_seed(symbol_seed ^ batch_number);
+                    trace.merge(trace_simulations(data, settings, batch, batch_seed));
+                    used += batch;
+                    batch_number += 1;
+                }
+                trace
+            }
         };
+        trace.summary()
+    }
+
+    /// Every day's running gain or loss and the drawdowns of a set of paths
+    #[derive(Debug, Clone)]
+    pub(crate) struct PathTrace {
+        days: Vec<OutcomeDistribution>,
+        drawdowns: KllSketch,
+        worst: f64,
+    }
+
+    impl PathTrace {
+        fn new(periods: u32, quantile_method: QuantileMethod, seed: u64) -> PathTrace {
+            PathTrace {
+                days: (0..periods)
+                    .map(|_| OutcomeDistribution::new(quantile_method, seed))
+                    .collect(),
+                drawdowns: KllSketch::new(DEFAULT_SKETCH_K, seed),
+                worst: 0.0,
+            }
+        }
+
+        fn record(&mut self, simulation: &[f64]) {
+            let mut investment = 100.0;
+            let mut peak: f64 = investment;
+            let mut drawdown: f64 = 0.0;
+            for (day, rate) in simulation.iter().enumerate() {
+                investment += investment * rate;
+                self.days[day].record(investment - 100.0);
+                peak = peak.max(investment);
+                drawdown = drawdown.max((peak - investment) / peak * 100.0);
+            }
+            self.drawdowns.update(drawdown);
+            self.worst = self.worst.max(drawdown);
+        }
 
-        Some(prediction)
+        fn merge(&mut self, other: PathTrace) {
+            for (day, other) in self.days.iter_mut().zip(other.days) {
+                day.merge(other);
+            }
+            self.drawdowns.merge(other.drawdowns);
+            self.worst = self.worst.max(other.worst);
+        }
+
+        fn summary(&self) -> (Vec<FanBand>, DrawdownStats) {
+            if self.drawdowns.count() == 0 || self.days.iter().any(|day| day.is_empty()) {
+                return (Vec::new(), DrawdownStats::default());
+            }
+
+            let fan_chart = self
+                .days
+                .iter()
+                .enumerate()
+                .map(|(day, values)| {
+                    let bands = values.percents(&[10, 25, 50, 75, 90]);
+                    FanBand {
+                        day: day as u32 + 1,
+                        p10: bands[0],
+                        p25: bands[1],
+                        p50: bands[2],
+                        p75: bands[3],
+                        p90: bands[4],
+                    }
+                })
+                .collect();
+
+            let quantile = |fraction| self.drawdowns.quantile(fraction).unwrap_or_default();
+            let drawdown = DrawdownStats {
+                p50: quantile(0.50),
+                p75: quantile(0.75),
+                p90: quantile(0.90),
+                worst: self.worst,
+            };
+
+            (fan_chart, drawdown)
+        }
     }
 
-    fn get_total_sim_count(results: &BTreeMap<i32, u32>) -> u32 {
-        let values: Vec<u32> = results.values().cloned().collect();
-        values.iter().sum()
+    // the smallest value with at least the fraction of the sorted values at or below it
+    fn nearest_rank(sorted: &[f64], fraction: f64) -> f64 {
+        let rank = (fraction * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
     }
 
-    pub(crate) fn get_percentiles(results: &BTreeMap<i32, u32>, total: u32) -> Option<Percentiles> {
-        if results.len() == 0 {
+    /// Summary of the historical daily returns a symbol's simulations draw from
+    pub(crate) fn get_input_stats(data: &[f64]) -> InputStats {
+        if data.is_empty() {
+            return InputStats::default();
+        }
+
+        let count = data.len() as f64;
+        let mean = data.iter().sum::<f64>() / count;
+        let variance = data.iter().map(|rate| (rate - mean).powi(2)).sum::<f64>() / count;
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|left, right| left.total_cmp(right));
+
+        InputStats {
+            mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            median: nearest_rank(&sorted, 0.50),
+            max: sorted[sorted.len() - 1],
+            positive: data.iter().filter(|rate| **rate > 0.0).count() as f64 / count,
+        }
+    }
+
+    /// The outcome at each percent of a histogram, cut the same way as `get_percentiles`
+    pub(crate) fn get_percentile_table(
+        results: &BTreeMap<i32, u32>,
+        percents: &[u32],
+    ) -> Vec<(u32, i32)> {
+        let total: u64 = results.values().map(|count| *count as u64).sum();
+        let mut table = Vec::new();
+        let mut count: u64 = 0;
+        let mut remaining = percents.iter().peekable();
+        for (outcome, outcome_count) in results {
+            count += *outcome_count as u64;
+            while let Some(percent) = remaining.next_if(|p| count >= total * **p as u64 / 100) {
+                table.push((*percent, *outcome));
+            }
+        }
+        table
+    }
+
+    /// Seed of one symbol's simulations, derived from the run seed and the symbol so symbols do not share draws
+    pub(crate) fn get_symbol_seed(seed: u64, symbol: &str) -> u64 {
+        // FNV-1a, stable across platforms and releases unlike the standard library hasher
+        let hash = symbol.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+        mix_seed(seed ^ hash)
+    }
+
+    // SplitMix64 finaliser, spreads seeds that differ in a few bits over the whole range
+    fn mix_seed(seed: u64) -> u64 {
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Runs one chunk of simulations and keeps the outcomes the way the quantile method asks for
+    pub(crate) fn simulate_chunk(
+        data: &[f64],
+        periods: u32,
+        number_of_simulations: u32,
+        quantile_method: QuantileMethod,
+        sampler: Sampler,
+        seed: u64,
+    ) -> OutcomeDistribution {
+        let mut results = OutcomeDistribution::new(quantile_method, seed);
+        let record = |simulation: &[f64]| match &mut results {
+            OutcomeDistribution::Exact(exact) => {
+                let calc = perform_simulation_calculation(simulation);
+                *exact.entry(calc).or_insert(0) += 1;
+            }
+            OutcomeDistribution::Sketch(sketch) => {
+                sketch.update(simulation_outcome(simulation));
+            }
+        };
+        draw_paths(data, periods, number_of_simulations, sampler, seed, record);
+        results
+    }
+
+    /// Draws the paths of one chunk with the sampler and hands each to `record`, the same seed always gives the
+    /// same paths in the same order
+    fn draw_paths(
+        data: &[f64],
+        periods: u32,
+        number_of_simulations: u32,
+        sampler: Sampler,
+        seed: u64,
+        mut record: impl FnMut(&[f64]),
+    ) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|left, right| left.total_cmp(right));
+
+        match sampler {
+            Sampler::Independent => {
+                for _ in 0..number_of_simulations {
+                    record(&simulate_period(&mut rng, data, periods));
+                }
+            }
+            Sampler::Antithetic => {
+                for _ in 0..number_of_simulations / 2 {
+                    let (simulation, mirror) = simulate_antithetic_pair(&mut rng, &sorted, periods);
+                    record(&simulation);
+                    record(&mirror);
+                }
+                // an odd count leaves one path without a partner
+                if number_of_simulations % 2 == 1 {
+                    record(&simulate_period(&mut rng, data, periods));
+                }
+            }
+            Sampler::Stratified => {
+                let mut remaining = number_of_simulations;
+                while remaining > 0 {
+                    let block = remaining.min(STRATIFIED_BLOCK_SIZE);
+                    for simulation in simulate_stratified_block(&mut rng, &sorted, periods, block) {
+                        record(&simulation);
+                    }
+                    remaining -= block;
+                }
+            }
+        }
+    }
+
+    /// Method that draws one path and its antithetic partner from returns sorted low to high, the partner takes the
+    /// return the same distance from the other end of the sorted returns on every day
+    pub(crate) fn simulate_antithetic_pair<R: Rng + ?Sized>(
+        rng: &mut R,
+        sorted: &[f64],
+        periods: u32,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let count = sorted.len();
+        let mut simulation = Vec::new();
+        let mut mirror = Vec::new();
+        if count == 0 {
+            return (simulation, mirror);
+        }
+        for _ in 0..periods {
+            let rnd_index = rng.random_range(0..count);
+            simulation.push(sorted[rnd_index]);
+            mirror.push(sorted[count - 1 - rnd_index]);
+        }
+        (simulation, mirror)
+    }
+
+    /// Method that draws a block of paths as a latin hypercube over returns sorted low to high: on every day the
+    /// sorted returns are cut into one equal slice per path and each slice is drawn from by exactly one path
+    pub(crate) fn simulate_stratified_block<R: Rng + ?Sized>(
+        rng: &mut R,
+        sorted: &[f64],
+        periods: u32,
+        paths: u32,
+    ) -> Vec<Vec<f64>> {
+        let count = sorted.len();
+        let mut simulations = vec![Vec::new(); paths as usize];
+        if count == 0 {
+            return simulations;
+        }
+
+        let mut strata: Vec<usize> = Vec::with_capacity(paths as usize);
+        for _ in 0..periods {
+            strata.clear();
+            for stratum in 0..paths {
+                let position = (stratum as f64 + rng.random::<f64>()) / paths as f64;
+                strata.push(((position * count as f64) as usize).min(count - 1));
+            }
+            strata.shuffle(rng);
+            for (simulation, index) in simulations.iter_mut().zip(strata.iter()) {
+                simulation.push(sorted[*index]);
+            }
+        }
+        simulations
+    }
+
+    /// Splits the simulations into at most `threads` chunks that add up to the requested number
+    pub(crate) fn get_chunk_sizes(number_of_simulations: u32, threads: u32) -> Vec<u32> {
+        let wanted = number_of_simulations.div_ceil(MIN_SIMULATIONS_PER_CHUNK);
+        let chunk_count = wanted.clamp(1, threads.max(1));
+        let size = number_of_simulations / chunk_count;
+        let remainder = number_of_simulations % chunk_count;
+
+        (0..chunk_count)
+            .map(|index| size + u32::from(index < remainder))
+            .collect()
+    }
+
+    fn available_threads() -> u32 {
+        thread::available_parallelism()
+            .map(|threads| threads.get() as u32)
+            .unwrap_or(1)
+    }
+
+    /// The 25th, 50th and 75th percentiles of a histogram of outcomes, the cut points come from the number of outcomes in the histogram itself
+    pub(crate) fn get_percentiles(results: &BTreeMap<i32, u32>) -> Option<Percentiles> {
+        if results.is_empty() {
             return None;
         }
 
+        let total: u64 = results.values().map(|count| *count as u64).sum();
         let mut steps = Vec::new();
         steps.push(total * 75 / 100);
         steps.push(total * 50 / 100);
         steps.push(total * 25 / 100);
 
-        let mut count: u32 = 0;
+        let mut count: u64 = 0;
         let mut test_val_opt = steps.pop();
         let mut pcts = Vec::new();
         for k_v in results.iter() {
@@ -80,8 +706,8 @@ pub mod simulations {
                 break;
             }
 
-            count += *k_v.1 as u32;
-            while !test_val_opt.is_none() && Some(count) >= test_val_opt {
+            count += *k_v.1 as u64;
+            while test_val_opt.is_some() && Some(count) >= test_val_opt {
                 pcts.push(k_v.0);
                 test_val_opt = steps.pop();
             }
@@ -95,21 +721,30 @@ pub mod simulations {
     }
 
     // Method that randomly chooses period results from the input data in preparation for a simulation calculation
-    pub(crate) fn simulate_period(input: &Vec<f64>, number_of_periods: u32) -> Vec<f64> {
+    pub(crate) fn simulate_period<R: Rng + ?Sized>(
+        rng: &mut R,
+        input: &[f64],
+        number_of_periods: u32,
+    ) -> Vec<f64> {
         let mut ret = Vec::new();
         let count = input.len();
         if count == 0 {
             return ret;
         }
         for _index in 0..number_of_periods {
-            let rnd_index = random_range(0..count);
+            let rnd_index = rng.random_range(0..count);
             ret.push(input[rnd_index]);
         }
         ret
     }
 
     // Method that returns the result from 1 simulation
-    pub(crate) fn perform_simulation_calculation(rates: &Vec<f64>) -> i32 {
+    pub(crate) fn perform_simulation_calculation(rates: &[f64]) -> i32 {
+        simulation_outcome(rates).round() as i32
+    }
+
+    // Method that returns the full precision gain or loss on 100 invested for 1 simulation
+    pub(crate) fn simulation_outcome(rates: &[f64]) -> f64 {
         let base_investment = 100.0;
         let mut investment = base_investment;
 
@@ -117,6 +752,6 @@ pub mod simulations {
             investment += investment * rate;
         }
 
-        (investment - base_investment).round() as i32
+        investment - base_investment
     }
 }
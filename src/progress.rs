@@ -0,0 +1,172 @@
+pub mod run_progress {
+    use std::{
+        io::{self, IsTerminal},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        logging::logger::NO_SYMBOL,
+        utilities::util::{log_phase, text_table},
+    };
+
+    /// How often the progress line is redrawn at most
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+
+    /// Time spent in each phase of a run, summed over every symbol and output
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    pub struct PhaseTimings {
+        /// reading and parsing the input files
+        pub load: Duration,
+        pub simulate: Duration,
+        /// picking the top x
+        pub rank: Duration,
+        /// building the contents of every output
+        pub render: Duration,
+        /// writing the outputs and the run history
+        pub save: Duration,
+    }
+
+    impl PhaseTimings {
+        /// Name and time of every phase in the order they run
+        pub fn phases(&self) -> [(&'static str, Duration); 5] {
+            [
+                ("load", self.load),
+                ("simulate", self.simulate),
+                ("rank", self.rank),
+                ("render", self.render),
+                ("save", self.save),
+            ]
+        }
+
+        pub fn total(&self) -> Duration {
+            self.phases().iter().map(|(_, duration)| *duration).sum()
+        }
+
+        pub fn add(&mut self, other: PhaseTimings) {
+            self.load += other.load;
+            self.simulate += other.simulate;
+            self.rank += other.rank;
+            self.render += other.render;
+            self.save += other.save;
+        }
+
+        /// Writes the time of every phase to the log as a timing of the whole run
+        pub fn log(&self) {
+            for (phase, duration) in self.phases() {
+                log_phase(NO_SYMBOL, phase, duration);
+            }
+        }
+    }
+
+    /// A table of the time and share of every phase with the total below
+    pub(crate) fn get_timing_text(timings: &PhaseTimings) -> String {
+        let total = timings.total().as_secs_f64();
+        let share = |duration: Duration| {
+            if total > 0.0 {
+                format!("{:.1}%", duration.as_secs_f64() / total * 100.0)
+            } else {
+                "0.0%".to_string()
+            }
+        };
+        let mut rows: Vec<Vec<String>> = timings
+            .phases()
+            .iter()
+            .map(|(phase, duration)| {
+                vec![
+                    phase.to_string(),
+                    format!("{:.3}", duration.as_secs_f64()),
+                    share(*duration),
+                ]
+            })
+            .collect();
+        rows.push(vec![
+            "total".to_string(),
+            format!("{total:.3}"),
+            share(timings.total()),
+        ]);
+        text_table(&["phase", "seconds", "share"], &rows)
+    }
+
+    /// e.g. `1h 02m`, `2m 05s` or `45s`
+    pub(crate) fn format_duration(duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        if seconds >= 3600 {
+            format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+        } else if seconds >= 60 {
+            format!("{}m {:02}s", seconds / 60, seconds % 60)
+        } else {
+            format!("{seconds}s")
+        }
+    }
+
+    /// e.g. `120/500 symbols, 3.4 symbols/s, ETA 1m 51s`, without throughput and ETA until a symbol is done
+    pub(crate) fn get_progress_text(done: usize, total: usize, elapsed: Duration) -> String {
+        let total = total.max(done);
+        let mut text = format!("{done}/{total} symbols");
+        let seconds = elapsed.as_secs_f64();
+        if done > 0 && seconds > 0.0 {
+            let throughput = done as f64 / seconds;
+            let remaining = (total - done) as f64 / throughput;
+            text.push_str(&format!(
+                ", {throughput:.1} symbols/s, ETA {}",
+                format_duration(Duration::from_secs_f64(remaining))
+            ));
+        }
+        text
+    }
+
+    /// A progress line on stderr redrawn as symbols finish, shown only when a person is watching
+    pub struct Progress {
+        total: usize,
+        done: usize,
+        started: Instant,
+        last_drawn: Option<Instant>,
+        visible: bool,
+    }
+
+    impl Progress {
+        pub fn new(total: usize) -> Progress {
+            Progress {
+                total,
+                done: 0,
+                started: Instant::now(),
+                last_drawn: None,
+                visible: io::stderr().is_terminal(),
+            }
+        }
+
+        pub fn is_visible(&self) -> bool {
+            self.visible
+        }
+
+        /// Counts one more symbol as done
+        pub fn advance(&mut self) {
+            self.done += 1;
+            let due = self
+                .last_drawn
+                .is_none_or(|drawn| drawn.elapsed() >= REDRAW_INTERVAL);
+            if due || self.done >= self.total {
+                self.draw();
+            }
+        }
+
+        fn draw(&mut self) {
+            if !self.visible {
+                return;
+            }
+            // the trailing spaces clear what is left of a longer line drawn before
+            eprint!(
+                "\r{}    ",
+                get_progress_text(self.done, self.total, self.started.elapsed())
+            );
+            self.last_drawn = Some(Instant::now());
+        }
+
+        /// Ends the progress line so what is printed next starts on its own line
+        pub fn finish(&mut self) {
+            if self.visible && self.last_drawn.is_some() {
+                eprintln!();
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+pub mod distributions {
+    use std::{collections::BTreeMap, fmt, str::FromStr};
+
+    use crate::monte_carlo::simulations::{get_percentile_table, get_percentiles, Percentiles};
+
+    /// Default accuracy parameter for the KLL sketch
+    pub const DEFAULT_SKETCH_K: usize = 200;
+
+    /// Smallest capacity a compactor level is allowed to shrink to
+    const MIN_LEVEL_CAPACITY: usize = 8;
+
+    /// How the simulation outcomes of a symbol are kept until the percentiles are calculated
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    pub enum QuantileMethod {
+        /// every distinct rounded outcome is counted, percentiles are exact
+        #[default]
+        Exact,
+        /// outcomes are kept at full precision in a KLL sketch with the given accuracy parameter
+        Sketch(usize),
+    }
+
+    impl FromStr for QuantileMethod {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "exact" => Ok(QuantileMethod::Exact),
+                "sketch" => Ok(QuantileMethod::Sketch(DEFAULT_SKETCH_K)),
+                _ => match s.strip_prefix("sketch:").map(|k| k.parse::<usize>()) {
+                    Some(Ok(k)) if k >= MIN_LEVEL_CAPACITY => Ok(QuantileMethod::Sketch(k)),
+                    _ => Err(format!(
+                        "unknown quantile method {s}, expected exact, sketch or sketch:<k> with k >= {MIN_LEVEL_CAPACITY}"
+                    )),
+                },
+            }
+        }
+    }
+
+    impl fmt::Display for QuantileMethod {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                QuantileMethod::Exact => write!(f, "exact"),
+                QuantileMethod::Sketch(k) => write!(f, "sketch:{k}"),
+            }
+        }
+    }
+
+    /// The outcomes of a set of simulations for one symbol
+    #[derive(Debug, Clone)]
+    pub enum OutcomeDistribution {
+        Exact(BTreeMap<i32, u32>),
+        Sketch(KllSketch),
+    }
+
+    impl OutcomeDistribution {
+        /// the seed only matters to a sketch, give each chunk of simulations its own
+        pub fn new(method: QuantileMethod, seed: u64) -> OutcomeDistribution {
+            match method {
+                QuantileMethod::Exact => OutcomeDistribution::Exact(BTreeMap::new()),
+                QuantileMethod::Sketch(k) => OutcomeDistribution::Sketch(KllSketch::new(k, seed)),
+            }
+        }
+
+        /// keeps one outcome, an exact distribution counts it rounded to a whole number
+        pub fn record(&mut self, outcome: f64) {
+            match self {
+                OutcomeDistribution::Exact(results) => {
+                    *results.entry(outcome.round() as i32).or_insert(0) += 1;
+                }
+                OutcomeDistribution::Sketch(sketch) => sketch.update(outcome),
+            }
+        }
+
+        /// combines the outcomes of another chunk of simulations into this one
+        pub fn merge(&mut self, other: OutcomeDistribution) {
+            match (self, other) {
+                (OutcomeDistribution::Exact(results), OutcomeDistribution::Exact(other)) => {
+                    for (outcome, count) in other {
+                        *results.entry(outcome).or_insert(0) += count;
+                    }
+                }
+                (OutcomeDistribution::Sketch(sketch), OutcomeDistribution::Sketch(other)) => {
+                    sketch.merge(other)
+                }
+                _ => panic!("cannot merge an exact distribution with a sketch"),
+            }
+        }
+
+        /// number of simulations recorded
+        pub fn count(&self) -> u64 {
+            match self {
+                OutcomeDistribution::Exact(results) => {
+                    results.values().map(|count| *count as u64).sum()
+                }
+                OutcomeDistribution::Sketch(sketch) => sketch.count(),
+            }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.count() == 0
+        }
+
+        /// number of simulations for every outcome rounded to a whole number, a sketch gives the weighted count
+        /// of the values it kept so the shape is approximate
+        pub fn histogram(&self) -> BTreeMap<i32, u32> {
+            match self {
+                OutcomeDistribution::Exact(results) => results.clone(),
+                OutcomeDistribution::Sketch(sketch) => {
+                    let mut histogram = BTreeMap::new();
+                    for (value, weight) in sketch.sorted_items() {
+                        *histogram.entry(value.round() as i32).or_insert(0) += weight as u32;
+                    }
+                    histogram
+                }
+            }
+        }
+
+        /// the outcome at each percent, cut the same way as the percentiles, empty without outcomes
+        pub fn percents(&self, percents: &[u32]) -> Vec<f64> {
+            match self {
+                OutcomeDistribution::Exact(results) => get_percentile_table(results, percents)
+                    .into_iter()
+                    .map(|(_, outcome)| outcome as f64)
+                    .collect(),
+                OutcomeDistribution::Sketch(sketch) => percents
+                    .iter()
+                    .filter_map(|percent| sketch.quantile(*percent as f64 / 100.0))
+                    .collect(),
+            }
+        }
+
+        /// the 25th, 50th and 75th percentiles of the recorded outcomes
+        pub fn percentiles(&self) -> Option<Percentiles> {
+            match self {
+                OutcomeDistribution::Exact(results) => get_percentiles(results),
+                OutcomeDistribution::Sketch(sketch) => Some(Percentiles {
+                    _25th: sketch.quantile(0.25)?.round() as i32,
+                    _50th: sketch.quantile(0.50)?.round() as i32,
+                    _75th: sketch.quantile(0.75)?.round() as i32,
+                }),
+            }
+        }
+    }
+
+    /// Streaming quantile sketch after Karnin, Lang and Liberty, "Optimal Quantile Approximation in Streams" (2016).
+    ///
+    /// Items are kept in a stack of compactors where an item on level h stands for 2^h outcomes. When a level
+    /// grows past its capacity it is sorted and every other item is promoted to the level above. The top level
+    /// holds k items and each level below holds 2/3 of the one above it (but at least 8), so memory stays around
+    /// 3k items no matter how many outcomes are recorded.
+    ///
+    /// Error bound: Apache DataSketches publishes 2.296 / k^0.9723 of the requested rank with 99% confidence for
+    /// its implementation of the same algorithm, +/- 1.33% for the default k of 200. That figure is borrowed, not
+    /// derived for this code; the tests only check that single and merged sketches stay inside it on their
+    /// streams. It relies on every sketch flipping its own coin, so each one is seeded separately.
+    #[derive(Debug, Clone)]
+    pub struct KllSketch {
+        k: usize,
+        levels: Vec<Vec<f64>>,
+        count: u64,
+        coin: u64,
+    }
+
+    impl KllSketch {
+        pub fn new(k: usize, seed: u64) -> KllSketch {
+            KllSketch {
+                k: k.max(MIN_LEVEL_CAPACITY),
+                levels: vec![Vec::new()],
+                count: 0,
+                // xorshift never leaves zero
+                coin: (seed ^ 0x9E37_79B9_7F4A_7C15).max(1),
+            }
+        }
+
+        pub fn count(&self) -> u64 {
+            self.count
+        }
+
+        pub fn update(&mut self, value: f64) {
+            self.levels[0].push(value);
+            self.count += 1;
+            self.compress();
+        }
+
+        /// folds another sketch into this one, the result is as accurate as if one sketch had seen every value
+        pub fn merge(&mut self, other: KllSketch) {
+            self.k = self.k.max(other.k);
+            while self.levels.len() < other.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            for (level, items) in other.levels.into_iter().enumerate() {
+                self.levels[level].extend(items);
+            }
+            self.count += other.count;
+            self.compress();
+        }
+
+        /// the smallest retained value whose rank is at least `fraction` of all recorded values
+        pub fn quantile(&self, fraction: f64) -> Option<f64> {
+            let weighted = self.sorted_items();
+            let target = fraction * self.count as f64;
+            let mut rank: u64 = 0;
+            for (value, weight) in weighted.iter() {
+                rank += weight;
+                if rank as f64 >= target {
+                    return Some(*value);
+                }
+            }
+            weighted.last().map(|(value, _)| *value)
+        }
+
+        /// every retained value together with the number of outcomes it stands for, sorted by value
+        pub fn sorted_items(&self) -> Vec<(f64, u64)> {
+            let mut weighted: Vec<(f64, u64)> = self
+                .levels
+                .iter()
+                .enumerate()
+                .flat_map(|(level, items)| items.iter().map(move |value| (*value, 1u64 << level)))
+                .collect();
+            weighted.sort_by(|left, right| left.0.total_cmp(&right.0));
+            weighted
+        }
+
+        fn capacity(&self, level: usize) -> usize {
+            let depth = (self.levels.len() - level - 1) as i32;
+            let capacity = (self.k as f64 * (2.0f64 / 3.0).powi(depth)).ceil() as usize;
+            capacity.max(MIN_LEVEL_CAPACITY)
+        }
+
+        fn compress(&mut self) {
+            let mut level = 0;
+            while level < self.levels.len() {
+                if self.levels[level].len() >= self.capacity(level) {
+                    if level + 1 == self.levels.len() {
+                        self.levels.push(Vec::new());
+                    }
+
+                    let mut items = std::mem::take(&mut self.levels[level]);
+                    items.sort_by(|left, right| left.total_cmp(right));
+                    // an odd item out stays behind so no weight is lost
+                    if items.len() % 2 == 1 {
+                        let leftover = items.remove(0);
+                        self.levels[level].push(leftover);
+                    }
+                    let offset = self.flip() as usize;
+                    let promoted = items.into_iter().skip(offset).step_by(2);
+                    self.levels[level + 1].extend(promoted);
+                }
+                level += 1;
+            }
+        }
+
+        // xorshift coin so compactions pick the odd or even items without a shared random source
+        fn flip(&mut self) -> bool {
+            self.coin ^= self.coin << 13;
+            self.coin ^= self.coin >> 7;
+            self.coin ^= self.coin << 17;
+            self.coin & 1 == 1
+        }
+    }
+}
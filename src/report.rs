@@ -0,0 +1,508 @@
+pub mod run_report {
+    use std::{
+        fmt, fs,
+        path::{Path, PathBuf},
+        str::FromStr,
+    };
+
+    use chrono::Utc;
+    use serde::{Deserialize, Serialize};
+    use structopt::StructOpt;
+
+    use crate::{
+        monte_carlo::simulations::{Percentiles, Prediction, SimulationSettings},
+        stock_simulation::stock_simulator::{
+            get_ranks, get_thresholds, HighestLow, MostCommonResult, PredictionManipulation,
+            Rating, SimulationError, Strategy, TopPredictions, TotalSpan, WeightedSpan,
+        },
+    };
+
+    /// Version of the machine readable layout, bumped whenever a field is renamed, removed or changes type.
+    /// Adding a field does not change the version. The layout is described in doc/predictions.schema.json.
+    pub const SCHEMA_VERSION: u32 = 1;
+
+    /// The ways the results of a run can be written out
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum OutputFormat {
+        Html,
+        Json,
+        Csv,
+        Xlsx,
+        Markdown,
+        /// every symbol in one sortable and filterable html table
+        HtmlTable,
+        /// printed to the terminal instead of written to the output file
+        Table,
+    }
+
+    impl OutputFormat {
+        pub fn extension(&self) -> &'static str {
+            match self {
+                OutputFormat::Html => "html",
+                OutputFormat::Json => "json",
+                OutputFormat::Csv => "csv",
+                OutputFormat::Xlsx => "xlsx",
+                OutputFormat::Markdown => "md",
+                OutputFormat::HtmlTable => "table.html",
+                OutputFormat::Table => "txt",
+            }
+        }
+
+        pub fn is_file(&self) -> bool {
+            *self != OutputFormat::Table
+        }
+    }
+
+    impl FromStr for OutputFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "html" => Ok(OutputFormat::Html),
+                "json" => Ok(OutputFormat::Json),
+                "csv" => Ok(OutputFormat::Csv),
+                "xlsx" => Ok(OutputFormat::Xlsx),
+                "markdown" | "md" => Ok(OutputFormat::Markdown),
+                "html-table" => Ok(OutputFormat::HtmlTable),
+                "table" => Ok(OutputFormat::Table),
+                _ => Err(format!(
+                    "unknown format {s}, expected html, json, csv, xlsx, markdown, html-table or table"
+                )),
+            }
+        }
+    }
+
+    impl fmt::Display for OutputFormat {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                OutputFormat::Markdown => write!(f, "markdown"),
+                OutputFormat::HtmlTable => write!(f, "html-table"),
+                OutputFormat::Table => write!(f, "table"),
+                _ => write!(f, "{}", self.extension()),
+            }
+        }
+    }
+
+    /// Which outputs a run writes and how the reports are branded
+    #[derive(Debug, Clone, Default)]
+    pub struct OutputSettings {
+        pub formats: Vec<OutputFormat>,
+        /// stylesheet used by the html reports in place of the built-in one
+        pub css: Option<String>,
+        /// template rendered in place of the built-in html index page
+        pub template: Option<String>,
+        pub header: Option<String>,
+        pub disclaimer: Option<String>,
+        /// directory every run is also saved to so it can be listed and exported again later
+        pub history: Option<PathBuf>,
+        /// config file and profile the options were read from
+        pub config: Option<String>,
+        /// every option the run resolved to, as written to the log
+        pub options: Option<String>,
+    }
+
+    impl OutputSettings {
+        /// The stylesheet the html reports embed
+        pub fn style(&self) -> &str {
+            self.css.as_deref().unwrap_or(include_str!("style.css"))
+        }
+    }
+
+    /// Parameters of the run the predictions came from
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct RunMetadata {
+        pub days: u32,
+        /// simulations per symbol, or the most any symbol could use in an adaptive run
+        pub simulations: u32,
+        pub adaptive_tolerance: Option<f64>,
+        pub batch_size: Option<u32>,
+        pub quantiles: String,
+        pub sampler: String,
+        pub seed: u64,
+        pub top_x: usize,
+        /// metric the top x was picked by
+        #[serde(default)]
+        pub strategy: Strategy,
+        pub source_dir: String,
+        /// RFC 3339 time the results were written
+        pub timestamp: String,
+        /// version of stock_simulator that produced the results
+        pub version: String,
+        /// company header shown at the top of every report
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub header: Option<String>,
+        /// disclaimer shown at the foot of every report
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub disclaimer: Option<String>,
+        /// config file and profile the options were read from, e.g. `runs.toml [weekly]`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub config: Option<String>,
+        /// every option the run resolved to from the command line and config file, as written to the log, e.g.
+        /// `source-dir = "data"; days = 30; ...`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub options: Option<String>,
+    }
+
+    impl RunMetadata {
+        pub fn new(source_dir: &Path, settings: &SimulationSettings, top_x: usize) -> RunMetadata {
+            RunMetadata {
+                days: settings.periods,
+                simulations: settings.number_of_simulations,
+                adaptive_tolerance: settings.convergence.map(|c| c.tolerance),
+                batch_size: settings.convergence.map(|c| c.batch_size),
+                quantiles: settings.quantile_method.to_string(),
+                sampler: settings.sampler.to_string(),
+                seed: settings.seed,
+                top_x,
+                strategy: Strategy::default(),
+                source_dir: source_dir.display().to_string(),
+                timestamp: Utc::now().to_rfc3339(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                header: None,
+                disclaimer: None,
+                config: None,
+                options: None,
+            }
+        }
+
+        /// Name and value of every run parameter in the order the reports list them
+        pub fn parameters(&self) -> Vec<(&'static str, String)> {
+            let optional = |value: Option<String>| value.unwrap_or_else(|| "n/a".to_string());
+            vec![
+                ("days", self.days.to_string()),
+                ("simulations", self.simulations.to_string()),
+                (
+                    "adaptive tolerance",
+                    optional(self.adaptive_tolerance.map(|t| t.to_string())),
+                ),
+                (
+                    "batch size",
+                    optional(self.batch_size.map(|b| b.to_string())),
+                ),
+                ("quantiles", self.quantiles.clone()),
+                ("sampler", self.sampler.clone()),
+                ("seed", self.seed.to_string()),
+                ("top x", self.top_x.to_string()),
+                ("strategy", self.strategy.to_string()),
+                ("source directory", self.source_dir.clone()),
+                ("timestamp", self.timestamp.clone()),
+                ("version", self.version.clone()),
+                ("config", optional(self.config.clone())),
+                ("options", optional(self.options.clone())),
+            ]
+        }
+    }
+
+    /// Every metric of one simulated symbol, with its rank under each ordering
+    #[derive(Debug)]
+    pub struct SymbolRow {
+        pub symbol: String,
+        pub observations: usize,
+        pub simulations: u32,
+        pub percentiles: Percentiles,
+        pub most_common: i32,
+        pub highest_low: i32,
+        pub total_span: i32,
+        pub weighted_span: i32,
+        pub most_common_rank: usize,
+        pub highest_low_rank: usize,
+        pub total_span_rank: usize,
+        pub weighted_span_rank: usize,
+    }
+
+    /// One row per simulated symbol in most common result order
+    pub(crate) fn get_symbol_rows(predictions: &Vec<Prediction>) -> Vec<SymbolRow> {
+        let most_common_ranks = get_ranks(predictions, Box::new(MostCommonResult {}));
+        let highest_low_ranks = get_ranks(predictions, Box::new(HighestLow {}));
+        let total_span_ranks = get_ranks(predictions, Box::new(TotalSpan {}));
+        let weighted_span_ranks = get_ranks(predictions, Box::new(WeightedSpan {}));
+
+        let mut rows: Vec<SymbolRow> = predictions
+            .iter()
+            .map(|prediction| {
+                let symbol = &prediction.symbol;
+                SymbolRow {
+                    symbol: symbol.clone(),
+                    observations: prediction.observations,
+                    simulations: prediction.simulations,
+                    percentiles: prediction.percentiles.clone(),
+                    most_common: MostCommonResult {}.calculation(prediction),
+                    highest_low: HighestLow {}.calculation(prediction),
+                    total_span: TotalSpan {}.calculation(prediction),
+                    weighted_span: WeightedSpan {}.calculation(prediction),
+                    most_common_rank: most_common_ranks[symbol],
+                    highest_low_rank: highest_low_ranks[symbol],
+                    total_span_rank: total_span_ranks[symbol],
+                    weighted_span_rank: weighted_span_ranks[symbol],
+                }
+            })
+            .collect();
+        rows.sort_by_key(|row| row.most_common_rank);
+        rows
+    }
+
+    /// A metric of one of the top x with its rating against the rest of the top x
+    #[derive(Debug, PartialEq, Serialize)]
+    pub struct RatedValue {
+        pub value: i32,
+        pub rating: Rating,
+    }
+
+    /// What the html, markdown and table renderers show for one of the top x, built once so they never disagree
+    #[derive(Debug, PartialEq, Serialize)]
+    pub struct PredictionView {
+        pub rank: usize,
+        pub symbol: String,
+        pub most_common: RatedValue,
+        pub highest_low: RatedValue,
+        pub total_span: RatedValue,
+        pub weighted_span: RatedValue,
+        pub simulations: u32,
+        pub notes: Vec<String>,
+    }
+
+    /// The top x in ranked order, rated against each other
+    pub(crate) fn get_prediction_views(
+        calcs: &[TopPredictions],
+        metadata: &RunMetadata,
+    ) -> Vec<PredictionView> {
+        let thresholds = get_thresholds(calcs);
+        calcs
+            .iter()
+            .enumerate()
+            .map(|(index, pred)| PredictionView {
+                rank: index + 1,
+                symbol: pred.symbol.clone(),
+                most_common: RatedValue {
+                    value: pred.most_common,
+                    rating: thresholds.most_common(pred.most_common),
+                },
+                highest_low: RatedValue {
+                    value: pred.highest_low,
+                    rating: thresholds.highest_low(pred.highest_low),
+                },
+                total_span: RatedValue {
+                    value: pred.total_span,
+                    rating: thresholds.total_span(pred.total_span),
+                },
+                weighted_span: RatedValue {
+                    value: pred.weighted_span,
+                    rating: thresholds.weighted_span(pred.weighted_span),
+                },
+                simulations: pred.simulations,
+                notes: get_notes(pred, metadata),
+            })
+            .collect()
+    }
+
+    /// Plain sentences about the shape of a prediction, outcomes are the gain or loss on 100 invested
+    pub(crate) fn get_notes(pred: &TopPredictions, metadata: &RunMetadata) -> Vec<String> {
+        let mut notes = Vec::new();
+        if pred.highest_low < 0 {
+            notes.push(format!(
+                "a quarter of the simulations lost more than {} on 100 invested",
+                -pred.highest_low
+            ));
+        } else {
+            notes.push(format!(
+                "three quarters of the simulations gained at least {} on 100 invested",
+                pred.highest_low
+            ));
+        }
+        notes.push(
+            match pred.weighted_span.cmp(&0) {
+                std::cmp::Ordering::Greater => "skewed up, the 75th percentile is further above the median than the 25th is below it",
+                std::cmp::Ordering::Less => "skewed down, the 25th percentile is further below the median than the 75th is above it",
+                std::cmp::Ordering::Equal => "even, the 25th and 75th percentiles are the same distance from the median",
+            }
+            .to_string(),
+        );
+        if pred.simulations < metadata.simulations {
+            notes.push(format!(
+                "converged after {} of {} simulations",
+                pred.simulations, metadata.simulations
+            ));
+        }
+        notes
+    }
+
+    /// Everything a run produced, the document behind the json output
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct RunResults {
+        pub schema_version: u32,
+        pub metadata: RunMetadata,
+        /// every symbol that was simulated
+        pub predictions: Vec<Prediction>,
+        /// the top x in ranked order
+        pub top_predictions: Vec<TopPredictions>,
+        /// what happened to every input file, runs saved before it was added read as complete
+        #[serde(default)]
+        pub summary: RunSummary,
+    }
+
+    /// How far a run got
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum RunStatus {
+        /// every input file was simulated
+        #[default]
+        Complete,
+        /// some files were skipped or failed, the predictions hold the rest
+        Partial,
+        /// the run was aborted, the predictions hold the symbols simulated before it stopped
+        Failed,
+    }
+
+    impl RunStatus {
+        pub fn name(&self) -> &'static str {
+            match self {
+                RunStatus::Complete => "complete",
+                RunStatus::Partial => "partial",
+                RunStatus::Failed => "failed",
+            }
+        }
+    }
+
+    /// An input file that was not simulated and why
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct SymbolIssue {
+        pub symbol: String,
+        pub reason: String,
+    }
+
+    /// What happened to every input file of a run
+    #[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct RunSummary {
+        pub status: RunStatus,
+        /// symbols that were simulated, in the order they were read
+        pub processed: Vec<String>,
+        /// files left in the input directory without being read
+        pub skipped: Vec<SymbolIssue>,
+        /// files that could not be read, parsed or simulated
+        pub failed: Vec<SymbolIssue>,
+        /// why the run stopped before reading every file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub aborted: Option<String>,
+    }
+
+    impl RunSummary {
+        /// The status the counts and the abort reason add up to
+        pub fn get_status(&self) -> RunStatus {
+            if self.aborted.is_some() {
+                RunStatus::Failed
+            } else if self.skipped.is_empty() && self.failed.is_empty() {
+                RunStatus::Complete
+            } else {
+                RunStatus::Partial
+            }
+        }
+
+        /// e.g. `48 processed, 0 skipped, 2 failed`
+        pub fn counts(&self) -> String {
+            format!(
+                "{} processed, {} skipped, {} failed",
+                self.processed.len(),
+                self.skipped.len(),
+                self.failed.len()
+            )
+        }
+    }
+
+    pub(crate) fn get_json(results: &RunResults) -> String {
+        serde_json::to_string_pretty(results).expect("run results always serialize")
+    }
+
+    /// Reads results written by the json output
+    pub(crate) fn read_results(path: &Path) -> Result<RunResults, SimulationError> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())))?;
+        serde_json::from_str(&json)
+            .map_err(|e| SimulationError::Parse(format!("{}: {e}", path.display())))
+    }
+
+    /// Where a format is written: the output file itself when it is the only file format, otherwise the output
+    /// file with the extension of the format
+    pub(crate) fn get_output_path(output: &Path, format: OutputFormat, formats: usize) -> PathBuf {
+        if formats <= 1 {
+            output.to_path_buf()
+        } else {
+            output.with_extension(format.extension())
+        }
+    }
+
+    /// Directory the per symbol pages of an html index are written to, `<stem>_files` beside the index so the
+    /// two never share a name, even when the index has no extension, and can be zipped together
+    pub(crate) fn get_details_dir(index: &Path) -> PathBuf {
+        let stem = index
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        index.with_file_name(format!("{stem}_files"))
+    }
+
+    /// Company header and disclaimer of the pages written by study, backtest, calibrate and compare
+    #[derive(Debug, Default, Clone, PartialEq, StructOpt)]
+    pub struct Branding {
+        /// company header shown at the top of the html page
+        #[structopt(long)]
+        pub header: Option<String>,
+        /// disclaimer shown at the foot of the html page
+        #[structopt(long)]
+        pub disclaimer: Option<String>,
+    }
+
+    /// The company header of the html reports, empty without one
+    pub(crate) fn get_header_html(header: Option<&str>) -> String {
+        match header {
+            Some(header) => format!(
+                "<div class=\"company-header\">{}</div>",
+                build_html::escape_html(header)
+            ),
+            None => String::new(),
+        }
+    }
+
+    /// The run summary footer of the html reports: the counts, why the run was aborted and every symbol that was
+    /// skipped or failed with the reason
+    pub(crate) fn get_summary_html(summary: &RunSummary) -> String {
+        let mut html = format!(
+            "<footer class=\"run-summary {}\"><p>Run {}: {}.</p>",
+            summary.status.name(),
+            summary.status.name(),
+            summary.counts()
+        );
+        if let Some(reason) = &summary.aborted {
+            html.push_str(&format!(
+                "<p class=\"aborted\">Aborted: {}</p>",
+                build_html::escape_html(reason)
+            ));
+        }
+        for (title, issues) in [("Skipped", &summary.skipped), ("Failed", &summary.failed)] {
+            if issues.is_empty() {
+                continue;
+            }
+            html.push_str(&format!("<p>{title}</p><ul>"));
+            for issue in issues {
+                html.push_str(&format!(
+                    "<li><strong>{}</strong>: {}</li>",
+                    build_html::escape_html(&issue.symbol),
+                    build_html::escape_html(&issue.reason)
+                ));
+            }
+            html.push_str("</ul>");
+        }
+        html.push_str("</footer>");
+        html
+    }
+
+    /// The disclaimer footer of the html reports, empty without one
+    pub(crate) fn get_disclaimer_html(disclaimer: Option<&str>) -> String {
+        match disclaimer {
+            Some(disclaimer) => format!(
+                "<footer class=\"disclaimer\">{}</footer>",
+                build_html::escape_html(disclaimer)
+            ),
+            None => String::new(),
+        }
+    }
+}
@@ -1,33 +1,112 @@
 pub mod stock_simulator {
     use std::{
+        collections::HashMap,
         error::Error,
         fmt,
         fs::{self, DirEntry, File},
-        io::{self, ErrorKind, Write},
-        path::PathBuf,
+        io::{self, IsTerminal, Write},
+        path::{Path, PathBuf},
+        str::FromStr,
+        time::Instant,
         vec,
     };
 
     use build_html::{Container, ContainerType, Html, HtmlContainer, HtmlPage};
     use itertools::Itertools;
+    use serde::{Deserialize, Serialize};
 
     use crate::{
-        monte_carlo::simulations::{self, Prediction},
-        utilities::util::log,
+        charts::svg_charts::{get_fan_chart_svg, get_histogram_svg},
+        csv_output::csv_report::get_csv,
+        detail_output::detail_report::get_detail_html,
+        history::run_history::save_run,
+        interactive_output::interactive_report::get_interactive_html,
+        logging::logger::NO_SYMBOL,
+        markdown_output::markdown_report::get_markdown,
+        monte_carlo::simulations::{self, get_path_summary, Prediction, SimulationSettings},
+        progress::run_progress::{get_timing_text, PhaseTimings, Progress},
+        report::run_report::{
+            get_details_dir, get_disclaimer_html, get_header_html, get_json, get_output_path,
+            get_prediction_views, get_summary_html, OutputFormat, OutputSettings, RunMetadata,
+            RunResults, RunSummary, SymbolIssue, SCHEMA_VERSION,
+        },
+        table_output::table_report::get_table,
+        template::templates::{get_template_context, render_template},
+        utilities::util::{log, log_debug, log_phase, log_warning},
+        xlsx_output::xlsx_report::get_xlsx,
     };
 
-    #[derive(Debug)]
-    pub struct SimulationError {
-        kind: ErrorKind,
-        message: String,
+    /// Everything that can stop a subcommand, each kind exits the process with its own code
+    #[derive(Debug, PartialEq)]
+    pub enum SimulationError {
+        /// a file or directory could not be read or written
+        Io(String),
+        /// an input file holds something other than what was expected
+        Parse(String),
+        /// an argument points somewhere that cannot be used
+        Validation(String),
+        /// the options or the config file are incomplete or wrong
+        Config(String),
+        /// the input directory held nothing to simulate
+        NoInput(String),
+        /// some symbols failed or were skipped, the outputs hold the rest
+        PartialFailure {
+            failed: usize,
+            skipped: usize,
+            total: usize,
+        },
+        /// the run stopped early after more failures than allowed, the outputs mark it failed
+        Aborted(String),
+    }
+
+    impl SimulationError {
+        /// Process exit code, 0 is left for success
+        pub fn exit_code(&self) -> i32 {
+            match self {
+                SimulationError::Config(_) => 2,
+                SimulationError::Validation(_) => 3,
+                SimulationError::Io(_) => 4,
+                SimulationError::Parse(_) => 5,
+                SimulationError::NoInput(_) => 6,
+                SimulationError::PartialFailure { .. } => 7,
+                SimulationError::Aborted(_) => 8,
+            }
+        }
     }
 
     impl Error for SimulationError {}
 
+    /// A top x of zero picks nothing, so every average and overlap over it would divide by zero
+    pub(crate) fn validate_top_x(top_x: usize) -> Result<(), SimulationError> {
+        if top_x == 0 {
+            return Err(SimulationError::Validation(
+                "the top x must pick at least one symbol".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
     impl fmt::Display for SimulationError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            let output = format!("{}: {}", self.kind, self.message);
-            write!(f, "{output}")
+            match self {
+                SimulationError::Io(message) => write!(f, "io error: {message}"),
+                SimulationError::Parse(message) => write!(f, "parse error: {message}"),
+                SimulationError::Validation(message) => write!(f, "invalid argument: {message}"),
+                SimulationError::Config(message) => write!(f, "configuration error: {message}"),
+                SimulationError::NoInput(message) => write!(f, "no input: {message}"),
+                SimulationError::PartialFailure {
+                    failed,
+                    skipped,
+                    total,
+                } => {
+                    write!(f, "partial failure: {failed} of {total} symbols failed")?;
+                    if *skipped > 0 {
+                        write!(f, ", {skipped} skipped")?;
+                    }
+                    Ok(())
+                }
+                SimulationError::Aborted(message) => write!(f, "run aborted: {message}"),
+            }
         }
     }
 
@@ -106,13 +185,65 @@ pub mod stock_simulator {
         }
     }
 
-    #[derive(Debug, PartialEq)]
+    /// Which metric the top x is picked by
+    #[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum Strategy {
+        #[default]
+        MostCommon,
+        HighestLow,
+        TotalSpan,
+        WeightedSpan,
+    }
+
+    impl Strategy {
+        pub fn ordering(&self) -> Box<dyn PredictionManipulation> {
+            match self {
+                Strategy::MostCommon => Box::new(MostCommonResult {}),
+                Strategy::HighestLow => Box::new(HighestLow {}),
+                Strategy::TotalSpan => Box::new(TotalSpan {}),
+                Strategy::WeightedSpan => Box::new(WeightedSpan {}),
+            }
+        }
+    }
+
+    impl FromStr for Strategy {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "most-common" => Ok(Strategy::MostCommon),
+                "highest-low" => Ok(Strategy::HighestLow),
+                "total-span" => Ok(Strategy::TotalSpan),
+                "weighted-span" => Ok(Strategy::WeightedSpan),
+                _ => Err(format!(
+                    "unknown strategy {s}, expected most-common, highest-low, total-span or weighted-span"
+                )),
+            }
+        }
+    }
+
+    impl fmt::Display for Strategy {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                Strategy::MostCommon => "most-common",
+                Strategy::HighestLow => "highest-low",
+                Strategy::TotalSpan => "total-span",
+                Strategy::WeightedSpan => "weighted-span",
+            };
+            write!(f, "{name}")
+        }
+    }
+
+    #[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
     pub struct TopPredictions {
         pub symbol: String,
         pub most_common: i32,
         pub highest_low: i32,
         pub total_span: i32,
         pub weighted_span: i32,
+        /// number of simulations behind the prediction
+        pub simulations: u32,
     }
 
     #[derive(PartialEq, Debug)]
@@ -125,175 +256,532 @@ pub mod stock_simulator {
         pub total_span_yellow: i32,
     }
 
+    /// Where a metric falls against the thresholds, shown as green, yellow or red by every renderer
+    #[derive(PartialEq, Debug, Clone, Copy, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Rating {
+        Green,
+        Yellow,
+        Red,
+    }
+
+    impl Rating {
+        /// the css class, also used as the plain text name
+        pub fn class(&self) -> &'static str {
+            match self {
+                Rating::Green => "green",
+                Rating::Yellow => "yellow",
+                Rating::Red => "red",
+            }
+        }
+    }
+
+    impl Thresholds {
+        pub fn most_common(&self, value: i32) -> Rating {
+            if value >= self.most_common_green {
+                Rating::Green
+            } else if value < self.most_common_yellow {
+                Rating::Red
+            } else {
+                Rating::Yellow
+            }
+        }
+
+        pub fn highest_low(&self, value: i32) -> Rating {
+            if value >= self.highest_low_green {
+                Rating::Green
+            } else if value < self.highest_low_yellow {
+                Rating::Red
+            } else {
+                Rating::Yellow
+            }
+        }
+
+        pub fn total_span(&self, value: i32) -> Rating {
+            if value <= self.total_span_green {
+                Rating::Green
+            } else if value > self.total_span_yellow {
+                Rating::Red
+            } else {
+                Rating::Yellow
+            }
+        }
+
+        /// the weighted span is rated by its sign alone
+        pub fn weighted_span(&self, value: i32) -> Rating {
+            match value.cmp(&0) {
+                std::cmp::Ordering::Greater => Rating::Green,
+                std::cmp::Ordering::Less => Rating::Red,
+                std::cmp::Ordering::Equal => Rating::Yellow,
+            }
+        }
+    }
+
+    /// Simulates every symbol in the directory and writes the outputs with a summary of what happened to every
+    /// file. The outputs are still written when some symbols fail, the error then says how many. More failures
+    /// than `max_failures` stops the run early and marks it failed.
     pub fn run_simulator(
-        dir: &PathBuf,
-        periods: u32,
-        number_of_simulations: u32,
+        dir: &Path,
+        settings: &SimulationSettings,
         top_x: usize,
-        output_html: &PathBuf,
-    ) {
-        // get the path to the next file to be processed
-        let mut symbol_file_opt = get_next_file(&dir);
+        strategy: Strategy,
+        output: &Path,
+        output_settings: &OutputSettings,
+        max_failures: Option<usize>,
+    ) -> Result<(), SimulationError> {
+        let periods = settings.periods;
+        let number_of_simulations = settings.number_of_simulations;
 
-        let mut symbol_count = 0;
-        let mut all_symbols = Vec::new();
-        while !symbol_file_opt.is_none() {
-            let symbol_file_result = symbol_file_opt.unwrap();
-            let symbol_file: PathBuf;
-            match symbol_file_result {
-                Err(e) => {
-                    log("N/A", e);
-                    symbol_file_opt = get_next_file(&dir);
+        if !dir.is_dir() {
+            return Err(SimulationError::Validation(format!(
+                "{} is not a directory",
+                dir.display()
+            )));
+        }
+        validate_top_x(top_x)?;
 
-                    continue;
-                }
-                Ok(sym) => symbol_file = sym,
-            }
-            let symbol = symbol_file.file_name().unwrap().to_str().unwrap();
+        let mut summary = RunSummary::default();
+        let mut all_symbols = Vec::new();
+        // the returns of every simulated symbol, the top x are simulated again for their paths
+        let mut inputs = HashMap::new();
+        let mut timings = PhaseTimings::default();
+        let mut progress = Progress::new(count_symbol_files(dir));
+        // get the path to the next file to be processed
+        while let Some(symbol_file) = get_next_file(dir)? {
+            let symbol = symbol_file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let symbol = symbol.as_str();
 
             // run the simulation
-            log(&symbol, "simulation begin");
+            log_debug(symbol, "simulation begin");
+            let started = Instant::now();
             let data_result = get_simulation_data(&symbol_file);
-            match data_result {
+            timings.load += started.elapsed();
+            log_phase(symbol, "load", started.elapsed());
+            let failure = match data_result {
                 Ok(data) => {
-                    log(
-                        &symbol,
+                    log_debug(
+                        symbol,
                         format!(
                             "{} items, {periods} periods, {number_of_simulations} simulations",
                             &data.len()
                         ),
                     );
-                    let results = simulations::monte_carlo_simulation(
-                        symbol.to_string(),
-                        &data,
-                        periods,
-                        number_of_simulations,
-                    );
-
-                    if results.is_some() {
-                        let sim = results.unwrap();
-                        all_symbols.push(sim);
+                    let started = Instant::now();
+                    let results =
+                        simulations::monte_carlo_simulation(symbol.to_string(), &data, settings);
+                    timings.simulate += started.elapsed();
+                    log_phase(symbol, "simulate", started.elapsed());
+
+                    match results {
+                        Ok(sim) => {
+                            log(symbol, format!("{} simulations used", sim.simulations));
+                            all_symbols.push(sim);
+                            inputs.insert(symbol.to_string(), data);
+                            None
+                        }
+                        Err(reason) => Some(reason),
                     }
                 }
-                Err(e) => log(symbol, e),
+                Err(e) => Some(e.to_string()),
+            };
+            match failure {
+                None => summary.processed.push(symbol.to_string()),
+                Some(reason) => {
+                    log_warning(symbol, &reason);
+                    summary.failed.push(SymbolIssue {
+                        symbol: symbol.to_string(),
+                        reason,
+                    });
+                }
+            }
+            log_debug(symbol, "simulation end");
+            progress.advance();
+
+            if let Some(max_failures) = max_failures {
+                if summary.failed.len() > max_failures {
+                    summary.aborted = Some(format!(
+                        "{} symbols failed, more than the {max_failures} allowed",
+                        summary.failed.len()
+                    ));
+                    break;
+                }
             }
-            log(&symbol, "simulation end");
+        }
+        progress.finish();
+        summary.skipped = get_skipped(dir, summary.aborted.is_some());
+        summary.status = summary.get_status();
+
+        if summary.processed.is_empty() && summary.failed.is_empty() {
+            return Err(SimulationError::NoInput(format!(
+                "no symbol files in {}",
+                dir.display()
+            )));
+        }
 
-            symbol_count += 1;
-            symbol_file_opt = get_next_file(&dir);
+        let metadata = RunMetadata {
+            header: output_settings.header.clone(),
+            disclaimer: output_settings.disclaimer.clone(),
+            config: output_settings.config.clone(),
+            options: output_settings.options.clone(),
+            strategy,
+            ..RunMetadata::new(dir, settings, top_x)
+        };
+        log(
+            NO_SYMBOL,
+            format!("run {}: {}", summary.status.name(), summary.counts()),
+        );
+        let aborted = summary.aborted.clone();
+        let failed = summary.failed.len();
+        let skipped = summary.skipped.len();
+        let total = failed + skipped + summary.processed.len();
+        timings.add(output_results(
+            output,
+            output_settings,
+            metadata,
+            all_symbols,
+            summary,
+            &inputs,
+            settings,
+        )?);
+
+        timings.log();
+        if progress.is_visible() {
+            eprint!("{}", get_timing_text(&timings));
         }
+        if let Some(reason) = aborted {
+            return Err(SimulationError::Aborted(reason));
+        }
+        if failed > 0 || skipped > 0 {
+            return Err(SimulationError::PartialFailure {
+                failed,
+                skipped,
+                total,
+            });
+        }
+        Ok(())
+    }
 
-        output_results(top_x, output_html, &all_symbols);
+    /// The files the run will pick up, as `get_next_file` chooses them
+    fn count_symbol_files(dir: &Path) -> usize {
+        let Ok(contents) = fs::read_dir(dir) else {
+            return 0;
+        };
+        contents
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                !path.is_dir()
+                    && path
+                        .metadata()
+                        .is_ok_and(|metadata| !metadata.permissions().readonly())
+            })
+            .count()
+    }
+
+    /// The files still in the input directory once the run is over: read only files are never moved to the
+    /// archive, and an aborted run leaves the files it did not reach
+    fn get_skipped(dir: &Path, aborted: bool) -> Vec<SymbolIssue> {
+        let Ok(contents) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut skipped: Vec<SymbolIssue> = contents
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| !path.is_dir())
+            .map(|path| {
+                let readonly = path
+                    .metadata()
+                    .map(|metadata| metadata.permissions().readonly())
+                    .unwrap_or(true);
+                let reason = if readonly {
+                    "read only, left in the input directory"
+                } else if aborted {
+                    "not reached before the run was aborted"
+                } else {
+                    "added while the run was finishing"
+                };
+                SymbolIssue {
+                    symbol: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    reason: reason.to_string(),
+                }
+            })
+            .collect();
+        skipped.sort_by(|left, right| left.symbol.cmp(&right.symbol));
+        skipped
+    }
 
-        log("N/A", format!("processed {symbol_count} symbols"));
+    /// Picks the top x by the strategy of the run, draws their paths, saves the run to the history and writes every output, timing the rank,
+    /// render and save phases
+    fn output_results(
+        output: &Path,
+        output_settings: &OutputSettings,
+        metadata: RunMetadata,
+        mut predictions: Vec<Prediction>,
+        summary: RunSummary,
+        inputs: &HashMap<String, Vec<f64>>,
+        settings: &SimulationSettings,
+    ) -> Result<PhaseTimings, SimulationError> {
+        let mut timings = PhaseTimings::default();
+        log_debug(NO_SYMBOL, "determine top x begin");
+        let started = Instant::now();
+        let prediction_calcs =
+            get_highest_x(metadata.top_x, &predictions, metadata.strategy.ordering());
+        timings.rank = started.elapsed();
+        log_debug(NO_SYMBOL, "determine top x end");
+
+        let started = Instant::now();
+        add_path_summaries(&mut predictions, &prediction_calcs, inputs, settings);
+        timings.simulate += started.elapsed();
+
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata,
+            predictions,
+            top_predictions: prediction_calcs,
+            summary,
+        };
+        if let Some(store) = &output_settings.history {
+            let started = Instant::now();
+            match save_run(store, &results) {
+                Ok(id) => log(NO_SYMBOL, format!("saved as run {id}")),
+                Err(e) => log_warning(NO_SYMBOL, format!("run history: {e}")),
+            }
+            timings.save += started.elapsed();
+        }
+        timings.add(write_outputs(output, output_settings, &results)?);
+        Ok(timings)
+    }
+
+    /// Fills the fan chart and drawdowns of the top x from the same paths their percentiles came from, the other
+    /// symbols are never drawn so their paths are not kept
+    pub(crate) fn add_path_summaries(
+        predictions: &mut [Prediction],
+        top: &[TopPredictions],
+        inputs: &HashMap<String, Vec<f64>>,
+        settings: &SimulationSettings,
+    ) {
+        for prediction in predictions
+            .iter_mut()
+            .filter(|prediction| top.iter().any(|top| top.symbol == prediction.symbol))
+        {
+            let Some(data) = inputs.get(&prediction.symbol) else {
+                continue;
+            };
+            let (fan_chart, drawdown) =
+                get_path_summary(&prediction.symbol, data, settings, prediction.simulations);
+            prediction.fan_chart = fan_chart;
+            prediction.drawdown = drawdown;
+        }
     }
 
-    fn output_results(top_x: usize, output_html: &PathBuf, predictions: &Vec<Prediction>) {
-        // instead output an html file that can been seen in a browser with all the data hardcoded
-        let most_common_box = Box::new(MostCommonResult {});
-        log("N/A", "determine top x begin");
-        let prediction_calcs = get_highest_x(top_x, predictions, most_common_box);
-        log("N/A", "determine top x end");
-        log("N/A", "html creation begin");
-        let html = get_html(&prediction_calcs);
-        log("N/A", "html creation end");
-        save_results(output_html, &html);
+    /// Writes the results in every format of the output settings, giving the time spent rendering and saving.
+    /// A template that does not render fails the whole run rather than leaving it without its html page.
+    pub(crate) fn write_outputs(
+        output: &Path,
+        output_settings: &OutputSettings,
+        results: &RunResults,
+    ) -> Result<PhaseTimings, SimulationError> {
+        let mut timings = PhaseTimings::default();
+        let formats = &output_settings.formats;
+        let style = output_settings.style();
+        let files = formats.iter().filter(|format| format.is_file()).count();
+        for format in formats {
+            log_debug(NO_SYMBOL, format!("{format} creation begin"));
+            let path = get_output_path(output, *format, files);
+            let started = Instant::now();
+            let mut details_timings = PhaseTimings::default();
+            let contents = match format {
+                // an index page that can been seen in a browser with all the data hardcoded, and a page per symbol
+                // in a directory beside it
+                OutputFormat::Html => {
+                    let details = get_details_dir(&path);
+                    details_timings = save_details(&details, &path, results, style)?;
+                    let details_name = details
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    match &output_settings.template {
+                        None => get_html(results, &details_name, style).into_bytes(),
+                        Some(template) => {
+                            let context = get_template_context(results, &details_name, style);
+                            render_template(template, &context)
+                                .map_err(|e| SimulationError::Parse(format!("template: {e}")))?
+                                .into_bytes()
+                        }
+                    }
+                }
+                OutputFormat::Json => get_json(results).into_bytes(),
+                OutputFormat::Csv => get_csv(&results.predictions).into_bytes(),
+                OutputFormat::Xlsx => get_xlsx(results)
+                    .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())))?,
+                OutputFormat::HtmlTable => get_interactive_html(results, style).into_bytes(),
+                OutputFormat::Markdown => get_markdown(
+                    &results.top_predictions,
+                    &results.metadata,
+                    &results.summary,
+                )
+                .into_bytes(),
+                OutputFormat::Table => {
+                    // color only when a person is watching, pipes and files get plain text
+                    let color =
+                        io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+                    print!(
+                        "{}",
+                        get_table(
+                            &results.top_predictions,
+                            &results.metadata,
+                            &results.summary,
+                            color
+                        )
+                    );
+                    timings.render += started.elapsed();
+                    log_debug(NO_SYMBOL, format!("{format} creation end"));
+                    continue;
+                }
+            };
+            // the detail pages time themselves, the rest is the index or the file of the format
+            timings.render += started
+                .elapsed()
+                .saturating_sub(details_timings.render + details_timings.save);
+            timings.add(details_timings);
+            log_debug(NO_SYMBOL, format!("{format} creation end"));
+            let started = Instant::now();
+            save_results(&path, contents)?;
+            timings.save += started.elapsed();
+        }
+        Ok(timings)
     }
 
-    fn save_results(path: &PathBuf, html: &str) {
-        let file_result = File::create(path);
-        match file_result {
-            Err(e) => log("N/A", e),
-            Ok(mut file) => match file.write_all(html.as_bytes()) {
-                Err(e) => log("N/A", e),
-                Ok(_) => (),
-            },
+    /// Writes the detail page of every symbol in the top x into the directory, linking back to the index beside it
+    fn save_details(
+        details: &Path,
+        index: &Path,
+        results: &RunResults,
+        style: &str,
+    ) -> Result<PhaseTimings, SimulationError> {
+        let mut timings = PhaseTimings::default();
+        fs::create_dir_all(details)
+            .map_err(|e| SimulationError::Io(format!("{}: {e}", details.display())))?;
+        let index = index
+            .file_name()
+            .map(|name| format!("../{}", name.to_string_lossy()))
+            .unwrap_or_default();
+
+        for view in get_prediction_views(&results.top_predictions, &results.metadata) {
+            let Some(prediction) = results.predictions.iter().find(|p| p.symbol == view.symbol)
+            else {
+                continue;
+            };
+            let started = Instant::now();
+            let html = get_detail_html(&view, prediction, &results.metadata, &index, style);
+            timings.render += started.elapsed();
+            let started = Instant::now();
+            save_results(&details.join(format!("{}.html", view.symbol)), html)?;
+            timings.save += started.elapsed();
         }
+        Ok(timings)
     }
 
-    pub(crate) fn get_html(calcs: &Vec<TopPredictions>) -> String {
-        let threholds = get_thresholds(calcs);
+    /// Writes the contents to the file, a file that cannot be created or written fails the run
+    pub(crate) fn save_results<C: AsRef<[u8]>>(
+        path: &Path,
+        contents: C,
+    ) -> Result<(), SimulationError> {
+        File::create(path)
+            .and_then(|mut file| file.write_all(contents.as_ref()))
+            .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())))
+    }
 
+    /// The index page, every card links to the symbol's page in the `details` directory beside it
+    pub(crate) fn get_html(results: &RunResults, details: &str, style: &str) -> String {
+        let metadata = &results.metadata;
         let mut list =
             Container::new(ContainerType::Div).with_attributes(vec![("class", "items-container")]);
 
-        for pred in calcs {
+        for pred in get_prediction_views(&results.top_predictions, metadata) {
             let mut outer_div = Container::new(ContainerType::Div)
                 .with_attributes(vec![("class", "item-container")]);
 
             let mut title_div = Container::new(ContainerType::Div)
                 .with_attributes(vec![("class", "item-header"), ("id", &*pred.symbol)]);
-            title_div.add_html(&*pred.symbol);
+            title_div.add_link(format!("{details}/{}.html", pred.symbol), &*pred.symbol);
             outer_div.add_container(title_div);
 
             // Most Common
             let mut most_common =
                 Container::new(ContainerType::Div).with_attributes(vec![("class", "info")]);
-            let color: &str;
-            if pred.most_common >= threholds.most_common_green {
-                color = "green";
-            } else if pred.most_common < threholds.most_common_yellow {
-                color = "red";
-            } else {
-                color = "yellow";
-            }
             most_common.add_html(format!(
                 "Most common result: <span class=\"primary {}\">{}</span>",
-                color, pred.most_common
+                pred.most_common.rating.class(),
+                pred.most_common.value
             ));
             outer_div.add_container(most_common);
 
             // Highest Low
             let mut highest_low =
                 Container::new(ContainerType::Div).with_attributes(vec![("class", "info")]);
-            let color: &str;
-            if pred.highest_low >= threholds.highest_low_green {
-                color = "green";
-            } else if pred.highest_low < threholds.highest_low_yellow {
-                color = "red";
-            } else {
-                color = "yellow";
-            }
             highest_low.add_html(format!(
                 "Bottom 25th: <span class=\"{}\">{}</span>",
-                color, pred.highest_low
+                pred.highest_low.rating.class(),
+                pred.highest_low.value
             ));
             outer_div.add_container(highest_low);
 
             // Total Span
             let mut total_span =
                 Container::new(ContainerType::Div).with_attributes(vec![("class", "info")]);
-            let color: &str;
-            if pred.total_span <= threholds.total_span_green {
-                color = "green";
-            } else if pred.total_span > threholds.total_span_yellow {
-                color = "red";
-            } else {
-                color = "yellow";
-            }
             total_span.add_html(format!(
                 "25th to 75th span: <span class=\"{}\">{}</span>",
-                color, pred.total_span
+                pred.total_span.rating.class(),
+                pred.total_span.value
             ));
             outer_div.add_container(total_span);
 
             // Weighted Span
             let mut weighted_span =
                 Container::new(ContainerType::Div).with_attributes(vec![("class", "info")]);
-            let color: &str;
-            if pred.weighted_span > 0 {
-                color = "green";
-            } else if pred.weighted_span < 0 {
-                color = "red";
-            } else {
-                color = "yellow";
-            }
             weighted_span.add_html(format!(
                 "Weighted span: <span class=\"{}\">{}</span>",
-                color, pred.weighted_span
+                pred.weighted_span.rating.class(),
+                pred.weighted_span.value
             ));
             outer_div.add_container(weighted_span);
 
+            // Simulations
+            let mut simulations =
+                Container::new(ContainerType::Div).with_attributes(vec![("class", "info")]);
+            simulations.add_html(format!(
+                "Simulations: <span class=\"blue\">{}</span>",
+                pred.simulations
+            ));
+            outer_div.add_container(simulations);
+
+            // Histogram and fan chart
+            if let Some(prediction) = results.predictions.iter().find(|p| p.symbol == pred.symbol) {
+                let histogram = get_histogram_svg(&prediction.data, &prediction.percentiles);
+                if !histogram.is_empty() {
+                    outer_div.add_html(histogram);
+                }
+                let fan_chart = get_fan_chart_svg(&prediction.fan_chart);
+                if !fan_chart.is_empty() {
+                    outer_div.add_html(fan_chart);
+                }
+            }
+
+            // Notes
+            let mut notes = Container::new(ContainerType::UnorderedList)
+                .with_attributes(vec![("class", "notes")]);
+            for note in pred.notes {
+                notes.add_html(note);
+            }
+            outer_div.add_container(notes);
+
             list.add_container(outer_div);
         }
 
@@ -304,17 +792,27 @@ pub mod stock_simulator {
                 ("content", "width=device-width, initial-scale=1.0"),
             ])
             .with_title("Stock Predictions")
-            .with_style(include_str!("style.css"))
+            .with_style(style)
+            .with_raw(get_header_html(metadata.header.as_deref()))
             .with_header(
                 1,
                 chrono::Local::now().format("Stock Predictions - %B %d, %Y"),
             )
-            .with_container(list);
+            .with_paragraph_attr(
+                format!(
+                    "{} days, {} simulations, {} sampling, seed {}",
+                    metadata.days, metadata.simulations, metadata.sampler, metadata.seed
+                ),
+                [("class", "run-info")],
+            )
+            .with_container(list)
+            .with_raw(get_summary_html(&results.summary))
+            .with_raw(get_disclaimer_html(metadata.disclaimer.as_deref()));
 
         page.to_html_string()
     }
 
-    pub(crate) fn get_thresholds(calcs: &Vec<TopPredictions>) -> Thresholds {
+    pub(crate) fn get_thresholds(calcs: &[TopPredictions]) -> Thresholds {
         let count = calcs.len();
         if count == 0 {
             return Thresholds {
@@ -330,23 +828,18 @@ pub mod stock_simulator {
         let low_index = std::cmp::max(threshold_length, 1) - 1;
         let high_index = std::cmp::min(count - threshold_length, count - 1);
 
-        let most_common_sorted: Vec<i32> =
-            calcs.into_iter().map(|p| p.most_common).sorted().collect();
-        let highest_low_sorted: Vec<i32> =
-            calcs.into_iter().map(|p| p.highest_low).sorted().collect();
-        let total_span_sorted: Vec<i32> =
-            calcs.into_iter().map(|p| p.total_span).sorted().collect();
+        let most_common_sorted: Vec<i32> = calcs.iter().map(|p| p.most_common).sorted().collect();
+        let highest_low_sorted: Vec<i32> = calcs.iter().map(|p| p.highest_low).sorted().collect();
+        let total_span_sorted: Vec<i32> = calcs.iter().map(|p| p.total_span).sorted().collect();
 
-        let thresholds = Thresholds {
+        Thresholds {
             most_common_green: most_common_sorted[high_index],
             most_common_yellow: most_common_sorted[low_index],
             highest_low_green: highest_low_sorted[high_index],
             highest_low_yellow: highest_low_sorted[low_index],
             total_span_green: total_span_sorted[low_index],
             total_span_yellow: total_span_sorted[high_index],
-        };
-
-        thresholds
+        }
     }
 
     pub(crate) fn get_highest_x(
@@ -370,6 +863,7 @@ pub mod stock_simulator {
                 highest_low: HighestLow {}.calculation(prediction),
                 total_span: TotalSpan {}.calculation(prediction),
                 weighted_span: WeightedSpan {}.calculation(prediction),
+                simulations: prediction.simulations,
             };
 
             if index == results.len() {
@@ -384,61 +878,97 @@ pub mod stock_simulator {
         results
     }
 
-    fn get_next_file(dir: &PathBuf) -> Option<Result<PathBuf, Box<dyn Error>>> {
-        if !dir.is_dir() {
-            return Some(Err(Box::new(SimulationError {
-                kind: ErrorKind::NotFound,
-                message: format!("directory not found: {:?}", dir),
-            })));
-        }
-
-        let contents_result = fs::read_dir(dir);
-        match contents_result {
-            Err(e) => return Some(Err(Box::new(e))),
-            Ok(mut contents) => {
-                let mut first_opt = contents.next();
-                let mut first: DirEntry;
-                loop {
-                    if first_opt.is_none() {
-                        return None;
-                    }
+    /// Method that gives the 1 based rank of every prediction under the passed ordering
+    pub(crate) fn get_ranks(
+        all: &Vec<Prediction>,
+        ordering: Box<dyn PredictionManipulation>,
+    ) -> HashMap<String, usize> {
+        get_highest_x(all.len(), all, ordering)
+            .into_iter()
+            .enumerate()
+            .map(|(index, top)| (top.symbol, index + 1))
+            .collect()
+    }
 
-                    let first_result = first_opt.unwrap();
-                    match first_result {
-                        Err(e) => return Some(Err(Box::new(e))),
-                        Ok(f) => first = f,
-                    }
+    /// Moves the next file of the directory to the archive and gives its new path, none once the directory holds
+    /// no more files
+    fn get_next_file(dir: &Path) -> Result<Option<PathBuf>, SimulationError> {
+        let io_error = |e: io::Error| SimulationError::Io(format!("{}: {e}", dir.display()));
+        for entry in fs::read_dir(dir).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            let path = entry.path();
+            let readonly = path
+                .metadata()
+                .map(|metadata| metadata.permissions().readonly())
+                .unwrap_or(true);
+            if path.is_dir() || readonly {
+                continue;
+            }
 
-                    let first_path = first.path();
-                    if first_path.is_dir()
-                        || first_path.metadata().unwrap().permissions().readonly()
-                    {
-                        first_opt = contents.next();
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
+            return move_file_to_archive(entry)
+                .map(Some)
+                .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())));
+        }
+        Ok(None)
+    }
+
+    /// Method to read the simulation data of every symbol in the directory without moving the files to the archive
+    pub(crate) fn read_symbols(dir: &PathBuf) -> Vec<(String, Vec<f64>)> {
+        let mut symbols = Vec::new();
+        let contents = match fs::read_dir(dir) {
+            Err(e) => {
+                log_warning(NO_SYMBOL, format!("{}: {e}", dir.display()));
+                return symbols;
+            }
+            Ok(contents) => contents,
+        };
 
-                let file_to_process_result = move_file_to_archive(first);
-                match file_to_process_result {
-                    Err(e) => return Some(Err(Box::new(e))),
-                    Ok(file_to_process) => return Some(Ok(file_to_process)),
+        for entry in contents {
+            let path = match entry {
+                Err(e) => {
+                    log_warning(NO_SYMBOL, e);
+                    continue;
                 }
+                Ok(entry) => entry.path(),
+            };
+            if path.is_dir() {
+                continue;
+            }
+
+            let symbol = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            match get_simulation_data(&path) {
+                Ok(data) => symbols.push((symbol, data)),
+                Err(e) => log_warning(&symbol, e),
             }
         }
+
+        symbols.sort_by(|left, right| left.0.cmp(&right.0));
+        symbols
     }
 
     /// Method to get the simulation data from the comman separated file passed in to the method
-    pub(crate) fn get_simulation_data(path: &PathBuf) -> Result<Vec<f64>, Box<dyn Error>> {
+    pub(crate) fn get_simulation_data(path: &Path) -> Result<Vec<f64>, SimulationError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| SimulationError::Io(format!("{}: {e}", path.display())))?;
+        get_returns(&content)
+            .map_err(|e| SimulationError::Parse(format!("{}: {e}", path.display())))
+    }
+
+    /// Method to parse comma separated returns, an error names the item that could not be read
+    pub(crate) fn get_returns(content: &str) -> Result<Vec<f64>, String> {
         let mut ret: Vec<f64> = Vec::new();
-        let content = fs::read_to_string(path)?;
         let content = content.trim().trim_matches(',');
 
         let items = content.split(',');
 
-        for item in items {
-            let val = item.parse::<f64>()?;
+        for (index, item) in items.enumerate() {
+            let val = item
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("item {}: {e}", index + 1))?;
             ret.push(val);
         }
 
@@ -446,8 +976,7 @@ pub mod stock_simulator {
     }
 
     fn move_file_to_archive(f: DirEntry) -> Result<PathBuf, io::Error> {
-        let mut archive = PathBuf::from(f.path().parent().unwrap());
-        archive.push("archive");
+        let mut archive = f.path().with_file_name("archive");
         fs::create_dir_all(archive.as_path())?;
         archive.push(f.file_name());
         fs::rename(f.path(), archive.as_path())?;
@@ -0,0 +1,113 @@
+pub mod table_report {
+    use crate::{
+        report::run_report::{get_prediction_views, RunMetadata, RunSummary},
+        stock_simulation::stock_simulator::{Rating, TopPredictions},
+        utilities::util::aligned_table,
+    };
+
+    const HEADER: [&str; 7] = [
+        "rank",
+        "symbol",
+        "most common",
+        "bottom 25th",
+        "25th to 75th span",
+        "weighted span",
+        "simulations",
+    ];
+
+    const RESET: &str = "\x1b[0m";
+
+    fn get_ansi_color(rating: Rating) -> &'static str {
+        match rating {
+            Rating::Green => "\x1b[32m",
+            Rating::Yellow => "\x1b[33m",
+            Rating::Red => "\x1b[31m",
+        }
+    }
+
+    /// The ranked top x as an aligned table for a terminal, followed by the run summary. Without color the rating
+    /// of each metric is written after it in brackets so the plain text keeps the same information.
+    pub(crate) fn get_table(
+        calcs: &[TopPredictions],
+        metadata: &RunMetadata,
+        summary: &RunSummary,
+        color: bool,
+    ) -> String {
+        let rows: Vec<Vec<(String, Option<Rating>)>> = get_prediction_views(calcs, metadata)
+            .into_iter()
+            .map(|pred| {
+                vec![
+                    (pred.rank.to_string(), None),
+                    (pred.symbol, None),
+                    (
+                        pred.most_common.value.to_string(),
+                        Some(pred.most_common.rating),
+                    ),
+                    (
+                        pred.highest_low.value.to_string(),
+                        Some(pred.highest_low.rating),
+                    ),
+                    (
+                        pred.total_span.value.to_string(),
+                        Some(pred.total_span.rating),
+                    ),
+                    (
+                        pred.weighted_span.value.to_string(),
+                        Some(pred.weighted_span.rating),
+                    ),
+                    (pred.simulations.to_string(), None),
+                ]
+            })
+            .map(|row| {
+                row.into_iter()
+                    .map(|(text, rating)| match (rating, color) {
+                        (Some(rating), false) => (format!("{text} [{}]", rating.class()), None),
+                        _ => (text, rating),
+                    })
+                    .collect()
+            })
+            .collect();
+        let cells: Vec<Vec<&str>> = rows
+            .iter()
+            .map(|row| row.iter().map(|(text, _)| text.as_str()).collect())
+            .collect();
+
+        let mut table = String::new();
+        if let Some(header) = &metadata.header {
+            table.push_str(&format!("{header}\n"));
+        }
+        table.push_str(&format!(
+            "Stock Predictions - {} days, {} simulations, {} sampling, seed {}\n\n",
+            metadata.days, metadata.simulations, metadata.sampler, metadata.seed
+        ));
+        // the symbol is left aligned, every number is right aligned
+        table.push_str(&aligned_table(
+            &HEADER,
+            &cells,
+            1,
+            |row, column, padded| match rows[row][column].1 {
+                Some(rating) => format!("{}{padded}{RESET}", get_ansi_color(rating)),
+                None => padded,
+            },
+        ));
+
+        table.push_str(&format!(
+            "\nRun {}: {}.\n",
+            summary.status.name(),
+            summary.counts()
+        ));
+        if let Some(reason) = &summary.aborted {
+            table.push_str(&format!("Aborted: {reason}\n"));
+        }
+        for (title, issues) in [("Skipped", &summary.skipped), ("Failed", &summary.failed)] {
+            for issue in issues {
+                table.push_str(&format!("{title} {}: {}\n", issue.symbol, issue.reason));
+            }
+        }
+
+        if let Some(disclaimer) = &metadata.disclaimer {
+            table.push_str(&format!("\n{disclaimer}\n"));
+        }
+        table
+    }
+}
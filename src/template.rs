@@ -0,0 +1,196 @@
+pub mod templates {
+    use build_html::escape_html;
+    use serde_json::{json, Value};
+
+    use crate::report::run_report::{get_prediction_views, RunResults};
+
+    /// A parsed piece of a template
+    #[derive(Debug)]
+    enum Node {
+        Text(String),
+        /// `{{name}}` is html escaped, `{{{name}}}` is written as is
+        Variable {
+            name: String,
+            escaped: bool,
+        },
+        /// `{{#name}}...{{/name}}` repeats for every item of a list and shows once for any other value that is
+        /// not empty, `{{^name}}...{{/name}}` shows only when the value is missing or empty
+        Section {
+            name: String,
+            inverted: bool,
+            children: Vec<Node>,
+        },
+    }
+
+    /// Renders a template in a small subset of mustache against the context. Names are looked up from the
+    /// innermost section outwards, `a.b` reaches into objects and `.` is the current item.
+    pub(crate) fn render_template(template: &str, context: &Value) -> Result<String, String> {
+        let (nodes, _) = parse(template, None)?;
+        let mut output = String::new();
+        render(&nodes, &mut vec![context], &mut output);
+        Ok(output)
+    }
+
+    /// Parses until the end of the template or the closing tag of `section`, returning the nodes and the text
+    /// after the closing tag
+    fn parse<'a>(template: &'a str, section: Option<&str>) -> Result<(Vec<Node>, &'a str), String> {
+        let mut nodes = Vec::new();
+        let mut rest = template;
+        loop {
+            let Some(start) = rest.find("{{") else {
+                if let Some(name) = section {
+                    return Err(format!("section {name} is never closed"));
+                }
+                if !rest.is_empty() {
+                    nodes.push(Node::Text(rest.to_string()));
+                }
+                return Ok((nodes, ""));
+            };
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            rest = &rest[start..];
+
+            let (tag, after, raw) = if let Some(inner) = rest.strip_prefix("{{{") {
+                let end = inner.find("}}}").ok_or("a {{{ tag is never closed")?;
+                (&inner[..end], &inner[end + 3..], true)
+            } else {
+                let inner = &rest[2..];
+                let end = inner.find("}}").ok_or("a {{ tag is never closed")?;
+                (&inner[..end], &inner[end + 2..], false)
+            };
+            let tag = tag.trim();
+            rest = after;
+
+            if raw {
+                nodes.push(Node::Variable {
+                    name: tag.to_string(),
+                    escaped: false,
+                });
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                return match section {
+                    Some(open) if open == name => Ok((nodes, rest)),
+                    Some(open) => Err(format!("section {open} is closed by {name}")),
+                    None => Err(format!("section {name} is closed but never opened")),
+                };
+            } else if tag.starts_with('!') {
+                // comments are dropped
+            } else if let Some((inverted, name)) = tag
+                .strip_prefix('#')
+                .map(|name| (false, name))
+                .or_else(|| tag.strip_prefix('^').map(|name| (true, name)))
+            {
+                let name = name.trim();
+                let (children, after) = parse(rest, Some(name))?;
+                rest = after;
+                nodes.push(Node::Section {
+                    name: name.to_string(),
+                    inverted,
+                    children,
+                });
+            } else {
+                nodes.push(Node::Variable {
+                    name: tag.to_string(),
+                    escaped: true,
+                });
+            }
+        }
+    }
+
+    fn lookup<'a>(name: &str, stack: &[&'a Value]) -> Option<&'a Value> {
+        if name == "." {
+            return stack.last().copied();
+        }
+        let mut parts = name.split('.');
+        let first = parts.next()?;
+        let mut value = stack.iter().rev().find_map(|scope| scope.get(first))?;
+        for part in parts {
+            value = value.get(part)?;
+        }
+        Some(value)
+    }
+
+    fn is_empty(value: Option<&Value>) -> bool {
+        match value {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => true,
+            Some(Value::String(text)) => text.is_empty(),
+            Some(Value::Array(items)) => items.is_empty(),
+            _ => false,
+        }
+    }
+
+    fn render(nodes: &[Node], stack: &mut Vec<&Value>, output: &mut String) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Variable { name, escaped } => {
+                    let text = match lookup(name, stack) {
+                        None | Some(Value::Null) => String::new(),
+                        Some(Value::String(text)) => text.clone(),
+                        Some(value) => value.to_string(),
+                    };
+                    if *escaped {
+                        output.push_str(&escape_html(&text));
+                    } else {
+                        output.push_str(&text);
+                    }
+                }
+                Node::Section {
+                    name,
+                    inverted,
+                    children,
+                } => {
+                    let value = lookup(name, stack);
+                    if *inverted {
+                        if is_empty(value) {
+                            render(children, stack, output);
+                        }
+                        continue;
+                    }
+                    match value {
+                        _ if is_empty(value) => {}
+                        Some(Value::Array(items)) => {
+                            for item in items {
+                                stack.push(item);
+                                render(children, stack, output);
+                                stack.pop();
+                            }
+                        }
+                        Some(value) => {
+                            stack.push(value);
+                            render(children, stack, output);
+                            stack.pop();
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Everything a template can use: the run results as in the json output with the counts of the summary as
+    /// `summary.counts`, the rated top x as `cards`, the stylesheet, the date, and the company header and
+    /// disclaimer
+    pub(crate) fn get_template_context(results: &RunResults, details: &str, style: &str) -> Value {
+        let cards: Vec<Value> = get_prediction_views(&results.top_predictions, &results.metadata)
+            .into_iter()
+            .map(|view| {
+                let link = format!("{details}/{}.html", view.symbol);
+                let mut card =
+                    serde_json::to_value(view).expect("prediction views always serialize");
+                card["link"] = json!(link);
+                card
+            })
+            .collect();
+
+        let mut context = serde_json::to_value(results).expect("run results always serialize");
+        context["summary"]["counts"] = json!(results.summary.counts());
+        context["cards"] = json!(cards);
+        context["style"] = json!(style);
+        context["date"] = json!(chrono::Local::now().format("%B %d, %Y").to_string());
+        context["header"] = json!(results.metadata.header);
+        context["disclaimer"] = json!(results.metadata.disclaimer);
+        context
+    }
+}
@@ -1,42 +1,89 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, HashMap};
     use std::fmt::Debug;
     use std::path::PathBuf;
+    use std::time::{Duration, Instant};
 
+    use crate::backtest::walk_forward::{
+        get_backtest_csv, get_calendar, get_dated_returns, get_realized_return, get_rebalances,
+        get_summary, run_backtest, BacktestSettings, Forecast, History, Rebalance,
+    };
+    use crate::calibration::calibration_report::{
+        get_calibration, get_calibration_csv, get_coverage, get_pinball_loss, get_pit,
+        get_pit_histogram,
+    };
+    use crate::charts::svg_charts::{get_fan_chart_svg, get_histogram_svg, get_pit_svg};
+    use crate::comparison::run_comparison::{
+        get_changes, get_comparison_csv, get_comparison_html, get_comparison_rows, Movement,
+    };
+    use crate::config::run_config::{get_config, SimulateConfig};
+    use crate::convergence_study::study::{
+        get_rank_correlation, get_stability, get_stability_text, get_stable_count,
+        get_study_counts, get_study_csv, get_study_html, StudyStep,
+    };
+    use crate::csv_output::csv_report::get_csv;
+    use crate::detail_output::detail_report::get_detail_html;
+    use crate::history::run_history::{
+        get_run_entries, get_run_id, get_run_list, get_symbol_forecasts, load_run, load_runs,
+        save_run,
+    };
+    use crate::inspection::input_inspection::{
+        check_file, get_data_problems, get_inspection_text, get_validation_text,
+    };
+    use crate::interactive_output::interactive_report::get_interactive_html;
+    use crate::logging::logger::{
+        self, format_record, Level, LogFilter, LogFormat, Record, NO_SYMBOL,
+    };
+    use crate::markdown_output::markdown_report::get_markdown;
     use crate::monte_carlo::simulations::{
-        get_percentiles, perform_simulation_calculation, simulate_period, Percentiles, Prediction,
+        check_simulation_count, get_chunk_sizes, get_input_stats, get_path_summary,
+        get_percentile_table, get_percentiles, get_seed, monte_carlo_simulation,
+        percentiles_converged, perform_simulation_calculation, simulate_antithetic_pair,
+        simulate_chunk, simulate_period, simulate_stratified_block, Convergence, DrawdownStats,
+        FanBand, InputStats, Percentiles, Prediction, Sampler, SimulationSettings, MAX_SEED,
+    };
+    use crate::progress::run_progress::{
+        format_duration, get_progress_text, get_timing_text, PhaseTimings,
+    };
+    use crate::quantiles::distributions::{KllSketch, OutcomeDistribution, QuantileMethod};
+    use crate::report::run_report::{
+        get_details_dir, get_json, get_notes, get_output_path, get_prediction_views,
+        get_summary_html, read_results, Branding, OutputFormat, OutputSettings, RunMetadata,
+        RunResults, RunStatus, RunSummary, SymbolIssue, SCHEMA_VERSION,
     };
     use crate::stock_simulation::stock_simulator::{
-        get_highest_x, get_simulation_data, get_thresholds, HighestLow, MostCommonResult,
-        Thresholds, TopPredictions, TotalSpan, WeightedSpan,
+        add_path_summaries, get_highest_x, get_html, get_returns, get_simulation_data,
+        get_thresholds, read_symbols, run_simulator, write_outputs, HighestLow, MostCommonResult,
+        Rating, SimulationError, Strategy, Thresholds, TopPredictions, TotalSpan, WeightedSpan,
     };
+    use crate::table_output::table_report::get_table;
+    use crate::template::templates::{get_template_context, render_template};
+    use crate::utilities::util::text_table;
+    use crate::xlsx_output::xlsx_report::get_xlsx;
 
     fn vectors_are_equal<T: PartialEq + Debug>(v1: Vec<T>, v2: Vec<T>) -> bool {
-        if v1.iter().count() != v2.iter().count() {
-            println!(
-                "counts are not equal v1={} v2 = {}",
-                v1.iter().count(),
-                v2.iter().count()
-            );
+        if v1.len() != v2.len() {
+            println!("counts are not equal v1={} v2 = {}", v1.len(), v2.len());
             return false;
         }
 
         for s in v1.iter() {
-            if !v2.contains(&s) {
+            if !v2.contains(s) {
                 println!("v2 search found no {:?}", s);
                 return false;
             }
         }
 
         for s in v2.iter() {
-            if !v1.contains(&s) {
+            if !v1.contains(s) {
                 println!("v1 search found no {:?}", s);
                 return false;
             }
         }
 
-        return true;
+        true
     }
 
     fn vector_is_subset<T: PartialEq + Debug>(subset: Vec<T>, original: Vec<T>) -> bool {
@@ -47,7 +94,7 @@ mod tests {
             }
         }
 
-        return true;
+        true
     }
 
     //#[test]
@@ -110,7 +157,7 @@ mod tests {
         let period = 40;
 
         // act
-        let actual = simulate_period(&input, period);
+        let actual = simulate_period(&mut rand::rng(), &input, period);
 
         // assert
         assert_eq!(period, actual.len() as u32);
@@ -125,7 +172,7 @@ mod tests {
         let expected = vec![1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0];
 
         // act
-        let actual = simulate_period(&input, period);
+        let actual = simulate_period(&mut rand::rng(), &input, period);
 
         // assert
         assert_eq!(actual.len(), expected.len());
@@ -139,7 +186,7 @@ mod tests {
         let period = 10;
 
         // act
-        let actual = simulate_period(&input, period);
+        let actual = simulate_period(&mut rand::rng(), &input, period);
 
         // assert
         assert_eq!(actual.len(), 0);
@@ -155,7 +202,7 @@ mod tests {
         let period = 10;
 
         // act
-        let actual = simulate_period(&input, period);
+        let actual = simulate_period(&mut rand::rng(), &input, period);
 
         // assert
         assert_eq!(period, actual.len() as u32);
@@ -251,7 +298,7 @@ mod tests {
             Ok(actual) => {
                 assert!(vectors_are_equal(expected, actual))
             }
-            Err(e) => assert!(false, "{e}"),
+            Err(e) => panic!("{e}"),
         }
     }
 
@@ -284,6 +331,7 @@ mod tests {
             highest_low: -6,
             total_span: 33,
             weighted_span: 3,
+            ..Default::default()
         }];
         let expected = Thresholds {
             most_common_green: 9,
@@ -311,6 +359,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -318,6 +367,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
         ];
         let expected = Thresholds {
@@ -345,6 +395,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -352,6 +403,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
         ];
         let expected = Thresholds {
@@ -380,6 +432,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -387,6 +440,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADI".to_string(),
@@ -394,6 +448,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 13,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAOI".to_string(),
@@ -401,6 +456,7 @@ mod tests {
                 highest_low: -9,
                 total_span: 26,
                 weighted_span: 2,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPB".to_string(),
@@ -408,6 +464,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPL".to_string(),
@@ -415,6 +472,7 @@ mod tests {
                 highest_low: -1,
                 total_span: 3,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPD".to_string(),
@@ -422,6 +480,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 3,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADR".to_string(),
@@ -429,6 +488,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 2,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAL".to_string(),
@@ -436,6 +496,7 @@ mod tests {
                 highest_low: -7,
                 total_span: 8,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAME".to_string(),
@@ -443,6 +504,7 @@ mod tests {
                 highest_low: -14,
                 total_span: 17,
                 weighted_span: 1,
+                ..Default::default()
             },
         ];
         let expected = Thresholds {
@@ -472,6 +534,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -480,6 +543,7 @@ mod tests {
                     _50th: 2,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -488,6 +552,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -496,6 +561,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -504,6 +570,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -512,6 +579,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -520,6 +588,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -528,6 +597,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -536,6 +606,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -544,6 +615,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -555,6 +627,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -562,6 +635,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADI".to_string(),
@@ -569,6 +643,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 13,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAOI".to_string(),
@@ -576,6 +651,7 @@ mod tests {
                 highest_low: -9,
                 total_span: 26,
                 weighted_span: 2,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPB".to_string(),
@@ -583,6 +659,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPL".to_string(),
@@ -590,6 +667,7 @@ mod tests {
                 highest_low: -1,
                 total_span: 3,
                 weighted_span: -3,
+                ..Default::default()
             },
         ];
 
@@ -612,6 +690,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -620,6 +699,7 @@ mod tests {
                     _50th: 2,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -628,6 +708,7 @@ mod tests {
                     _50th: 2,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -636,6 +717,7 @@ mod tests {
                     _50th: 2,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -644,6 +726,7 @@ mod tests {
                     _50th: 2,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -652,6 +735,7 @@ mod tests {
                     _50th: 2,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -660,6 +744,7 @@ mod tests {
                     _50th: 2,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -668,6 +753,7 @@ mod tests {
                     _50th: 2,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -676,6 +762,7 @@ mod tests {
                     _50th: 2,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -684,6 +771,7 @@ mod tests {
                     _50th: 2,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -695,6 +783,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 17,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -702,6 +791,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 7,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADI".to_string(),
@@ -709,6 +799,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 13,
                 weighted_span: 5,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAOI".to_string(),
@@ -716,6 +807,7 @@ mod tests {
                 highest_low: -9,
                 total_span: 26,
                 weighted_span: 4,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPB".to_string(),
@@ -723,6 +815,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPL".to_string(),
@@ -730,6 +823,7 @@ mod tests {
                 highest_low: -1,
                 total_span: 3,
                 weighted_span: -3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPD".to_string(),
@@ -737,6 +831,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 0,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADR".to_string(),
@@ -744,6 +839,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 4,
                 weighted_span: -4,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAL".to_string(),
@@ -751,6 +847,7 @@ mod tests {
                 highest_low: -7,
                 total_span: 10,
                 weighted_span: -4,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAME".to_string(),
@@ -758,6 +855,7 @@ mod tests {
                 highest_low: -14,
                 total_span: 17,
                 weighted_span: -15,
+                ..Default::default()
             },
         ];
 
@@ -780,6 +878,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -788,6 +887,7 @@ mod tests {
                     _50th: 1,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -796,6 +896,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -804,6 +905,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -812,6 +914,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -820,6 +923,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -828,6 +932,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -836,6 +941,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -844,6 +950,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -852,6 +959,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 11;
@@ -863,6 +971,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAON".to_string(),
@@ -870,6 +979,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADI".to_string(),
@@ -877,6 +987,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 13,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAOI".to_string(),
@@ -884,6 +995,7 @@ mod tests {
                 highest_low: -9,
                 total_span: 26,
                 weighted_span: 2,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPB".to_string(),
@@ -891,6 +1003,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPL".to_string(),
@@ -898,6 +1011,7 @@ mod tests {
                 highest_low: -1,
                 total_span: 3,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAPD".to_string(),
@@ -905,6 +1019,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 3,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AADR".to_string(),
@@ -912,6 +1027,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 2,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAL".to_string(),
@@ -919,6 +1035,7 @@ mod tests {
                 highest_low: -7,
                 total_span: 8,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: "AAME".to_string(),
@@ -926,6 +1043,7 @@ mod tests {
                 highest_low: -14,
                 total_span: 17,
                 weighted_span: 1,
+                ..Default::default()
             },
         ];
 
@@ -947,6 +1065,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -955,6 +1074,7 @@ mod tests {
                     _50th: 1,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -963,6 +1083,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -971,6 +1092,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -979,6 +1101,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -987,6 +1110,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -995,6 +1119,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -1003,6 +1128,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -1011,6 +1137,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -1019,6 +1146,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -1030,6 +1158,7 @@ mod tests {
                 most_common: 9,
                 highest_low: -6,
                 total_span: 33,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAOI"),
@@ -1037,6 +1166,7 @@ mod tests {
                 most_common: 3,
                 highest_low: -9,
                 total_span: 26,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAON"),
@@ -1044,6 +1174,7 @@ mod tests {
                 most_common: 5,
                 highest_low: 2,
                 total_span: 7,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAME"),
@@ -1051,6 +1182,7 @@ mod tests {
                 most_common: -6,
                 highest_low: -14,
                 total_span: 17,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AADI"),
@@ -1058,6 +1190,7 @@ mod tests {
                 most_common: 4,
                 highest_low: -2,
                 total_span: 13,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPD"),
@@ -1065,6 +1198,7 @@ mod tests {
                 most_common: -1,
                 highest_low: -2,
                 total_span: 3,
+                ..Default::default()
             },
         ];
 
@@ -1087,6 +1221,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -1095,6 +1230,7 @@ mod tests {
                     _50th: 1,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -1103,6 +1239,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -1111,6 +1248,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -1119,6 +1257,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -1127,6 +1266,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -1135,6 +1275,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -1143,6 +1284,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -1151,6 +1293,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -1159,6 +1302,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -1170,6 +1314,7 @@ mod tests {
                 most_common: -1,
                 highest_low: -2,
                 weighted_span: 0,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPL"),
@@ -1177,6 +1322,7 @@ mod tests {
                 most_common: 1,
                 highest_low: -1,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPD"),
@@ -1184,6 +1330,7 @@ mod tests {
                 most_common: -1,
                 highest_low: -2,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAON"),
@@ -1191,6 +1338,7 @@ mod tests {
                 most_common: 5,
                 highest_low: 2,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPB"),
@@ -1198,6 +1346,7 @@ mod tests {
                 most_common: 2,
                 highest_low: -2,
                 weighted_span: -1,
+                ..Default::default()
             },
         ];
 
@@ -1219,6 +1368,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -1227,6 +1377,7 @@ mod tests {
                     _50th: 1,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -1235,6 +1386,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -1243,6 +1395,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -1251,6 +1404,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -1259,6 +1413,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -1267,6 +1422,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -1275,6 +1431,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -1283,6 +1440,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -1291,6 +1449,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -1302,6 +1461,7 @@ mod tests {
                 most_common: 5,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPL"),
@@ -1309,6 +1469,7 @@ mod tests {
                 most_common: 1,
                 total_span: 3,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPB"),
@@ -1316,6 +1477,7 @@ mod tests {
                 most_common: 2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPD"),
@@ -1323,6 +1485,7 @@ mod tests {
                 most_common: -1,
                 total_span: 3,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AADI"),
@@ -1330,6 +1493,7 @@ mod tests {
                 most_common: 4,
                 total_span: 13,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AADR"),
@@ -1337,6 +1501,7 @@ mod tests {
                 most_common: -1,
                 total_span: 2,
                 weighted_span: 0,
+                ..Default::default()
             },
         ];
 
@@ -1359,6 +1524,7 @@ mod tests {
                     _50th: 2,
                     _75th: 5,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPL".to_string(),
@@ -1367,6 +1533,7 @@ mod tests {
                     _50th: 1,
                     _75th: 2,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAL".to_string(),
@@ -1375,6 +1542,7 @@ mod tests {
                     _50th: -3,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAPD".to_string(),
@@ -1383,6 +1551,7 @@ mod tests {
                     _50th: -1,
                     _75th: 1,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADI".to_string(),
@@ -1391,6 +1560,7 @@ mod tests {
                     _50th: 4,
                     _75th: 11,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AADR".to_string(),
@@ -1399,6 +1569,7 @@ mod tests {
                     _50th: -1,
                     _75th: 0,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AACG".to_string(),
@@ -1407,6 +1578,7 @@ mod tests {
                     _50th: 9,
                     _75th: 27,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAME".to_string(),
@@ -1415,6 +1587,7 @@ mod tests {
                     _50th: -6,
                     _75th: 3,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAON".to_string(),
@@ -1423,6 +1596,7 @@ mod tests {
                     _50th: 5,
                     _75th: 9,
                 },
+                ..Default::default()
             },
             Prediction {
                 symbol: "AAOI".to_string(),
@@ -1431,6 +1605,7 @@ mod tests {
                     _50th: 3,
                     _75th: 17,
                 },
+                ..Default::default()
             },
         ];
         let top_x = 5;
@@ -1442,6 +1617,7 @@ mod tests {
                 highest_low: -6,
                 total_span: 33,
                 weighted_span: 3,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAON"),
@@ -1449,6 +1625,7 @@ mod tests {
                 highest_low: 2,
                 total_span: 7,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AADI"),
@@ -1456,6 +1633,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 13,
                 weighted_span: 1,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAOI"),
@@ -1463,6 +1641,7 @@ mod tests {
                 highest_low: -9,
                 total_span: 26,
                 weighted_span: 2,
+                ..Default::default()
             },
             TopPredictions {
                 symbol: String::from("AAPB"),
@@ -1470,6 +1649,7 @@ mod tests {
                 highest_low: -2,
                 total_span: 7,
                 weighted_span: -1,
+                ..Default::default()
             },
         ];
 
@@ -1484,10 +1664,9 @@ mod tests {
     fn get_percentiles_empty_results_zeroes_in_percentiles() {
         // assign
         let results = BTreeMap::new();
-        let number_of_results = results.len() as u32;
 
         // act
-        let actual_opt = get_percentiles(&results, number_of_results);
+        let actual_opt = get_percentiles(&results);
 
         // assert
         assert!(actual_opt.is_none());
@@ -1496,7 +1675,6 @@ mod tests {
     #[test]
     fn get_percentiles_less_than_100_in_result() {
         // assign
-        let number_of_results = 20;
         let results = BTreeMap::from([
             (1, 1),
             (2, 1),
@@ -1526,7 +1704,7 @@ mod tests {
         };
 
         // act
-        let actual_opt = get_percentiles(&results, number_of_results);
+        let actual_opt = get_percentiles(&results);
 
         // assert
         let actual = actual_opt.unwrap();
@@ -1538,7 +1716,6 @@ mod tests {
     #[test]
     fn get_percentiles_one_in_result_all_that_number() {
         // assign
-        let number_of_results = 1;
         let results = BTreeMap::from([(2, 1)]);
         let expected = Percentiles {
             _25th: 2,
@@ -1547,7 +1724,7 @@ mod tests {
         };
 
         // act
-        let actual_opt = get_percentiles(&results, number_of_results);
+        let actual_opt = get_percentiles(&results);
 
         // assert
         let actual = actual_opt.unwrap();
@@ -1559,7 +1736,6 @@ mod tests {
     #[test]
     fn get_percentiles_happy_path() {
         // assign
-        let number_of_results = 100;
         let results = BTreeMap::from([
             (-44, 1),
             (-43, 1),
@@ -1624,14 +1800,15 @@ mod tests {
             (66, 1),
             (94, 1),
         ]);
+        // the histogram holds 99 outcomes, the cut points are the 24th, 49th and 74th of them
         let expected = Percentiles {
-            _25th: -13,
-            _50th: 5,
+            _25th: -14,
+            _50th: 3,
             _75th: 25,
         };
 
         // act
-        let actual_opt = get_percentiles(&results, number_of_results);
+        let actual_opt = get_percentiles(&results);
 
         // assert
         let actual = actual_opt.unwrap();
@@ -1639,4 +1816,2995 @@ mod tests {
         assert_eq!(actual._50th, expected._50th);
         assert_eq!(actual._75th, expected._75th);
     }
+
+    #[test]
+    fn kll_sketch_percentiles_within_error_bound() {
+        // assign
+        let total = 100_000;
+        let mut sketch = KllSketch::new(200, 1);
+        // visit the values out of order so the compactions see a shuffled stream
+        let values = (0..total).map(|i| ((i * 7919) % total) as f64);
+
+        // act
+        for value in values {
+            sketch.update(value);
+        }
+
+        // assert
+        assert_eq!(sketch.count(), total as u64);
+        for fraction in [0.25, 0.50, 0.75] {
+            let actual = sketch.quantile(fraction).unwrap();
+            let rank_error = (actual / total as f64 - fraction).abs();
+            assert!(rank_error < 0.0133, "{fraction}: {actual}");
+        }
+    }
+
+    #[test]
+    fn kll_sketch_merged_chunks_stay_within_error_bound() {
+        // assign
+        let total = 100_000;
+        let chunks = 8;
+
+        for trial in 0..10u64 {
+            // act
+            // every chunk sees its own slice of a shuffled stream and flips its own coin, as simulation chunks do
+            let mut merged = KllSketch::new(200, trial);
+            for chunk in 0..chunks {
+                let mut sketch = KllSketch::new(200, trial * 100 + chunk + 1);
+                for i in (chunk..total).step_by(chunks as usize) {
+                    sketch.update(((i * 7919 + trial * 104_729) % total) as f64);
+                }
+                merged.merge(sketch);
+            }
+
+            // assert
+            assert_eq!(merged.count(), total);
+            for fraction in [0.25, 0.50, 0.75] {
+                let actual = merged.quantile(fraction).unwrap();
+                let rank_error = (actual / total as f64 - fraction).abs();
+                assert!(rank_error < 0.0133, "trial {trial}, {fraction}: {actual}");
+            }
+        }
+    }
+
+    #[test]
+    fn kll_sketch_seeds_change_the_compactions() {
+        // assign
+        let mut left = KllSketch::new(200, 1);
+        let mut right = KllSketch::new(200, 2);
+
+        // act
+        for i in 0..10_000 {
+            left.update(i as f64);
+            right.update(i as f64);
+        }
+
+        // assert
+        assert_ne!(left.sorted_items(), right.sorted_items());
+    }
+
+    #[test]
+    fn kll_sketch_memory_does_not_grow_with_count() {
+        // assign
+        let mut small = KllSketch::new(200, 1);
+        let mut large = KllSketch::new(200, 2);
+
+        // act
+        for i in 0..10_000 {
+            small.update(i as f64);
+        }
+        for i in 0..1_000_000 {
+            large.update(i as f64);
+        }
+
+        // assert
+        assert!(small.sorted_items().len() <= 3 * 200);
+        assert!(large.sorted_items().len() <= 3 * 200 + 8 * 20);
+    }
+
+    #[test]
+    fn kll_sketch_merge_keeps_every_weight() {
+        // assign
+        let mut left = KllSketch::new(200, 1);
+        let mut right = KllSketch::new(200, 2);
+        for i in 0..50_000 {
+            left.update(i as f64);
+            right.update((50_000 + i) as f64);
+        }
+
+        // act
+        left.merge(right);
+
+        // assert
+        let weight: u64 = left.sorted_items().iter().map(|(_, w)| w).sum();
+        assert_eq!(left.count(), 100_000);
+        assert_eq!(weight, 100_000);
+        let median = left.quantile(0.5).unwrap();
+        assert!((median - 50_000.0).abs() < 1_330.0, "{median}");
+    }
+
+    #[test]
+    fn kll_sketch_empty_has_no_quantile() {
+        // assign
+        let sketch = KllSketch::new(200, 1);
+
+        // act
+        let actual = sketch.quantile(0.5);
+
+        // assert
+        assert!(actual.is_none());
+    }
+
+    #[test]
+    fn outcome_distribution_exact_merge_adds_counts() {
+        // assign
+        let mut left = OutcomeDistribution::Exact(BTreeMap::from([(1, 2), (3, 1)]));
+        let right = OutcomeDistribution::Exact(BTreeMap::from([(1, 1), (5, 4)]));
+
+        // act
+        left.merge(right);
+
+        // assert
+        assert_eq!(left.count(), 8);
+        match left {
+            OutcomeDistribution::Exact(results) => {
+                assert_eq!(results, BTreeMap::from([(1, 3), (3, 1), (5, 4)]))
+            }
+            OutcomeDistribution::Sketch(_) => panic!("expected an exact distribution"),
+        }
+    }
+
+    #[test]
+    fn quantile_method_parses_cli_values() {
+        // assign
+        let inputs = ["exact", "sketch", "sketch:400", "sketch:2", "tdigest"];
+
+        // act
+        let actual: Vec<Result<QuantileMethod, String>> =
+            inputs.iter().map(|input| input.parse()).collect();
+
+        // assert
+        assert_eq!(actual[0], Ok(QuantileMethod::Exact));
+        assert_eq!(actual[1], Ok(QuantileMethod::Sketch(200)));
+        assert_eq!(actual[2], Ok(QuantileMethod::Sketch(400)));
+        assert!(actual[3].is_err());
+        assert!(actual[4].is_err());
+    }
+
+    #[test]
+    fn get_chunk_sizes_adds_up_to_requested_simulations() {
+        // assign
+        let number_of_simulations = 1_000_003;
+
+        // act
+        let actual = get_chunk_sizes(number_of_simulations, 8);
+
+        // assert
+        assert_eq!(actual.len(), 8);
+        assert_eq!(actual.iter().sum::<u32>(), number_of_simulations);
+        assert!(actual.iter().max().unwrap() - actual.iter().min().unwrap() <= 1);
+    }
+
+    #[test]
+    fn get_chunk_sizes_small_run_stays_on_one_thread() {
+        // assign
+        let number_of_simulations = 500;
+
+        // act
+        let actual = get_chunk_sizes(number_of_simulations, 8);
+
+        // assert
+        assert_eq!(actual, vec![500]);
+    }
+
+    #[test]
+    fn simulate_chunk_sketch_records_every_simulation() {
+        // assign
+        let data = vec![-0.01, 0.0, 0.01, 0.02];
+
+        // act
+        let actual = simulate_chunk(
+            &data,
+            10,
+            5_000,
+            QuantileMethod::Sketch(200),
+            Sampler::Independent,
+            7,
+        );
+
+        // assert
+        assert_eq!(actual.count(), 5_000);
+        let percentiles = actual.percentiles().unwrap();
+        assert!(percentiles._25th <= percentiles._50th);
+        assert!(percentiles._50th <= percentiles._75th);
+    }
+
+    #[test]
+    fn percentiles_converged_within_tolerance() {
+        // assign
+        let before = Percentiles {
+            _25th: -5,
+            _50th: 3,
+            _75th: 12,
+        };
+        let after = Percentiles {
+            _25th: -4,
+            _50th: 3,
+            _75th: 11,
+        };
+
+        // act
+        let actual = percentiles_converged(&before, &after, 1.0);
+
+        // assert
+        assert!(actual);
+    }
+
+    #[test]
+    fn percentiles_converged_one_percentile_moved_too_far() {
+        // assign
+        let before = Percentiles {
+            _25th: -5,
+            _50th: 3,
+            _75th: 12,
+        };
+        let after = Percentiles {
+            _25th: -5,
+            _50th: 3,
+            _75th: 14,
+        };
+
+        // act
+        let actual = percentiles_converged(&before, &after, 1.0);
+
+        // assert
+        assert!(!actual);
+    }
+
+    #[test]
+    fn monte_carlo_simulation_adaptive_constant_returns_stops_after_two_batches() {
+        // assign
+        let data = vec![0.01];
+        let settings = SimulationSettings {
+            periods: 10,
+            number_of_simulations: 1_000_000,
+            convergence: Some(Convergence {
+                batch_size: 1_000,
+                tolerance: 0.0,
+            }),
+            seed: 7,
+            ..test_settings()
+        };
+
+        // act
+        let actual = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+
+        // assert
+        assert_eq!(actual.simulations, 2_000);
+        assert_eq!(actual.percentiles._50th, 10);
+    }
+
+    #[test]
+    fn monte_carlo_simulation_adaptive_never_exceeds_maximum() {
+        // assign
+        let data = vec![-0.2, 0.3];
+        let settings = SimulationSettings {
+            number_of_simulations: 2_500,
+            convergence: Some(Convergence {
+                batch_size: 1_000,
+                tolerance: -1.0,
+            }),
+            seed: 7,
+            ..test_settings()
+        };
+
+        // act
+        let actual = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+
+        // assert
+        assert_eq!(actual.simulations, 2_500);
+    }
+
+    #[test]
+    fn get_seed_stays_within_what_json_readers_keep_exactly() {
+        // act
+        let picked: Vec<u64> = (0..100).map(|_| get_seed(None).unwrap()).collect();
+        let given = get_seed(Some(MAX_SEED));
+        let too_large = get_seed(Some(MAX_SEED + 1));
+
+        // assert
+        assert!(picked.iter().all(|seed| *seed <= MAX_SEED));
+        assert!(picked.iter().all(|seed| *seed as f64 as u64 == *seed));
+        assert_eq!(given, Ok(MAX_SEED));
+        assert!(too_large.is_err());
+    }
+
+    #[test]
+    fn convergence_refuses_tolerances_that_can_never_be_met() {
+        // act
+        let refused: Vec<f64> = [0.0, -1.0, f64::NAN, f64::INFINITY]
+            .into_iter()
+            .filter(|tolerance| Convergence::new(1_000, *tolerance).is_err())
+            .collect();
+        let accepted = Convergence::new(1_000, 0.5);
+
+        // assert
+        assert_eq!(refused.len(), 4);
+        assert_eq!(
+            accepted,
+            Ok(Convergence {
+                batch_size: 1_000,
+                tolerance: 0.5,
+            })
+        );
+    }
+
+    fn study_step(simulations: u32, order: &[&str]) -> StudyStep {
+        let predictions = order
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| Prediction {
+                symbol: symbol.to_string(),
+                percentiles: Percentiles {
+                    _25th: -(index as i32),
+                    _50th: 10 - index as i32,
+                    _75th: 20 - index as i32,
+                },
+                simulations,
+                observations: 250,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            })
+            .collect();
+        let ranks = order
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| (symbol.to_string(), index + 1))
+            .collect();
+        StudyStep {
+            simulations,
+            predictions,
+            ranks,
+        }
+    }
+
+    #[test]
+    fn get_rank_correlation_same_order_is_one() {
+        // assign
+        let ranks: HashMap<String, usize> = HashMap::from([
+            ("AAPL".to_string(), 1),
+            ("AAL".to_string(), 2),
+            ("AACG".to_string(), 3),
+        ]);
+
+        // act
+        let actual = get_rank_correlation(&ranks, &ranks.clone());
+
+        // assert
+        assert_eq!(actual, 1.0);
+    }
+
+    #[test]
+    fn get_rank_correlation_reversed_order_is_minus_one() {
+        // assign
+        let left: HashMap<String, usize> = HashMap::from([
+            ("AAPL".to_string(), 1),
+            ("AAL".to_string(), 2),
+            ("AACG".to_string(), 3),
+        ]);
+        let right: HashMap<String, usize> = HashMap::from([
+            ("AAPL".to_string(), 3),
+            ("AAL".to_string(), 2),
+            ("AACG".to_string(), 1),
+        ]);
+
+        // act
+        let actual = get_rank_correlation(&left, &right);
+
+        // assert
+        assert_eq!(actual, -1.0);
+    }
+
+    #[test]
+    fn get_stability_one_symbol_leaves_top_2() {
+        // assign
+        let previous = study_step(100_000, &["AAPL", "AAL", "AACG"]);
+        let current = study_step(250_000, &["AAPL", "AACG", "AAL"]);
+
+        // act
+        let actual = get_stability(&previous, &current, 2);
+
+        // assert
+        assert_eq!(actual.top_x_overlap, 1);
+        assert_eq!(actual.largest_rank_move, 1);
+        assert_eq!(actual.rank_correlation, 0.5);
+    }
+
+    #[test]
+    fn get_stable_count_top_settles_at_second_count() {
+        // assign
+        let steps = vec![
+            study_step(100_000, &["AAL", "AACG", "AAPL"]),
+            study_step(250_000, &["AAPL", "AAL", "AACG"]),
+            study_step(500_000, &["AAL", "AAPL", "AACG"]),
+            study_step(1_000_000, &["AAPL", "AAL", "AACG"]),
+        ];
+
+        // act
+        let actual = get_stable_count(&steps, 2);
+
+        // assert
+        assert_eq!(actual, Some(250_000));
+    }
+
+    #[test]
+    fn get_study_counts_sorts_unsorted_counts_before_the_stable_count() {
+        // assign
+        let counts = [500_000, 100_000, 1_000_000, 250_000, 500_000];
+        let tops: [&[&str]; 4] = [
+            &["AAL", "AACG", "AAPL"],
+            &["AAPL", "AAL", "AACG"],
+            &["AAL", "AAPL", "AACG"],
+            &["AAPL", "AAL", "AACG"],
+        ];
+
+        // act
+        let actual = get_study_counts(&counts);
+        let steps: Vec<StudyStep> = actual
+            .iter()
+            .zip(tops)
+            .map(|(count, top)| study_step(*count, top))
+            .collect();
+
+        // assert
+        assert_eq!(actual, vec![100_000, 250_000, 500_000, 1_000_000]);
+        assert_eq!(get_stable_count(&steps, 2), Some(250_000));
+    }
+
+    #[test]
+    fn get_study_html_escapes_symbols() {
+        // assign
+        let steps = vec![study_step(100_000, &["A<B>", "AAPL"])];
+
+        // act
+        let actual = get_study_html(&steps, 1, &Branding::default());
+
+        // assert
+        assert!(actual.contains("<td>A&lt;B&gt;</td>"));
+        assert!(!actual.contains("A<B>"));
+    }
+
+    #[test]
+    fn get_stable_count_top_changes_at_last_count() {
+        // assign
+        let steps = vec![
+            study_step(100_000, &["AAPL", "AAL", "AACG"]),
+            study_step(250_000, &["AACG", "AAL", "AAPL"]),
+        ];
+
+        // act
+        let actual = get_stable_count(&steps, 1);
+
+        // assert
+        assert_eq!(actual, None);
+    }
+
+    #[test]
+    fn get_stability_text_has_its_own_message_without_data() {
+        // assign
+        let changed = vec![
+            study_step(100_000, &["AAPL", "AAL"]),
+            study_step(250_000, &["AAL", "AAPL"]),
+        ];
+        let empty = vec![study_step(100_000, &[]), study_step(250_000, &[])];
+
+        // act
+        let none = get_stability_text(&[], 2);
+        let no_symbols = get_stability_text(&empty, 2);
+        let unstable = get_stability_text(&changed, 1);
+
+        // assert
+        assert_eq!(
+            none,
+            "No symbol was simulated, so there is no top 2 to compare."
+        );
+        assert_eq!(no_symbols, none);
+        assert_eq!(
+            unstable,
+            "The top 1 changed between the two largest simulation counts."
+        );
+    }
+
+    #[test]
+    fn get_study_csv_one_row_per_symbol_and_stability_footer() {
+        // assign
+        let steps = vec![
+            study_step(100_000, &["AAPL", "AAL"]),
+            study_step(250_000, &["AAPL", "AAL"]),
+        ];
+        let expected = "symbol,100000 25th,100000 50th,100000 75th,100000 rank,250000 25th,250000 50th,250000 75th,250000 rank,largest rank move,largest percentile shift\n\
+            AAPL,0,10,20,1,0,10,20,1,0,0\n\
+            AAL,-1,9,19,2,-1,9,19,2,0,0\n\
+            rank correlation,,,,,,,,1.0000,,\n\
+            top 1 overlap,,,,,,,,1/1,,\n\
+            largest rank move,,,,,,,,0,,\n\
+            top 1 stable,,,,yes,,,,yes,,\n";
+
+        // act
+        let actual = get_study_csv(&steps, 1);
+
+        // assert
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn simulate_antithetic_pair_mirrors_sorted_draws() {
+        // assign
+        let sorted = vec![-0.2, -0.1, 0.0, 0.1, 0.3];
+
+        // act
+        let (simulation, mirror) = simulate_antithetic_pair(&mut rand::rng(), &sorted, 20);
+
+        // assert
+        assert_eq!(simulation.len(), 20);
+        assert_eq!(mirror.len(), 20);
+        for (draw, mirrored) in simulation.iter().zip(mirror.iter()) {
+            let index = sorted.iter().position(|v| v == draw).unwrap();
+            assert_eq!(*mirrored, sorted[sorted.len() - 1 - index]);
+        }
+    }
+
+    #[test]
+    fn simulate_stratified_block_draws_each_stratum_once_per_day() {
+        // assign
+        let sorted = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let periods = 12;
+
+        // act
+        let actual =
+            simulate_stratified_block(&mut rand::rng(), &sorted, periods, sorted.len() as u32);
+
+        // assert
+        assert_eq!(actual.len(), sorted.len());
+        for day in 0..periods as usize {
+            let mut drawn: Vec<f64> = actual.iter().map(|path| path[day]).collect();
+            drawn.sort_by(|left, right| left.total_cmp(right));
+            assert_eq!(drawn, sorted);
+        }
+    }
+
+    #[test]
+    fn simulate_chunk_antithetic_odd_count_records_every_simulation() {
+        // assign
+        let data = vec![0.02, -0.01, 0.0, 0.01];
+
+        // act
+        let actual = simulate_chunk(
+            &data,
+            10,
+            1_001,
+            QuantileMethod::Exact,
+            Sampler::Antithetic,
+            7,
+        );
+
+        // assert
+        assert_eq!(actual.count(), 1_001);
+    }
+
+    #[test]
+    fn simulate_chunk_stratified_partial_block_records_every_simulation() {
+        // assign
+        let data = vec![0.02, -0.01, 0.0, 0.01];
+
+        // act
+        let actual = simulate_chunk(
+            &data,
+            10,
+            2_345,
+            QuantileMethod::Exact,
+            Sampler::Stratified,
+            7,
+        );
+
+        // assert
+        assert_eq!(actual.count(), 2_345);
+    }
+
+    /// Benchmark of the effective speedup of each sampler on the test_data set, run it with
+    /// cargo test --release -- --ignored sampler_effective_speedup --nocapture
+    #[test]
+    #[ignore]
+    fn sampler_effective_speedup() {
+        // assign
+        let symbols = read_symbols(&PathBuf::from("test_data"));
+        let repetitions = 200;
+        let simulations = 2_000;
+        let periods = 30;
+        // a sketch larger than the number of simulations never compacts, so its quantiles are exact at full precision
+        let quantile_method = QuantileMethod::Sketch(4_096);
+
+        // act
+        let mut speedups = Vec::new();
+        for sampler in [
+            Sampler::Independent,
+            Sampler::Antithetic,
+            Sampler::Stratified,
+        ] {
+            let mut variance_sum = 0.0;
+            let start = Instant::now();
+            for (_, data) in symbols.iter() {
+                let medians: Vec<f64> = (0..repetitions)
+                    .map(|_| {
+                        match simulate_chunk(
+                            data,
+                            periods,
+                            simulations,
+                            quantile_method,
+                            sampler,
+                            rand::random(),
+                        ) {
+                            OutcomeDistribution::Sketch(sketch) => sketch.quantile(0.5).unwrap(),
+                            OutcomeDistribution::Exact(_) => unreachable!(),
+                        }
+                    })
+                    .collect();
+                let mean = medians.iter().sum::<f64>() / repetitions as f64;
+                variance_sum += medians.iter().map(|m| (m - mean).powi(2)).sum::<f64>()
+                    / (repetitions - 1) as f64;
+            }
+            let seconds = start.elapsed().as_secs_f64();
+            speedups.push((sampler, variance_sum, seconds));
+        }
+
+        // assert
+        let (_, base_variance, base_seconds) = speedups[0];
+        for (sampler, variance, seconds) in speedups.iter() {
+            let speedup = (base_variance * base_seconds) / (variance * seconds);
+            println!(
+                "{sampler}: median variance {variance:.4}, {seconds:.2}s, effective speedup {speedup:.2}x"
+            );
+            assert!(variance.is_finite());
+        }
+    }
+
+    #[test]
+    fn monte_carlo_simulation_runs_exactly_the_requested_count() {
+        // assign
+        let data = vec![-0.01, 0.0, 0.01];
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 25_001,
+            seed: 7,
+            ..test_settings()
+        };
+
+        // act
+        let actual = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+
+        // assert
+        assert_eq!(actual.simulations, 25_001);
+    }
+
+    #[test]
+    fn check_simulation_count_matching_returns_count() {
+        // assign
+        let results = OutcomeDistribution::Exact(BTreeMap::from([(1, 2), (3, 1)]));
+
+        // act
+        let actual = check_simulation_count("AAPL", 3, &results);
+
+        // assert
+        assert_eq!(actual, Ok(3));
+    }
+
+    #[test]
+    fn check_simulation_count_mismatch_fails_loudly() {
+        // assign
+        let results = OutcomeDistribution::Exact(BTreeMap::from([(1, 2), (3, 1)]));
+
+        // act
+        let actual = check_simulation_count("AAPL", 4, &results);
+
+        // assert
+        assert_eq!(
+            actual,
+            Err("4 simulations requested but 3 were recorded".to_string())
+        );
+    }
+
+    #[test]
+    fn get_percentiles_counts_too_large_for_u32_cut_points() {
+        // assign
+        let results = BTreeMap::from([(-1, 40_000_000), (0, 40_000_000), (1, 40_000_000)]);
+
+        // act
+        let actual = get_percentiles(&results).unwrap();
+
+        // assert
+        assert_eq!(actual._25th, -1);
+        assert_eq!(actual._50th, 0);
+        assert_eq!(actual._75th, 1);
+    }
+
+    #[test]
+    fn monte_carlo_simulation_same_seed_same_prediction() {
+        // assign
+        let data = vec![-0.03, -0.01, 0.0, 0.01, 0.04];
+        let settings = SimulationSettings {
+            periods: 20,
+            number_of_simulations: 50_000,
+            quantile_method: QuantileMethod::Sketch(200),
+            sampler: Sampler::Stratified,
+            seed: 42,
+            ..test_settings()
+        };
+
+        // act
+        let first = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+        let second = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+
+        // assert
+        assert_eq!(first.percentiles._25th, second.percentiles._25th);
+        assert_eq!(first.percentiles._50th, second.percentiles._50th);
+        assert_eq!(first.percentiles._75th, second.percentiles._75th);
+    }
+
+    #[test]
+    fn simulate_chunk_different_seeds_different_draws() {
+        // assign
+        let data: Vec<f64> = (0..100).map(|i| i as f64 / 1000.0).collect();
+
+        // act
+        let first = simulate_chunk(
+            &data,
+            5,
+            10,
+            QuantileMethod::Sketch(200),
+            Sampler::Independent,
+            1,
+        );
+        let second = simulate_chunk(
+            &data,
+            5,
+            10,
+            QuantileMethod::Sketch(200),
+            Sampler::Independent,
+            2,
+        );
+
+        // assert
+        match (first, second) {
+            (OutcomeDistribution::Sketch(first), OutcomeDistribution::Sketch(second)) => {
+                assert_ne!(first.sorted_items(), second.sorted_items())
+            }
+            _ => panic!("expected sketches"),
+        }
+    }
+
+    #[test]
+    fn get_json_has_schema_version_metadata_and_every_prediction() {
+        // assign
+        let settings = SimulationSettings {
+            sampler: Sampler::Antithetic,
+            ..test_settings()
+        };
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: RunMetadata::new(&PathBuf::from("test_data"), &settings, 1),
+            predictions: vec![
+                Prediction {
+                    symbol: "AAPL".to_string(),
+                    percentiles: Percentiles {
+                        _25th: -1,
+                        _50th: 2,
+                        _75th: 5,
+                    },
+                    simulations: 10_000,
+                    observations: 250,
+                    data: BTreeMap::new(),
+                    fan_chart: Vec::new(),
+                    drawdown: DrawdownStats::default(),
+                    input: InputStats::default(),
+                },
+                Prediction {
+                    symbol: "AAL".to_string(),
+                    percentiles: Percentiles {
+                        _25th: -14,
+                        _50th: -8,
+                        _75th: -2,
+                    },
+                    simulations: 10_000,
+                    observations: 250,
+                    data: BTreeMap::new(),
+                    fan_chart: Vec::new(),
+                    drawdown: DrawdownStats::default(),
+                    input: InputStats::default(),
+                },
+            ],
+            top_predictions: vec![TopPredictions {
+                symbol: "AAPL".to_string(),
+                most_common: 2,
+                highest_low: -1,
+                total_span: 6,
+                weighted_span: 0,
+                simulations: 10_000,
+            }],
+            summary: RunSummary {
+                status: RunStatus::Partial,
+                processed: vec!["AAPL".to_string(), "MSFT".to_string()],
+                skipped: Vec::new(),
+                failed: vec![SymbolIssue {
+                    symbol: "BAD".to_string(),
+                    reason: "item 2: invalid float literal".to_string(),
+                }],
+                aborted: None,
+            },
+        };
+
+        // act
+        let actual = get_json(&results);
+
+        // assert
+        let value: serde_json::Value = serde_json::from_str(&actual).unwrap();
+        assert_eq!(value["schema_version"], 1);
+        assert_eq!(value["metadata"]["days"], 30);
+        assert_eq!(value["metadata"]["seed"], 99);
+        assert_eq!(value["metadata"]["sampler"], "antithetic");
+        assert_eq!(value["metadata"]["source_dir"], "test_data");
+        assert_eq!(value["metadata"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(value["predictions"].as_array().unwrap().len(), 2);
+        assert_eq!(value["predictions"][1]["percentiles"]["p25"], -14);
+        assert_eq!(value["top_predictions"][0]["total_span"], 6);
+        let round_trip: RunResults = serde_json::from_str(&actual).unwrap();
+        assert_eq!(round_trip.metadata, results.metadata);
+        assert_eq!(round_trip.top_predictions, results.top_predictions);
+        assert_eq!(value["summary"]["status"], "partial");
+        assert_eq!(value["summary"]["failed"][0]["symbol"], "BAD");
+        assert!(value["summary"].get("aborted").is_none());
+        assert_eq!(round_trip.summary, results.summary);
+    }
+
+    #[test]
+    fn prediction_written_before_observations_still_reads() {
+        // assign
+        let json = r#"{"symbol": "AAPL", "percentiles": {"p25": -1, "p50": 2, "p75": 5}, "simulations": 100}"#;
+
+        // act
+        let actual: Prediction = serde_json::from_str(json).unwrap();
+
+        // assert
+        assert_eq!(actual.observations, 0);
+        assert_eq!(actual.percentiles._50th, 2);
+    }
+
+    #[test]
+    fn get_output_path_single_format_keeps_output_file() {
+        // assign
+        let output = PathBuf::from("out/predictions.html");
+
+        // act
+        let actual = get_output_path(&output, OutputFormat::Json, 1);
+
+        // assert
+        assert_eq!(actual, PathBuf::from("out/predictions.html"));
+    }
+
+    #[test]
+    fn get_output_path_several_formats_replaces_extension() {
+        // assign
+        let output = PathBuf::from("out/predictions.html");
+        let formats = ["html", "json"];
+
+        // act
+        let actual: Vec<PathBuf> = formats
+            .iter()
+            .map(|format| get_output_path(&output, format.parse().unwrap(), formats.len()))
+            .collect();
+
+        // assert
+        assert_eq!(
+            actual,
+            vec![
+                PathBuf::from("out/predictions.html"),
+                PathBuf::from("out/predictions.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn get_csv_every_symbol_with_rank_under_every_ordering() {
+        // assign
+        let predictions = vec![
+            Prediction {
+                symbol: "AAPL".to_string(),
+                percentiles: Percentiles {
+                    _25th: -1,
+                    _50th: 2,
+                    _75th: 5,
+                },
+                simulations: 10_000,
+                observations: 23,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            },
+            Prediction {
+                symbol: "AACG".to_string(),
+                percentiles: Percentiles {
+                    _25th: -6,
+                    _50th: 9,
+                    _75th: 27,
+                },
+                simulations: 10_000,
+                observations: 23,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            },
+            Prediction {
+                symbol: "AAON".to_string(),
+                percentiles: Percentiles {
+                    _25th: 2,
+                    _50th: 5,
+                    _75th: 9,
+                },
+                simulations: 10_000,
+                observations: 21,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            },
+        ];
+        let expected = "symbol,observations,simulations,25th,50th,75th,most_common,highest_low,total_span,weighted_span,most_common_rank,highest_low_rank,total_span_rank,weighted_span_rank\n\
+            AACG,23,10000,-6,9,27,9,-6,33,3,1,3,3,1\n\
+            AAON,21,10000,2,5,9,5,2,7,1,2,1,2,2\n\
+            AAPL,23,10000,-1,2,5,2,-1,6,0,3,2,1,3\n";
+
+        // act
+        let actual = get_csv(&predictions);
+
+        // assert
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn run_metadata_parameters_lists_every_setting() {
+        // assign
+        let settings = SimulationSettings {
+            quantile_method: QuantileMethod::Sketch(200),
+            convergence: Some(Convergence {
+                batch_size: 5_000,
+                tolerance: 0.5,
+            }),
+            sampler: Sampler::Stratified,
+            seed: 7,
+            ..test_settings()
+        };
+        let metadata = RunMetadata::new(&PathBuf::from("test_data"), &settings, 3);
+
+        // act
+        let actual = metadata.parameters();
+
+        // assert
+        let names: Vec<&str> = actual.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "days",
+                "simulations",
+                "adaptive tolerance",
+                "batch size",
+                "quantiles",
+                "sampler",
+                "seed",
+                "top x",
+                "strategy",
+                "source directory",
+                "timestamp",
+                "version",
+                "config",
+                "options"
+            ]
+        );
+        assert_eq!(actual[2].1, "0.5");
+        assert_eq!(actual[3].1, "5000");
+        assert_eq!(actual[4].1, "sketch:200");
+        assert_eq!(actual[5].1, "stratified");
+        assert_eq!(actual[8].1, "most-common");
+        assert_eq!(actual[9].1, "test_data");
+        assert_eq!(actual[12].1, "n/a");
+        assert_eq!(actual[13].1, "n/a");
+    }
+
+    #[test]
+    fn get_xlsx_writes_workbook() {
+        // assign
+        let predictions = vec![
+            Prediction {
+                symbol: "AAPL".to_string(),
+                percentiles: Percentiles {
+                    _25th: -1,
+                    _50th: 2,
+                    _75th: 5,
+                },
+                simulations: 10_000,
+                observations: 23,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            },
+            Prediction {
+                symbol: "AACG".to_string(),
+                percentiles: Percentiles {
+                    _25th: -6,
+                    _50th: 9,
+                    _75th: 27,
+                },
+                simulations: 10_000,
+                observations: 23,
+                data: BTreeMap::new(),
+                fan_chart: Vec::new(),
+                drawdown: DrawdownStats::default(),
+                input: InputStats::default(),
+            },
+        ];
+        let top_predictions = get_highest_x(1, &predictions, Box::new(MostCommonResult {}));
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: test_metadata(1),
+            predictions,
+            top_predictions,
+            summary: RunSummary::default(),
+        };
+
+        // act
+        let actual = get_xlsx(&results);
+
+        // assert
+        let workbook = actual.unwrap();
+        // an xlsx file is a zip archive
+        assert_eq!(&workbook[..2], b"PK");
+    }
+
+    #[test]
+    fn thresholds_rate_each_metric() {
+        // assign
+        let thresholds = Thresholds {
+            most_common_green: 10,
+            most_common_yellow: 5,
+            highest_low_green: 2,
+            highest_low_yellow: 0,
+            total_span_green: 6,
+            total_span_yellow: 12,
+        };
+
+        // act
+        let most_common = [12, 10, 7, 5, 4].map(|value| thresholds.most_common(value));
+        let highest_low = [2, 1, -1].map(|value| thresholds.highest_low(value));
+        let total_span = [6, 12, 13].map(|value| thresholds.total_span(value));
+        let weighted_span = [1, 0, -1].map(|value| thresholds.weighted_span(value));
+
+        // assert
+        assert_eq!(
+            most_common,
+            [
+                Rating::Green,
+                Rating::Green,
+                Rating::Yellow,
+                Rating::Yellow,
+                Rating::Red
+            ]
+        );
+        assert_eq!(highest_low, [Rating::Green, Rating::Yellow, Rating::Red]);
+        assert_eq!(total_span, [Rating::Green, Rating::Yellow, Rating::Red]);
+        assert_eq!(weighted_span, [Rating::Green, Rating::Yellow, Rating::Red]);
+    }
+
+    /// 30 days of 10000 exact, independent simulations from seed 99, what the output tests are written against
+    fn test_settings() -> SimulationSettings {
+        SimulationSettings {
+            periods: 30,
+            number_of_simulations: 10_000,
+            quantile_method: QuantileMethod::Exact,
+            convergence: None,
+            sampler: Sampler::Independent,
+            seed: 99,
+        }
+    }
+
+    fn test_metadata(top_x: usize) -> RunMetadata {
+        RunMetadata::new(&PathBuf::from("test_data"), &test_settings(), top_x)
+    }
+
+    fn table_predictions() -> Vec<TopPredictions> {
+        vec![
+            TopPredictions {
+                symbol: "AACG".to_string(),
+                most_common: 9,
+                highest_low: -6,
+                total_span: 33,
+                weighted_span: 3,
+                simulations: 10_000,
+            },
+            TopPredictions {
+                symbol: "AAPL".to_string(),
+                most_common: 2,
+                highest_low: -1,
+                total_span: 6,
+                weighted_span: 0,
+                simulations: 10_000,
+            },
+        ]
+    }
+
+    #[test]
+    fn get_table_plain_text_aligned_with_rating_markers() {
+        // assign
+        let metadata = test_metadata(2);
+        let expected = "Stock Predictions - 30 days, 10000 simulations, independent sampling, seed 99\n\n\
+            rank  symbol  most common  bottom 25th  25th to 75th span  weighted span  simulations\n\
+            ----  ------  -----------  -----------  -----------------  -------------  -----------\n   \
+            1  AACG      9 [green]  -6 [yellow]        33 [yellow]      3 [green]        10000\n   \
+            2  AAPL     2 [yellow]   -1 [green]          6 [green]     0 [yellow]        10000\n\n\
+            Run complete: 0 processed, 0 skipped, 0 failed.\n";
+
+        // act
+        let actual = get_table(
+            &table_predictions(),
+            &metadata,
+            &RunSummary::default(),
+            false,
+        );
+
+        // assert
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn get_table_color_wraps_rated_cells() {
+        // assign
+        let metadata = test_metadata(2);
+
+        // act
+        let actual = get_table(
+            &table_predictions(),
+            &metadata,
+            &RunSummary::default(),
+            true,
+        );
+
+        // assert
+        assert!(actual.contains("\x1b[32m          9\x1b[0m"));
+        assert!(actual.contains("\x1b[33m            0\x1b[0m"));
+        assert!(!actual.contains("[green]"));
+    }
+
+    #[test]
+    fn get_notes_loss_skew_and_early_convergence() {
+        // assign
+        let settings = SimulationSettings {
+            number_of_simulations: 20_000,
+            ..test_settings()
+        };
+        let metadata = RunMetadata::new(&PathBuf::from("test_data"), &settings, 2);
+        let predictions = table_predictions();
+
+        // act
+        let actual = get_notes(&predictions[0], &metadata);
+
+        // assert
+        assert_eq!(
+            actual,
+            vec![
+                "a quarter of the simulations lost more than 6 on 100 invested",
+                "skewed up, the 75th percentile is further above the median than the 25th is below it",
+                "converged after 10000 of 20000 simulations"
+            ]
+        );
+    }
+
+    #[test]
+    fn get_prediction_views_rank_and_rate_top_x() {
+        // assign
+        let metadata = test_metadata(2);
+
+        // act
+        let actual = get_prediction_views(&table_predictions(), &metadata);
+
+        // assert
+        assert_eq!(actual.len(), 2);
+        assert_eq!(actual[1].rank, 2);
+        assert_eq!(actual[1].symbol, "AAPL");
+        assert_eq!(actual[1].most_common.value, 2);
+        assert_eq!(actual[1].most_common.rating, Rating::Yellow);
+        assert_eq!(actual[1].total_span.rating, Rating::Green);
+        assert_eq!(
+            actual[1].notes,
+            vec![
+                "a quarter of the simulations lost more than 1 on 100 invested",
+                "even, the 25th and 75th percentiles are the same distance from the median"
+            ]
+        );
+    }
+
+    #[test]
+    fn get_markdown_ranked_table_parameters_and_notes() {
+        // assign
+        let metadata = test_metadata(2);
+
+        // act
+        let actual = get_markdown(&table_predictions(), &metadata, &RunSummary::default());
+
+        // assert
+        assert!(actual.starts_with("# Stock Predictions - "));
+        assert!(actual.contains(
+            "| rank | symbol | most common | bottom 25th | 25th to 75th span | weighted span | simulations |\n\
+            | ---: | --- | ---: | ---: | ---: | ---: | ---: |\n\
+            | 1 | AACG | 🟢 9 | 🟡 -6 | 🟡 33 | 🟢 3 | 10000 |\n\
+            | 2 | AAPL | 🟡 2 | 🟢 -1 | 🟢 6 | 🟡 0 | 10000 |\n"
+        ));
+        assert!(actual.contains("| seed | 99 |\n"));
+        assert!(actual.contains("| source directory | test_data |\n"));
+        assert!(actual.contains(
+            "### AAPL\n\n- a quarter of the simulations lost more than 1 on 100 invested\n"
+        ));
+    }
+
+    #[test]
+    fn outcome_distribution_histogram_keeps_every_simulation() {
+        // assign
+        let mut sketch = KllSketch::new(200, 1);
+        for value in 0..10_000 {
+            sketch.update((value % 50) as f64 - 10.2);
+        }
+        let exact = OutcomeDistribution::Exact(BTreeMap::from([(-3, 4), (2, 7)]));
+        let sketch = OutcomeDistribution::Sketch(sketch);
+
+        // act
+        let exact_histogram = exact.histogram();
+        let sketch_histogram = sketch.histogram();
+
+        // assert
+        assert_eq!(exact_histogram, BTreeMap::from([(-3, 4), (2, 7)]));
+        assert_eq!(
+            sketch_histogram.values().map(|c| *c as u64).sum::<u64>(),
+            10_000
+        );
+        assert!(*sketch_histogram.keys().next().unwrap() >= -10);
+        assert!(*sketch_histogram.keys().next_back().unwrap() <= 39);
+    }
+
+    #[test]
+    fn monte_carlo_simulation_keeps_histogram() {
+        // assign
+        let data = vec![-0.02, -0.01, 0.0, 0.01, 0.02, 0.03];
+        let settings = SimulationSettings {
+            periods: 10,
+            number_of_simulations: 5_000,
+            seed: 3,
+            ..test_settings()
+        };
+
+        // act
+        let actual = monte_carlo_simulation("TEST".to_string(), &data, &settings).unwrap();
+
+        // assert
+        assert_eq!(actual.data.values().sum::<u32>(), 5_000);
+        assert_eq!(actual.data.values().sum::<u32>(), actual.simulations);
+    }
+
+    #[test]
+    fn get_histogram_svg_bars_and_percentile_markers() {
+        // assign
+        let data = BTreeMap::from([(-2, 1), (0, 4), (1, 2), (3, 1)]);
+        let percentiles = Percentiles {
+            _25th: 0,
+            _50th: 0,
+            _75th: 1,
+        };
+
+        // act
+        let actual = get_histogram_svg(&data, &percentiles);
+
+        // assert
+        assert!(actual.starts_with("<svg class=\"histogram\""));
+        assert!(actual.ends_with("</svg>"));
+        // six outcomes wide, 60 units each, the tallest bar fills the plot
+        assert!(actual.contains(
+            "<rect class=\"bar\" x=\"120.0\" y=\"0.0\" width=\"60.0\" height=\"110.0\"/>"
+        ));
+        assert_eq!(actual.matches("<rect").count(), 4);
+        assert!(actual.contains("<line class=\"marker p25\" x1=\"150.0\""));
+        assert!(actual.contains("<line class=\"marker p75\" x1=\"210.0\""));
+        assert!(actual.contains("25th 0 / 50th 0 / 75th 1"));
+        assert!(!actual.contains("<script"));
+    }
+
+    #[test]
+    fn get_histogram_svg_outcomes_at_the_ends_of_i32() {
+        // assign
+        let data = BTreeMap::from([(-100, 3), (i32::MAX, 2)]);
+        let percentiles = Percentiles {
+            _25th: -100,
+            _50th: -100,
+            _75th: i32::MAX,
+        };
+
+        // act
+        let actual = get_histogram_svg(&data, &percentiles);
+
+        // assert
+        assert_eq!(actual.matches("<rect").count(), 2);
+        assert!(actual.contains(&format!("Outcomes from -100 to {}", i32::MAX)));
+    }
+
+    #[test]
+    fn get_histogram_svg_groups_wide_ranges_and_skips_empty() {
+        // assign
+        let data: BTreeMap<i32, u32> = (-100..200).map(|outcome| (outcome, 1)).collect();
+        let percentiles = Percentiles {
+            _25th: -25,
+            _50th: 50,
+            _75th: 125,
+        };
+
+        // act
+        let actual = get_histogram_svg(&data, &percentiles);
+        let empty = get_histogram_svg(&BTreeMap::new(), &percentiles);
+
+        // assert
+        assert_eq!(actual.matches("<rect").count(), 60);
+        assert_eq!(empty, "");
+    }
+
+    #[test]
+    fn get_path_summary_one_ordered_band_per_day() {
+        // assign
+        let data = vec![-0.02, -0.01, 0.0, 0.01, 0.02, 0.03];
+        let settings = SimulationSettings {
+            periods: 20,
+            ..test_settings()
+        };
+
+        // act
+        let (actual, _) = get_path_summary("AAPL", &data, &settings, 2_000);
+
+        // assert
+        assert_eq!(actual.len(), 20);
+        for (index, band) in actual.iter().enumerate() {
+            assert_eq!(band.day, index as u32 + 1);
+            assert!(band.p10 <= band.p25);
+            assert!(band.p25 <= band.p50);
+            assert!(band.p50 <= band.p75);
+            assert!(band.p75 <= band.p90);
+        }
+        // the spread of the running value widens over the horizon
+        assert!(actual[19].p90 - actual[19].p10 > actual[0].p90 - actual[0].p10);
+        assert_eq!(actual, get_path_summary("AAPL", &data, &settings, 2_000).0);
+    }
+
+    #[test]
+    fn get_path_summary_single_return() {
+        // assign
+        let data = vec![0.1];
+        let settings = SimulationSettings {
+            periods: 2,
+            ..test_settings()
+        };
+
+        // act
+        let (actual, _) = get_path_summary("AAPL", &data, &settings, 10);
+
+        // assert
+        assert_eq!(actual.len(), 2);
+        assert!((actual[0].p10 - 10.0).abs() < 1e-9);
+        assert!((actual[1].p90 - 21.0).abs() < 1e-9);
+        assert!(get_path_summary("AAPL", &[], &settings, 10).0.is_empty());
+    }
+
+    #[test]
+    fn get_fan_chart_svg_bands_median_and_zero_line() {
+        // assign
+        let bands = vec![
+            FanBand {
+                day: 1,
+                p10: -10.0,
+                p25: -5.0,
+                p50: 0.0,
+                p75: 5.0,
+                p90: 10.0,
+            },
+            FanBand {
+                day: 2,
+                p10: -20.0,
+                p25: -5.0,
+                p50: 5.0,
+                p75: 15.0,
+                p90: 35.0,
+            },
+        ];
+
+        // act
+        let actual = get_fan_chart_svg(&bands);
+
+        // assert
+        assert!(actual.starts_with("<svg class=\"fan-chart\""));
+        assert!(actual.ends_with("</svg>"));
+        // values run from 35 at the top to -20 at the bottom of a 110 high plot, 2 units per point
+        assert!(actual.contains("<polygon class=\"outer\" points=\"0.0,70.0 180.0,50.0 360.0,0.0 360.0,110.0 180.0,90.0\"/>"));
+        assert!(actual
+            .contains("<polyline class=\"median\" points=\"0.0,70.0 180.0,70.0 360.0,60.0\"/>"));
+        assert!(actual.contains("<line class=\"zero\" x1=\"0\" y1=\"70.0\""));
+        assert!(actual.contains("day 2: 10th -20 / 25th -5 / 50th 5 / 75th 15 / 90th 35"));
+        assert_eq!(get_fan_chart_svg(&[]), "");
+    }
+
+    #[test]
+    fn get_interactive_html_every_symbol_sortable_and_self_contained() {
+        // assign
+        let predictions: Vec<Prediction> =
+            [("AAPL", -1, 2, 5), ("AACG", -6, 9, 27), ("A&B", 2, 5, 9)]
+                .iter()
+                .map(|(symbol, low, median, high)| Prediction {
+                    symbol: symbol.to_string(),
+                    percentiles: Percentiles {
+                        _25th: *low,
+                        _50th: *median,
+                        _75th: *high,
+                    },
+                    simulations: 10_000,
+                    observations: 23,
+                    data: BTreeMap::new(),
+                    fan_chart: Vec::new(),
+                    drawdown: DrawdownStats::default(),
+                    input: InputStats::default(),
+                })
+                .collect();
+        let top_predictions = get_highest_x(1, &predictions, Box::new(MostCommonResult {}));
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: test_metadata(1),
+            predictions,
+            top_predictions,
+            summary: RunSummary::default(),
+        };
+
+        // act
+        let actual = get_interactive_html(&results, OutputSettings::default().style());
+
+        // assert
+        assert_eq!(actual.matches("<tr><td>").count(), 3);
+        assert!(actual.contains("<th data-key=\"25th\">25th</th>"));
+        assert!(actual.contains("<th data-key=\"total_span\">total span</th>"));
+        assert!(actual.contains("<tr><td>AACG</td><td data-value=\"23\">23</td>"));
+        assert!(actual.contains("<td class=\"green\" data-value=\"9\">9</td>"));
+        assert!(actual.contains("<tr><td>A&amp;B</td>"));
+        assert!(actual.contains("id=\"filter\""));
+        assert!(actual.contains("function parseFilter"));
+        assert!(!actual.contains("src="));
+    }
+
+    #[test]
+    fn get_path_summary_drawdowns() {
+        // assign
+        let data = vec![-0.1];
+        let settings = SimulationSettings {
+            periods: 2,
+            ..test_settings()
+        };
+
+        // act
+        let (_, actual) = get_path_summary("AAPL", &data, &settings, 10);
+
+        // assert
+        // 100 falls to 90 and then 81
+        assert!((actual.p50 - 19.0).abs() < 1e-9);
+        assert!((actual.worst - 19.0).abs() < 1e-9);
+        assert_eq!(get_path_summary("AAPL", &[0.1], &settings, 10).1.worst, 0.0);
+    }
+
+    #[test]
+    fn get_path_summary_last_day_is_the_simulated_distribution() {
+        // assign
+        let data = vec![-0.03, -0.01, 0.0, 0.01, 0.02, 0.04];
+        let adaptive = SimulationSettings {
+            number_of_simulations: 50_000,
+            convergence: Some(Convergence::new(5_000, 0.5).unwrap()),
+            ..test_settings()
+        };
+        let runs = [
+            test_settings(),
+            SimulationSettings {
+                sampler: Sampler::Antithetic,
+                number_of_simulations: 10_001,
+                ..test_settings()
+            },
+            SimulationSettings {
+                sampler: Sampler::Stratified,
+                ..test_settings()
+            },
+            SimulationSettings {
+                quantile_method: QuantileMethod::Sketch(200),
+                ..test_settings()
+            },
+            adaptive,
+        ];
+
+        for settings in runs {
+            // act
+            let prediction = monte_carlo_simulation("AAPL".to_string(), &data, &settings).unwrap();
+            let (actual, _) = get_path_summary("AAPL", &data, &settings, prediction.simulations);
+
+            // assert
+            let last = actual.last().unwrap();
+            assert_eq!(
+                last.p50.round() as i32,
+                prediction.percentiles._50th,
+                "{settings:?}"
+            );
+            assert_eq!(
+                last.p25.round() as i32,
+                prediction.percentiles._25th,
+                "{settings:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn add_path_summaries_only_draws_the_top_x() {
+        // assign
+        let data = vec![-0.02, 0.0, 0.01, 0.03];
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 1_000,
+            ..test_settings()
+        };
+        let mut predictions: Vec<Prediction> = ["AAPL", "MSFT"]
+            .iter()
+            .map(|symbol| monte_carlo_simulation(symbol.to_string(), &data, &settings).unwrap())
+            .collect();
+        let top = get_highest_x(1, &predictions, Box::new(MostCommonResult {}));
+        let inputs = HashMap::from([
+            ("AAPL".to_string(), data.clone()),
+            ("MSFT".to_string(), data.clone()),
+        ]);
+
+        // act
+        add_path_summaries(&mut predictions, &top, &inputs, &settings);
+
+        // assert
+        for prediction in &predictions {
+            let drawn = prediction.symbol == top[0].symbol;
+            assert_eq!(prediction.fan_chart.len(), if drawn { 5 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn get_input_stats_happy_path() {
+        // assign
+        let data = vec![0.02, -0.01, 0.0, 0.03];
+
+        // act
+        let actual = get_input_stats(&data);
+
+        // assert
+        assert!((actual.mean - 0.01).abs() < 1e-12);
+        assert!((actual.std_dev - 0.000_250_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(actual.min, -0.01);
+        assert_eq!(actual.median, 0.0);
+        assert_eq!(actual.max, 0.03);
+        assert_eq!(actual.positive, 0.5);
+        assert_eq!(get_input_stats(&[]), InputStats::default());
+    }
+
+    #[test]
+    fn get_percentile_table_matches_get_percentiles() {
+        // assign
+        let data: BTreeMap<i32, u32> = (-10..10).map(|outcome| (outcome, 5)).collect();
+        let expected = get_percentiles(&data).unwrap();
+
+        // act
+        let actual = get_percentile_table(&data, &[5, 25, 50, 75, 95]);
+
+        // assert
+        assert_eq!(
+            actual,
+            vec![
+                (5, -10),
+                (25, expected._25th),
+                (50, expected._50th),
+                (75, expected._75th),
+                (95, 8)
+            ]
+        );
+    }
+
+    #[test]
+    fn get_details_dir_beside_index() {
+        // assign
+        let index = PathBuf::from("out/predictions.html");
+
+        // act
+        let actual = get_details_dir(&index);
+
+        // assert
+        assert_eq!(actual, PathBuf::from("out/predictions_files"));
+    }
+
+    #[test]
+    fn get_details_dir_index_without_extension() {
+        // assign
+        let index = PathBuf::from("out/predictions");
+
+        // act
+        let actual = get_details_dir(&index);
+
+        // assert
+        assert_ne!(actual, index);
+        assert_eq!(actual, PathBuf::from("out/predictions_files"));
+    }
+
+    #[test]
+    fn get_detail_html_every_section_and_link_back() {
+        // assign
+        let settings = test_settings();
+        let metadata = test_metadata(2);
+        let data = vec![-0.02, -0.01, 0.0, 0.01, 0.02, 0.03];
+        let prediction = monte_carlo_simulation("AACG".to_string(), &data, &settings).unwrap();
+        let top = get_highest_x(2, &vec![prediction], Box::new(MostCommonResult {}));
+        let views = get_prediction_views(&top, &metadata);
+        let mut predictions =
+            vec![monte_carlo_simulation("AACG".to_string(), &data, &settings).unwrap()];
+        let inputs = HashMap::from([("AACG".to_string(), data.clone())]);
+        add_path_summaries(&mut predictions, &top, &inputs, &settings);
+        let prediction = predictions.remove(0);
+
+        // act
+        let actual = get_detail_html(
+            &views[0],
+            &prediction,
+            &metadata,
+            "../predictions.html",
+            OutputSettings::default().style(),
+        );
+
+        // assert
+        assert!(actual.contains("<a class=\"back\" href=\"../predictions.html\">"));
+        assert!(actual.contains("<h1>AACG</h1>"));
+        assert!(actual.contains("<tr><td>rank</td><td>1 of 2</td></tr>"));
+        assert!(actual.contains(&format!(
+            "<tr><td>25th</td><td>{}</td></tr>",
+            prediction.percentiles._25th
+        )));
+        assert!(actual.contains("<h2>Largest Drawdown per Path</h2>"));
+        assert!(actual.contains("<tr><td>observations</td><td>6</td></tr>"));
+        assert!(actual.contains("<tr><td>up days</td><td>50.00%</td></tr>"));
+        assert!(actual.contains("<tr><td>seed</td><td>99</td></tr>"));
+        assert_eq!(actual.matches("<svg").count(), 2);
+    }
+
+    #[test]
+    fn render_template_variables_sections_and_escaping() {
+        // assign
+        let context = serde_json::json!({
+            "title": "Picks & Pans",
+            "raw": "<b>bold</b>",
+            "metadata": { "days": 30 },
+            "cards": [
+                { "symbol": "AAPL", "notes": ["up", "even"] },
+                { "symbol": "AAL", "notes": [] }
+            ],
+            "header": null
+        });
+        let template = "{{! comment }}<h1>{{title}}</h1>{{{raw}}} {{metadata.days}} days\
+            {{#cards}}[{{symbol}}{{#notes}} {{.}}{{/notes}}{{^notes}} none{{/notes}}]{{/cards}}\
+            {{#header}}never{{/header}}{{^header}}no header{{/header}}{{missing}}";
+
+        // act
+        let actual = render_template(template, &context);
+
+        // assert
+        assert_eq!(
+            actual.unwrap(),
+            "<h1>Picks &amp; Pans</h1><b>bold</b> 30 days[AAPL up even][AAL none]no header"
+        );
+    }
+
+    #[test]
+    fn render_template_unbalanced_sections() {
+        // assign
+        let context = serde_json::json!({});
+
+        // act
+        let unclosed = render_template("{{#cards}}x", &context);
+        let mismatched = render_template("{{#cards}}x{{/notes}}", &context);
+        let unopened = render_template("x{{/cards}}", &context);
+
+        // assert
+        assert_eq!(unclosed, Err("section cards is never closed".to_string()));
+        assert_eq!(
+            mismatched,
+            Err("section cards is closed by notes".to_string())
+        );
+        assert_eq!(
+            unopened,
+            Err("section cards is closed but never opened".to_string())
+        );
+    }
+
+    #[test]
+    fn render_template_example_with_run_results() {
+        // assign
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: RunMetadata {
+                header: Some("Example Capital".to_string()),
+                disclaimer: Some("Not investment advice.".to_string()),
+                ..test_metadata(2)
+            },
+            predictions: Vec::new(),
+            top_predictions: table_predictions(),
+            summary: partial_summary(),
+        };
+        let context = get_template_context(&results, "predictions", "body {}");
+
+        // act
+        let actual = render_template(include_str!("../doc/template.example.html"), &context);
+
+        // assert
+        let actual = actual.unwrap();
+        assert!(actual.contains("<style>body {}</style>"));
+        assert!(actual.contains("<div class=\"company-header\">Example Capital</div>"));
+        assert!(actual.contains("<a href=\"predictions/AACG.html\">1. AACG</a>"));
+        assert!(actual.contains("<span class=\"primary green\">9</span>"));
+        assert!(actual
+            .contains("<li>a quarter of the simulations lost more than 6 on 100 invested</li>"));
+        assert!(actual.contains(
+            "<footer class=\"run-summary failed\"><p>Run failed: 2 processed, 1 skipped, 1 failed.</p>"
+        ));
+        assert!(actual.contains("<p class=\"aborted\">Aborted: too many failures</p>"));
+        assert!(actual
+            .contains("<li><strong>ZZZ</strong> skipped: run aborted before it was read</li>"));
+        assert!(actual.contains("<li><strong>BAD</strong> failed: item 2: not a number</li>"));
+        assert!(actual.contains("<footer class=\"disclaimer\">Not investment advice.</footer>"));
+        assert!(!actual.contains("No symbols were simulated."));
+    }
+
+    #[test]
+    fn reports_carry_header_and_disclaimer() {
+        // assign
+        let metadata = RunMetadata {
+            header: Some("Example Capital".to_string()),
+            disclaimer: Some("Not investment advice.".to_string()),
+            ..test_metadata(2)
+        };
+        let results = RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: metadata.clone(),
+            predictions: Vec::new(),
+            top_predictions: table_predictions(),
+            summary: RunSummary::default(),
+        };
+
+        // act
+        let html = get_html(&results, "predictions", "body {}");
+        let markdown = get_markdown(&table_predictions(), &metadata, &RunSummary::default());
+        let table = get_table(
+            &table_predictions(),
+            &metadata,
+            &RunSummary::default(),
+            false,
+        );
+        let json = get_json(&results);
+
+        // assert
+        assert!(html.contains("<style>body {}</style>"));
+        assert!(html.contains("<div class=\"company-header\">Example Capital</div><h1>"));
+        assert!(
+            html.contains("<footer class=\"disclaimer\">Not investment advice.</footer></body>")
+        );
+        assert!(html.contains("<a href=\"predictions/AACG.html\">AACG</a>"));
+        assert!(markdown.starts_with("**Example Capital**\n\n# "));
+        assert!(markdown.ends_with("\n---\n\n*Not investment advice.*\n"));
+        assert!(table.starts_with("Example Capital\nStock Predictions"));
+        assert!(table.ends_with("\nNot investment advice.\n"));
+        assert!(json.contains("\"disclaimer\": \"Not investment advice.\""));
+    }
+
+    fn dated_history(start: u32, returns: &[f64]) -> History {
+        returns
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                let date = chrono::NaiveDate::from_ymd_opt(2024, 1, start + index as u32).unwrap();
+                (date, *value)
+            })
+            .collect()
+    }
+
+    fn rebalance(date: &str, portfolio: f64, benchmark: f64) -> Rebalance {
+        Rebalance {
+            date: date.to_string(),
+            picks: vec!["AAPL".to_string()],
+            portfolio,
+            benchmark,
+            forecasts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn get_dated_returns_reads_one_date_and_return_per_line() {
+        let history = get_dated_returns("2024-01-02,0.01\n\n2024-01-03, -0.02\n").unwrap();
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history.values().copied().collect::<Vec<f64>>(),
+            vec![0.01, -0.02]
+        );
+        assert!(get_dated_returns("2024-01-02,0.01\n0.02\n")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 2:"));
+        assert!(get_dated_returns("01/02/2024,0.01").is_err());
+    }
+
+    #[test]
+    fn get_calendar_joins_the_dates_of_every_symbol() {
+        let histories = vec![
+            ("A".to_string(), dated_history(1, &[0.0, 0.0])),
+            ("B".to_string(), dated_history(2, &[0.0, 0.0])),
+        ];
+
+        let calendar = get_calendar(&histories);
+
+        assert_eq!(calendar.len(), 3);
+        assert_eq!(calendar[0].to_string(), "2024-01-01");
+        assert_eq!(calendar[2].to_string(), "2024-01-03");
+    }
+
+    #[test]
+    fn get_realized_return_compounds_and_treats_missing_days_as_flat() {
+        let history = dated_history(1, &[0.1, 0.1]);
+        let dates: Vec<chrono::NaiveDate> = (1..=3)
+            .map(|day| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap())
+            .collect();
+
+        let realized = get_realized_return(&history, &dates);
+
+        assert!((realized - 21.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn get_rebalances_picks_from_the_trailing_window_only() {
+        // UP rises then falls, DOWN falls then rises, so each leads the other from one rebalance to the next
+        let up = [0.01, 0.01, -0.01, -0.01, 0.01, 0.01];
+        let down = [-0.01, -0.01, 0.01, 0.01, -0.01, -0.01];
+        let histories = vec![
+            ("DOWN".to_string(), dated_history(1, &down)),
+            ("UP".to_string(), dated_history(1, &up)),
+            // starts late so it has no full window at the first rebalance
+            (
+                "LATE".to_string(),
+                dated_history(3, &[0.02, 0.02, 0.02, 0.02]),
+            ),
+        ];
+        let settings = SimulationSettings {
+            periods: 2,
+            number_of_simulations: 1_000,
+            seed: 7,
+            ..test_settings()
+        };
+        let backtest = BacktestSettings {
+            window: 2,
+            step: 2,
+            top_x: 1,
+            strategy: Strategy::MostCommon,
+        };
+
+        let rebalances = get_rebalances(&histories, &settings, &backtest);
+
+        assert_eq!(rebalances.len(), 2);
+        assert_eq!(rebalances[0].date, "2024-01-02");
+        assert_eq!(rebalances[0].picks, vec!["UP"]);
+        assert_eq!(rebalances[0].forecasts.len(), 2);
+        assert!(rebalances[0].portfolio < rebalances[0].benchmark);
+        assert_eq!(rebalances[1].date, "2024-01-04");
+        assert_eq!(rebalances[1].picks, vec!["LATE"]);
+        assert_eq!(rebalances[1].forecasts.len(), 3);
+        assert!(rebalances[1].portfolio > rebalances[1].benchmark);
+        let late = rebalances[1]
+            .forecasts
+            .iter()
+            .find(|forecast| forecast.symbol == "LATE")
+            .unwrap();
+        assert!((late.realized - 4.04).abs() < 1e-9);
+        assert_eq!(late.quantiles.len(), 7);
+        assert!((late.pit - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn get_summary_averages_and_compounds_the_rebalances() {
+        let rebalances = vec![
+            rebalance("2024-01-02", 10.0, 5.0),
+            rebalance("2024-01-04", -10.0, 0.0),
+        ];
+
+        let summary = get_summary(&rebalances).unwrap();
+
+        assert_eq!(summary.periods, 2);
+        assert!((summary.mean_portfolio - 0.0).abs() < 1e-9);
+        assert!((summary.mean_excess + 2.5).abs() < 1e-9);
+        assert!((summary.hit_rate - 0.5).abs() < 1e-9);
+        assert!((summary.portfolio_growth + 1.0).abs() < 1e-9);
+        assert!((summary.benchmark_growth - 5.0).abs() < 1e-9);
+        assert!(get_summary(&[]).is_none());
+    }
+
+    #[test]
+    fn get_backtest_csv_one_row_per_rebalance_and_summary_footer() {
+        let rebalances = vec![rebalance("2024-01-02", 10.0, 5.0)];
+
+        let csv = get_backtest_csv(&rebalances);
+
+        assert_eq!(
+            csv,
+            "date,picks,top x,benchmark,excess,symbols\n\
+             2024-01-02,AAPL,10.00,5.00,5.00,0\n\
+             mean,,10.00,5.00,5.00,\n\
+             compounded,,10.00,5.00,5.00,\n\
+             beat the benchmark,,,,100%,\n"
+        );
+    }
+
+    #[test]
+    fn strategy_parses_and_displays_its_name() {
+        for name in ["most-common", "highest-low", "total-span", "weighted-span"] {
+            let strategy: Strategy = name.parse().unwrap();
+            assert_eq!(strategy.to_string(), name);
+        }
+        assert!("best".parse::<Strategy>().is_err());
+    }
+
+    fn forecast(realized: f64, pit: f64) -> Forecast {
+        Forecast {
+            symbol: "AAPL".to_string(),
+            percentiles: Percentiles {
+                _25th: -2,
+                _50th: 0,
+                _75th: 2,
+            },
+            quantiles: vec![
+                (5, -6),
+                (10, -4),
+                (25, -2),
+                (50, 0),
+                (75, 2),
+                (90, 4),
+                (95, 6),
+            ],
+            realized,
+            pit,
+        }
+    }
+
+    #[test]
+    fn get_pit_counts_half_of_the_outcomes_at_the_realized_value() {
+        let data = BTreeMap::from([(-1, 2), (0, 4), (1, 2), (2, 2)]);
+
+        assert!((get_pit(&data, 0.2) - 0.4).abs() < 1e-9);
+        assert!((get_pit(&data, -5.0) - 0.0).abs() < 1e-9);
+        assert!((get_pit(&data, 5.0) - 1.0).abs() < 1e-9);
+        assert!((get_pit(&BTreeMap::new(), 5.0) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn get_coverage_counts_outcomes_inside_the_band() {
+        let forecasts = vec![forecast(-3.0, 0.2), forecast(1.0, 0.6), forecast(2.0, 0.75)];
+
+        assert!((get_coverage(&forecasts, 25, 75) - 2.0 / 3.0).abs() < 1e-9);
+        assert!((get_coverage(&forecasts, 10, 90) - 1.0).abs() < 1e-9);
+        assert!((get_coverage(&forecasts, 1, 99) - 0.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn get_pinball_loss_weights_misses_by_the_percentile() {
+        let above = vec![forecast(10.0, 1.0)];
+        let at_median = vec![forecast(0.0, 0.5)];
+
+        // realized above every percentile, each costs its level times the miss
+        let expected = [
+            (5, -6),
+            (10, -4),
+            (25, -2),
+            (50, 0),
+            (75, 2),
+            (90, 4),
+            (95, 6),
+        ]
+        .iter()
+        .map(|(percent, outcome)| *percent as f64 / 100.0 * (10.0 - *outcome as f64))
+        .sum::<f64>()
+            / 7.0;
+        assert!((get_pinball_loss(&above) - expected).abs() < 1e-9);
+        assert!(get_pinball_loss(&at_median) < get_pinball_loss(&above));
+        assert!((get_pinball_loss(&[]) - 0.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn get_pit_histogram_puts_one_in_the_last_slice() {
+        let forecasts = vec![forecast(0.0, 0.0), forecast(0.0, 0.55), forecast(0.0, 1.0)];
+
+        assert_eq!(get_pit_histogram(&forecasts, 4), vec![1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn get_calibration_csv_one_section_per_grouping() {
+        let forecasts = vec![forecast(1.0, 0.6), forecast(5.0, 0.93)];
+        let by_run = vec![get_calibration("weekly", &forecasts, 2)];
+        let by_sampler = vec![get_calibration("independent", &forecasts, 2)];
+
+        let csv = get_calibration_csv(&by_run, &by_sampler);
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "run,forecasts,25th to 75th (50%),10th to 90th (80%),5th to 95th (90%),pinball loss,pit 0.00,pit 0.50"
+        );
+        assert!(lines[1].starts_with("weekly,2,50.0%,50.0%,100.0%,"));
+        assert!(lines[1].ends_with(",0,2"));
+        assert!(lines[2].starts_with("sampler,"));
+        assert!(lines[3].starts_with("independent,2,"));
+    }
+
+    #[test]
+    fn get_pit_svg_one_bar_per_slice_and_a_calibrated_line() {
+        let svg = get_pit_svg(&[1, 3]);
+
+        assert_eq!(svg.matches("<rect class=\"bar\"").count(), 2);
+        assert!(svg.contains("class=\"uniform\""));
+        assert!(svg.contains("4 forecasts"));
+        assert_eq!(get_pit_svg(&[0, 0]), "");
+    }
+
+    fn stored_run(timestamp: &str, medians: &[(&str, i32)]) -> RunResults {
+        let settings = SimulationSettings {
+            periods: 10,
+            number_of_simulations: 5_000,
+            seed: 1,
+            ..test_settings()
+        };
+        let predictions: Vec<Prediction> = medians
+            .iter()
+            .map(|(symbol, median)| Prediction {
+                symbol: symbol.to_string(),
+                percentiles: Percentiles {
+                    _25th: median - 1,
+                    _50th: *median,
+                    _75th: median + 1,
+                },
+                simulations: 5_000,
+                observations: 23,
+                ..Prediction::default()
+            })
+            .collect();
+        RunResults {
+            schema_version: SCHEMA_VERSION,
+            metadata: RunMetadata {
+                timestamp: timestamp.to_string(),
+                ..RunMetadata::new(&PathBuf::from("test_data"), &settings, 1)
+            },
+            top_predictions: get_highest_x(1, &predictions, Box::new(MostCommonResult {})),
+            predictions,
+            summary: RunSummary::default(),
+        }
+    }
+
+    #[test]
+    fn get_run_id_is_the_time_the_run_was_written() {
+        assert_eq!(
+            get_run_id("2025-01-06T17:30:05.123+00:00").unwrap(),
+            "20250106-173005"
+        );
+        assert!(get_run_id("yesterday").is_err());
+    }
+
+    #[test]
+    fn save_run_numbers_runs_in_the_same_second_and_loads_them_back() {
+        let store =
+            std::env::temp_dir().join(format!("stock_simulator_history_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&store);
+        let run = stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 3)]);
+
+        let first = save_run(&store, &run).unwrap();
+        let second = save_run(&store, &run).unwrap();
+        let runs = load_runs(&store).unwrap();
+        let loaded = load_run(&store, &second).unwrap();
+        let missing = load_run(&store, "20250101-000000");
+        std::fs::remove_dir_all(&store).unwrap();
+
+        assert_eq!(first, "20250106-173005");
+        assert_eq!(second, "20250106-173005-2");
+        assert_eq!(runs.len(), 2);
+        assert_eq!(loaded.predictions[0].symbol, "AAPL");
+        assert_eq!(loaded.metadata, run.metadata);
+        assert!(
+            matches!(missing, Err(SimulationError::Io(message)) if message.starts_with("run 20250101-000000:"))
+        );
+    }
+
+    #[test]
+    fn get_run_entries_filters_by_date() {
+        let runs = vec![
+            (
+                "20250106-173005".to_string(),
+                stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 3), ("AAL", 5)]),
+            ),
+            (
+                "20250113-173005".to_string(),
+                stored_run("2025-01-13T17:30:05+00:00", &[("AAPL", 3)]),
+            ),
+        ];
+
+        let all = get_run_entries(&runs, None);
+        let one = get_run_entries(&runs, Some("2025-01-13"));
+
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].symbols, 2);
+        assert_eq!(all[0].top, vec!["AAL"]);
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].id, "20250113-173005");
+        assert!(get_run_list(&one).contains("20250113-173005  2025-01-13T17:30:05+00:00"));
+    }
+
+    #[test]
+    fn get_symbol_forecasts_skips_runs_without_the_symbol() {
+        let runs = vec![
+            (
+                "20250106-173005".to_string(),
+                stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 3), ("AAL", 5)]),
+            ),
+            (
+                "20250113-173005".to_string(),
+                stored_run("2025-01-13T17:30:05+00:00", &[("AAL", 5)]),
+            ),
+            (
+                "20250120-173005".to_string(),
+                stored_run("2025-01-20T17:30:05+00:00", &[("AAPL", 7), ("AAL", 5)]),
+            ),
+        ];
+
+        let forecasts = get_symbol_forecasts(&runs, "AAPL");
+
+        assert_eq!(forecasts.len(), 2);
+        assert_eq!(forecasts[0].rank, 2);
+        assert_eq!(forecasts[1].id, "20250120-173005");
+        assert_eq!(forecasts[1].percentiles._50th, 7);
+        assert_eq!(forecasts[1].rank, 1);
+    }
+
+    #[test]
+    fn text_table_aligns_columns_under_the_header() {
+        let rows = vec![
+            vec!["AAPL".to_string(), "3".to_string()],
+            vec!["AACG".to_string(), "-12".to_string()],
+        ];
+
+        let table = text_table(&["symbol", "50th"], &rows);
+
+        assert_eq!(
+            table,
+            "symbol  50th\n------  ----\nAAPL       3\nAACG     -12\n"
+        );
+    }
+
+    #[test]
+    fn get_changes_finds_entering_leaving_and_rank_moves() {
+        let before = stored_run(
+            "2025-01-06T17:30:05+00:00",
+            &[("AAPL", 7), ("AAL", 5), ("AACG", 1)],
+        );
+        let after = stored_run(
+            "2025-01-13T17:30:05+00:00",
+            &[("AAPL", 4), ("AAL", 6), ("AAON", 2)],
+        );
+
+        let changes = get_changes(&before, &after);
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].symbol, "AAL");
+        assert_eq!(changes[0].movement, Movement::Entered);
+        assert_eq!(changes[0].before_rank, Some(2));
+        assert_eq!(changes[0].after_rank, Some(1));
+        assert_eq!(changes[0].rank_move(), Some(1));
+        assert_eq!(changes[0].delta.as_ref().unwrap().most_common, 1);
+        assert_eq!(changes[1].symbol, "AAPL");
+        assert_eq!(changes[1].movement, Movement::Left);
+        assert_eq!(changes[1].rank_move(), Some(-1));
+        assert_eq!(changes[1].delta.as_ref().unwrap().most_common, -3);
+    }
+
+    #[test]
+    fn get_changes_without_the_symbol_in_the_earlier_run_has_no_delta() {
+        let before = stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 7)]);
+        let after = stored_run("2025-01-13T17:30:05+00:00", &[("AAPL", 4), ("AAON", 9)]);
+
+        let changes = get_changes(&before, &after);
+        let csv = get_comparison_csv(&changes);
+
+        assert_eq!(changes[0].symbol, "AAON");
+        assert_eq!(changes[0].before_rank, None);
+        assert!(changes[0].delta.is_none());
+        assert_eq!(
+            csv,
+            "symbol,top x,before rank,after rank,rank move,most common change,bottom 25th change,25th to 75th span change,weighted span change\n\
+             AAON,entered,,1,,,,,\n\
+             AAPL,left,1,2,-1,-3,-3,0,0\n"
+        );
+    }
+
+    #[test]
+    fn get_comparison_html_colors_the_deltas() {
+        let before = stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 7), ("AAL", 5)]);
+        let after = stored_run("2025-01-13T17:30:05+00:00", &[("AAPL", 4), ("AAL", 6)]);
+
+        let changes = get_changes(&before, &after);
+        let html = get_comparison_html(&before, &after, &changes, &Branding::default());
+
+        assert!(html.contains("Entered the top 1: AAL. Left the top 1: AAPL."));
+        assert!(html.contains("<tr class=\"entered\"><td>AAL</td>"));
+        // an improvement is green and a setback red, however few symbols there are
+        assert!(html.contains("<td class=\"green\">+1</td>"));
+        assert!(html.contains("<td class=\"red\">-3</td>"));
+        // the span and weighted span of AAPL did not change
+        assert!(html.contains("<td class=\"yellow\">0</td>"));
+    }
+
+    #[test]
+    fn get_comparison_rows_identical_runs_have_no_green() {
+        let run = stored_run(
+            "2025-01-06T17:30:05+00:00",
+            &[("AAPL", 7), ("AAL", 5), ("MSFT", -2)],
+        );
+
+        let changes = get_changes(&run, &run);
+        let (_, rows) = get_comparison_rows(&changes);
+
+        let ratings: Vec<Rating> = rows
+            .iter()
+            .flatten()
+            .filter_map(|(_, rating)| *rating)
+            .collect();
+        assert!(!ratings.is_empty());
+        assert!(ratings.iter().all(|rating| *rating == Rating::Yellow));
+    }
+
+    #[test]
+    fn get_comparison_html_header_and_disclaimer() {
+        let before = stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 7)]);
+        let after = stored_run("2025-01-13T17:30:05+00:00", &[("AAPL", 4)]);
+        let branding = Branding {
+            header: Some("Acme & Co".to_string()),
+            disclaimer: Some("Not advice".to_string()),
+        };
+
+        let changes = get_changes(&before, &after);
+        let html = get_comparison_html(&before, &after, &changes, &branding);
+        let plain = get_comparison_html(&before, &after, &changes, &Branding::default());
+
+        assert!(html.contains("<div class=\"company-header\">Acme &amp; Co</div>"));
+        assert!(html.contains("<footer class=\"disclaimer\">Not advice</footer>"));
+        assert!(!plain.contains("class=\"company-header\""));
+        assert!(!plain.contains("class=\"disclaimer\""));
+    }
+
+    #[test]
+    fn write_outputs_fails_when_the_template_does_not_render() {
+        let dir =
+            std::env::temp_dir().join(format!("stock_simulator_template_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let results = stored_run("2025-01-06T17:30:05+00:00", &[("AAPL", 7)]);
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Html],
+            template: Some("{{#cards}}x".to_string()),
+            ..OutputSettings::default()
+        };
+
+        let actual = write_outputs(&dir.join("predictions.html"), &output_settings, &results);
+        let written = dir.join("predictions.html").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(actual, Err(SimulationError::Parse(_))));
+        assert!(!written);
+    }
+
+    const CONFIG: &str = r#"
+source-dir = "data"
+log-file = "simulator.log"
+days = 30
+simulations = 100000
+top-x = 10
+sampler = "stratified"
+
+[profiles.weekly]
+days = 5
+output-file = "weekly.html"
+format = ["html", "json"]
+strategy = "highest-low"
+
+[profiles.quarterly]
+days = 63
+quantiles = "sketch:400"
+"#;
+
+    #[test]
+    fn get_config_profile_overrides_the_top_of_the_file() {
+        let weekly = get_config(CONFIG, Some("weekly")).unwrap();
+        let quarterly = get_config(CONFIG, Some("quarterly")).unwrap();
+        let base = get_config(CONFIG, None).unwrap();
+
+        assert_eq!(weekly.days, Some(5));
+        assert_eq!(weekly.number_of_simulations, Some(100_000));
+        assert_eq!(weekly.sampler, Some(Sampler::Stratified));
+        assert_eq!(
+            weekly.format,
+            Some(vec![OutputFormat::Html, OutputFormat::Json])
+        );
+        assert_eq!(weekly.output_file, Some(PathBuf::from("weekly.html")));
+        assert_eq!(quarterly.quantiles, Some(QuantileMethod::Sketch(400)));
+        assert_eq!(base.days, Some(30));
+        assert_eq!(base.output_file, None);
+        assert_eq!(base.missing(), vec!["output-file"]);
+    }
+
+    #[test]
+    fn get_config_rejects_unknown_profiles_keys_and_values() {
+        assert_eq!(
+            get_config(CONFIG, Some("monthly")).unwrap_err(),
+            "unknown profile monthly, the file has quarterly, weekly"
+        );
+        assert!(get_config("dayz = 5", None)
+            .unwrap_err()
+            .contains("unknown field"));
+        assert!(get_config("sampler = \"random\"", None)
+            .unwrap_err()
+            .contains("unknown sampler random"));
+        assert!(get_config("[profiles.weekly]\nseeds = 1", Some("weekly"))
+            .unwrap_err()
+            .starts_with("profile weekly:"));
+    }
+
+    #[test]
+    fn simulate_config_command_line_overrides_the_file() {
+        let command_line = SimulateConfig {
+            days: Some(10),
+            seed: Some(42),
+            ..SimulateConfig::default()
+        };
+        let file = get_config(CONFIG, Some("weekly")).unwrap();
+
+        let resolved = command_line.or(file);
+
+        assert_eq!(resolved.days, Some(10));
+        assert_eq!(resolved.seed, Some(42));
+        assert_eq!(resolved.top_x, Some(10));
+        assert!(resolved.missing().is_empty());
+        assert_eq!(get_config(&resolved.to_toml(), None).unwrap(), resolved);
+        assert!(resolved.to_toml().contains("sampler = \"stratified\""));
+        assert_eq!(resolved.strategy, Some(Strategy::HighestLow));
+        assert!(resolved.to_toml().contains("strategy = \"highest-low\""));
+    }
+
+    #[test]
+    fn get_returns_names_the_item_it_cannot_read() {
+        assert_eq!(get_returns("0.01, -0.02,\n").unwrap(), vec![0.01, -0.02]);
+        assert_eq!(
+            get_returns("0.01,abc,0.02").unwrap_err().to_string(),
+            "item 2: invalid float literal"
+        );
+    }
+
+    #[test]
+    fn get_data_problems_flags_short_impossible_and_suspicious_returns() {
+        let fine: Vec<f64> = (0..20).map(|day| day as f64 / 1000.0).collect();
+        assert!(get_data_problems(&fine, 20).is_empty());
+
+        let problems = get_data_problems(&[0.01, -1.0, 2.5, f64::NAN], 20);
+        assert_eq!(problems.len(), 4);
+        assert_eq!(problems[0], "only 4 observations, at least 20 expected");
+        assert!(problems[1].starts_with("item 2:"));
+        assert!(problems[2].contains("percentages instead of fractions"));
+        assert!(problems[3].contains("not a number"));
+
+        assert_eq!(
+            get_data_problems(&[0.01; 5], 1),
+            vec!["every return is the same"]
+        );
+    }
+
+    #[test]
+    fn get_validation_text_lists_problems_and_counts_files() {
+        let checks = vec![
+            check_file("AAA", Ok("0.01,0.02,-0.01".to_string()), 2),
+            check_file("BBB", Ok("0.01,x".to_string()), 2),
+            check_file(
+                "CCC",
+                Err(std::io::Error::other("stream did not contain valid UTF-8")),
+                2,
+            ),
+        ];
+
+        let text = get_validation_text(&checks);
+
+        assert!(checks[0].problems.is_empty());
+        assert_eq!(checks[0].observations, 3);
+        assert!(!text.contains("AAA"));
+        assert!(text.contains("item 2: invalid float literal"));
+        assert!(text.contains("CCC"));
+        assert!(text.ends_with("3 files checked, 2 with problems\n"));
+    }
+
+    #[test]
+    fn get_inspection_text_shows_stats_and_problems() {
+        let text = get_inspection_text("AAA", &[0.01, 0.03, -0.02], 5);
+
+        assert!(text.starts_with("AAA\n"));
+        assert!(text.contains("observations"));
+        assert!(text.contains("0.67%"));
+        assert!(text.contains("66.67%"));
+        assert!(text.contains("- only 3 observations, at least 5 expected"));
+    }
+
+    #[test]
+    fn simulation_errors_have_distinct_non_zero_exit_codes() {
+        let errors = [
+            SimulationError::Io(String::new()),
+            SimulationError::Parse(String::new()),
+            SimulationError::Validation(String::new()),
+            SimulationError::Config(String::new()),
+            SimulationError::NoInput(String::new()),
+            SimulationError::PartialFailure {
+                failed: 1,
+                skipped: 0,
+                total: 2,
+            },
+            SimulationError::Aborted(String::new()),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(|e| e.exit_code()).collect();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0));
+        assert_eq!(
+            errors[5].to_string(),
+            "partial failure: 1 of 2 symbols failed"
+        );
+        assert_eq!(
+            SimulationError::PartialFailure {
+                failed: 0,
+                skipped: 2,
+                total: 5,
+            }
+            .to_string(),
+            "partial failure: 0 of 5 symbols failed, 2 skipped"
+        );
+    }
+
+    #[test]
+    fn get_simulation_data_tells_unreadable_from_unparsable() {
+        let dir = std::env::temp_dir().join(format!("stock_simulator_data_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("BAD"), "0.01,x").unwrap();
+
+        let missing = get_simulation_data(&dir.join("MISSING"));
+        let bad = get_simulation_data(&dir.join("BAD"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(missing, Err(SimulationError::Io(_))));
+        match bad {
+            Err(SimulationError::Parse(message)) => {
+                assert!(message.ends_with("BAD: item 2: invalid float literal"))
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn run_simulator_reports_no_input_and_partial_failure() {
+        let dir = std::env::temp_dir().join(format!("stock_simulator_run_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let input = dir.join("input");
+        std::fs::create_dir_all(&input).unwrap();
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 100,
+            seed: 42,
+            ..test_settings()
+        };
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Json],
+            options: Some("days = 5; simulations = 100".to_string()),
+            ..OutputSettings::default()
+        };
+        let output = dir.join("predictions.json");
+
+        let empty = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &output,
+            &output_settings,
+            None,
+        );
+        std::fs::write(input.join("GOOD"), "0.01,-0.02,0.03").unwrap();
+        std::fs::write(input.join("BAD"), "0.01,x").unwrap();
+        let partial = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &output,
+            &output_settings,
+            None,
+        );
+        let written = read_results(&output);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(empty, Err(SimulationError::NoInput(_))));
+        assert_eq!(
+            partial,
+            Err(SimulationError::PartialFailure {
+                failed: 1,
+                skipped: 0,
+                total: 2
+            })
+        );
+        assert_eq!(
+            written.unwrap().metadata.options.as_deref(),
+            Some("days = 5; simulations = 100")
+        );
+    }
+
+    #[test]
+    fn run_backtest_rejects_a_top_x_of_zero() {
+        let dir =
+            std::env::temp_dir().join(format!("stock_simulator_top_zero_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("GOOD"), "2025-01-06,0.01\n2025-01-07,-0.02\n").unwrap();
+        let backtest = BacktestSettings {
+            window: 1,
+            step: 1,
+            top_x: 0,
+            strategy: Strategy::MostCommon,
+        };
+        let output = dir.join("backtest.json");
+
+        let actual = run_backtest(
+            &dir,
+            &test_settings(),
+            &backtest,
+            &output,
+            &Branding::default(),
+        );
+        let written = output.exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(actual, Err(SimulationError::Validation(_))));
+        assert!(!written);
+    }
+
+    #[test]
+    fn run_simulator_html_without_extension_keeps_index_and_details_apart() {
+        let dir =
+            std::env::temp_dir().join(format!("stock_simulator_details_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let input = dir.join("input");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("GOOD"), "0.01,-0.02,0.03").unwrap();
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 100,
+            ..test_settings()
+        };
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Html],
+            ..OutputSettings::default()
+        };
+        let output = dir.join("predictions");
+
+        let actual = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &output,
+            &output_settings,
+            None,
+        );
+        let index = std::fs::read_to_string(&output).unwrap_or_default();
+        let detail = std::fs::read_to_string(dir.join("predictions_files").join("GOOD.html"))
+            .unwrap_or_default();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(actual, Ok(()));
+        assert!(index.contains("href=\"predictions_files/GOOD.html\""));
+        assert!(detail.contains("<a class=\"back\" href=\"../predictions\">"));
+    }
+
+    #[test]
+    fn run_simulator_reports_read_only_files_as_skipped_not_failed() {
+        let dir = std::env::temp_dir().join(format!("stock_simulator_skip_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let input = dir.join("input");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("GOOD"), "0.01,-0.02,0.03").unwrap();
+        std::fs::write(input.join("LOCKED"), "0.01,-0.02,0.03").unwrap();
+        let mut permissions = std::fs::metadata(input.join("LOCKED"))
+            .unwrap()
+            .permissions();
+        permissions.set_readonly(true);
+        std::fs::set_permissions(input.join("LOCKED"), permissions).unwrap();
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 100,
+            ..test_settings()
+        };
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Json],
+            ..OutputSettings::default()
+        };
+
+        let actual = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &dir.join("predictions.json"),
+            &output_settings,
+            None,
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            actual,
+            Err(SimulationError::PartialFailure {
+                failed: 0,
+                skipped: 1,
+                total: 2
+            })
+        );
+    }
+
+    #[test]
+    fn run_simulator_fails_when_the_output_cannot_be_written() {
+        let dir =
+            std::env::temp_dir().join(format!("stock_simulator_write_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let input = dir.join("input");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("GOOD"), "0.01,-0.02,0.03").unwrap();
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 100,
+            ..test_settings()
+        };
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Json],
+            ..OutputSettings::default()
+        };
+        // the output's directory does not exist, so the file cannot be created
+        let output = dir.join("missing").join("predictions.json");
+
+        let actual = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &output,
+            &output_settings,
+            None,
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(
+            matches!(actual, Err(SimulationError::Io(message)) if message.contains("predictions.json"))
+        );
+    }
+
+    #[test]
+    fn run_simulator_aborts_after_max_failures_and_marks_the_run_failed() {
+        let dir =
+            std::env::temp_dir().join(format!("stock_simulator_abort_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let input = dir.join("input");
+        std::fs::create_dir_all(&input).unwrap();
+        for symbol in ["A", "B", "C", "D"] {
+            std::fs::write(input.join(symbol), "x").unwrap();
+        }
+        let settings = SimulationSettings {
+            periods: 5,
+            number_of_simulations: 100,
+            seed: 42,
+            ..test_settings()
+        };
+        let output_settings = OutputSettings {
+            formats: vec![OutputFormat::Json],
+            ..OutputSettings::default()
+        };
+        let output = dir.join("predictions.json");
+
+        let result = run_simulator(
+            &input,
+            &settings,
+            3,
+            Strategy::MostCommon,
+            &output,
+            &output_settings,
+            Some(1),
+        );
+        let written = read_results(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            result,
+            Err(SimulationError::Aborted(
+                "2 symbols failed, more than the 1 allowed".to_string()
+            ))
+        );
+        let summary = written.summary;
+        assert_eq!(summary.status, RunStatus::Failed);
+        assert_eq!(summary.failed.len(), 2);
+        assert!(summary.failed[0]
+            .reason
+            .ends_with("item 1: invalid float literal"));
+        assert_eq!(summary.skipped.len(), 2);
+        assert_eq!(
+            summary.skipped[0].reason,
+            "not reached before the run was aborted"
+        );
+    }
+
+    #[test]
+    fn get_summary_html_lists_counts_and_reasons() {
+        let mut summary = RunSummary {
+            processed: vec!["AAPL".to_string()],
+            failed: vec![SymbolIssue {
+                symbol: "BAD".to_string(),
+                reason: "item 2: <invalid>".to_string(),
+            }],
+            ..RunSummary::default()
+        };
+        summary.status = summary.get_status();
+
+        let partial = get_summary_html(&summary);
+        summary.aborted = Some("too many failures".to_string());
+        summary.status = summary.get_status();
+        let failed = get_summary_html(&summary);
+
+        assert!(partial.contains("Run partial: 1 processed, 0 skipped, 1 failed."));
+        assert!(partial.contains("<li><strong>BAD</strong>: item 2: &lt;invalid&gt;</li>"));
+        assert!(!partial.contains("Skipped"));
+        assert!(failed.contains("run-summary failed"));
+        assert!(failed.contains("Aborted: too many failures"));
+    }
+
+    fn partial_summary() -> RunSummary {
+        let mut summary = RunSummary {
+            processed: vec!["AACG".to_string(), "AAPL".to_string()],
+            skipped: vec![SymbolIssue {
+                symbol: "ZZZ".to_string(),
+                reason: "run aborted before it was read".to_string(),
+            }],
+            failed: vec![SymbolIssue {
+                symbol: "BAD".to_string(),
+                reason: "item 2: not a number".to_string(),
+            }],
+            aborted: Some("too many failures".to_string()),
+            ..RunSummary::default()
+        };
+        summary.status = summary.get_status();
+        summary
+    }
+
+    #[test]
+    fn get_markdown_lists_run_summary_before_disclaimer() {
+        let metadata = RunMetadata {
+            disclaimer: Some("Not investment advice.".to_string()),
+            ..test_metadata(2)
+        };
+
+        let actual = get_markdown(&table_predictions(), &metadata, &partial_summary());
+
+        assert!(actual.ends_with(
+            "\n## Run Summary\n\nRun failed: 2 processed, 1 skipped, 1 failed.\n\n\
+            Aborted: too many failures\n\n\
+            Skipped:\n\n- **ZZZ**: run aborted before it was read\n\n\
+            Failed:\n\n- **BAD**: item 2: not a number\n\n\
+            ---\n\n*Not investment advice.*\n"
+        ));
+    }
+
+    #[test]
+    fn get_table_lists_run_summary_before_disclaimer() {
+        let metadata = RunMetadata {
+            disclaimer: Some("Not investment advice.".to_string()),
+            ..test_metadata(2)
+        };
+
+        let actual = get_table(&table_predictions(), &metadata, &partial_summary(), false);
+
+        assert!(actual.ends_with(
+            "\nRun failed: 2 processed, 1 skipped, 1 failed.\n\
+            Aborted: too many failures\n\
+            Skipped ZZZ: run aborted before it was read\n\
+            Failed BAD: item 2: not a number\n\n\
+            Not investment advice.\n"
+        ));
+    }
+
+    #[test]
+    fn log_filter_parses_a_level_with_module_overrides() {
+        let filter: LogFilter = "warn, monte_carlo=debug,backtest=error".parse().unwrap();
+
+        assert_eq!(filter.level, Level::Warn);
+        assert_eq!(filter.level_for("monte_carlo"), Level::Debug);
+        assert_eq!(filter.level_for("stock_simulation"), Level::Warn);
+        assert!(filter.enabled(Level::Debug, "monte_carlo"));
+        assert!(!filter.enabled(Level::Info, "stock_simulation"));
+        assert!(!filter.enabled(Level::Warn, "backtest"));
+        assert_eq!(filter.to_string(), "warn,monte_carlo=debug,backtest=error");
+        assert_eq!(
+            "".parse::<LogFilter>().unwrap(),
+            LogFilter::new(Level::Info)
+        );
+        assert!("loud"
+            .parse::<LogFilter>()
+            .unwrap_err()
+            .contains("unknown log level loud"));
+        assert!("=debug".parse::<LogFilter>().is_err());
+    }
+
+    #[test]
+    fn format_record_writes_text_and_json_lines() {
+        let timing = Record {
+            level: Level::Info,
+            module: "stock_simulation",
+            symbol: "AAPL",
+            message: "simulate end",
+            phase: Some("simulate"),
+            duration: Some(std::time::Duration::from_micros(12_500)),
+        };
+        let run = Record {
+            level: Level::Warn,
+            module: "history",
+            symbol: NO_SYMBOL,
+            message: "run 1: \"bad\"",
+            phase: None,
+            duration: None,
+        };
+        let timestamp = "2025-01-06T17:30:05.000000Z";
+
+        assert_eq!(
+            format_record(&timing, LogFormat::Text, timestamp),
+            "2025-01-06T17:30:05.000000Z INFO  stock_simulation [AAPL]: simulate end phase=simulate duration_ms=12.500"
+        );
+        assert_eq!(
+            format_record(&run, LogFormat::Text, timestamp),
+            "2025-01-06T17:30:05.000000Z WARN  history: run 1: \"bad\""
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_record(&timing, LogFormat::Json, timestamp)).unwrap();
+        assert_eq!(json["level"], "info");
+        assert_eq!(json["symbol"], "AAPL");
+        assert_eq!(json["phase"], "simulate");
+        assert_eq!(json["duration_ms"], 12.5);
+        let json: serde_json::Value =
+            serde_json::from_str(&format_record(&run, LogFormat::Json, timestamp)).unwrap();
+        assert!(json["symbol"].is_null());
+        assert_eq!(json["message"], "run 1: \"bad\"");
+        assert!(json.get("phase").is_none());
+    }
+
+    #[test]
+    fn logger_shares_one_buffered_file_across_threads() {
+        let path =
+            std::env::temp_dir().join(format!("stock_simulator_log_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let marker = "logger_shares_one_buffered_file_across_threads";
+        logger::init(Some(&path), LogFilter::new(Level::Info), LogFormat::Json).unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                scope.spawn(move || {
+                    for line in 0..50 {
+                        logger::write(&Record {
+                            level: Level::Info,
+                            module: "tests",
+                            symbol: NO_SYMBOL,
+                            message: &format!("{marker} {thread} {line}"),
+                            phase: None,
+                            duration: None,
+                        });
+                    }
+                });
+            }
+        });
+        logger::flush();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        logger::init(None, LogFilter::default(), LogFormat::Text).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|line: &serde_json::Value| {
+                line["message"]
+                    .as_str()
+                    .is_some_and(|m| m.starts_with(marker))
+            })
+            .collect();
+        assert_eq!(lines.len(), 200);
+    }
+
+    #[test]
+    fn get_progress_text_adds_throughput_and_eta_once_a_symbol_is_done() {
+        assert_eq!(
+            get_progress_text(0, 10, Duration::from_secs(3)),
+            "0/10 symbols"
+        );
+        assert_eq!(
+            get_progress_text(5, 10, Duration::from_secs(10)),
+            "5/10 symbols, 0.5 symbols/s, ETA 10s"
+        );
+        assert_eq!(
+            get_progress_text(12, 10, Duration::from_secs(4)),
+            "12/12 symbols, 3.0 symbols/s, ETA 0s"
+        );
+    }
+
+    #[test]
+    fn format_duration_uses_the_two_largest_units() {
+        assert_eq!(format_duration(Duration::from_millis(45_900)), "45s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3720)), "1h 02m");
+    }
+
+    #[test]
+    fn phase_timings_add_up_and_show_their_share() {
+        let mut timings = PhaseTimings {
+            load: Duration::from_millis(500),
+            simulate: Duration::from_millis(2500),
+            ..PhaseTimings::default()
+        };
+        timings.add(PhaseTimings {
+            rank: Duration::from_millis(250),
+            render: Duration::from_millis(500),
+            save: Duration::from_millis(1250),
+            ..PhaseTimings::default()
+        });
+        assert_eq!(timings.total(), Duration::from_secs(5));
+        let names: Vec<&str> = timings.phases().iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(names, ["load", "simulate", "rank", "render", "save"]);
+
+        let text = get_timing_text(&timings);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[0].contains("phase") && lines[0].contains("share"));
+        assert!(text.contains("simulate") && text.contains("2.500") && text.contains("50.0%"));
+        let total = lines.iter().find(|line| line.starts_with("total")).unwrap();
+        assert!(total.contains("5.000") && total.contains("100.0%"));
+        assert!(get_timing_text(&PhaseTimings::default()).contains("0.0%"));
+    }
 }
@@ -1,29 +1,139 @@
 pub mod util {
-    use std::{fs::OpenOptions, io::Write};
+    use std::{fmt::Display, panic::Location, path::Path, time::Duration};
 
-    use chrono::Utc;
-
-    use crate::LOG_FILE_PATH;
+    use crate::logging::logger::{self, Level, Record};
 
     /// convenience function to log trouble without interrupting things
-    pub fn log<T: std::fmt::Debug>(symbol: &str, info: T) {
-        let timestamp = Utc::now();
-        let message = format!("TS: {}: {}: {:?}\n", timestamp, symbol, info);
-        LOG_FILE_PATH.with(|path| {
-            let opt = path.borrow().clone();
-            match opt {
-                Some(log_file_path) => {
-                    let mut file = OpenOptions::new()
-                        .append(true)
-                        .create(true)
-                        .open(log_file_path)
-                        .unwrap();
-                    file.write(message.as_bytes()).unwrap();
-                }
-                None => {
-                    println!("{}", message);
+    #[track_caller]
+    pub fn log<T: Display>(symbol: &str, info: T) {
+        log_at(Level::Info, symbol, info);
+    }
+
+    /// something was skipped or failed but the run goes on
+    #[track_caller]
+    pub fn log_warning<T: Display>(symbol: &str, info: T) {
+        log_at(Level::Warn, symbol, info);
+    }
+
+    /// the run cannot go on
+    #[track_caller]
+    pub fn log_error<T: Display>(symbol: &str, info: T) {
+        log_at(Level::Error, symbol, info);
+    }
+
+    /// detail that is only worth seeing when looking into a problem
+    #[track_caller]
+    pub fn log_debug<T: Display>(symbol: &str, info: T) {
+        log_at(Level::Debug, symbol, info);
+    }
+
+    /// how long a phase of the run took, for one symbol or for the whole run with `N/A`
+    #[track_caller]
+    pub fn log_phase(symbol: &str, phase: &str, duration: Duration) {
+        let module = get_module(Location::caller());
+        if !logger::enabled(Level::Info, module) {
+            return;
+        }
+        logger::write(&Record {
+            level: Level::Info,
+            module,
+            symbol,
+            message: &format!("{phase} end"),
+            phase: Some(phase),
+            duration: Some(duration),
+        });
+    }
+
+    #[track_caller]
+    fn log_at<T: Display>(level: Level, symbol: &str, info: T) {
+        let module = get_module(Location::caller());
+        // the message is only put together when it will be written
+        if !logger::enabled(level, module) {
+            return;
+        }
+        logger::write(&Record {
+            level,
+            module,
+            symbol,
+            message: &info.to_string(),
+            phase: None,
+            duration: None,
+        });
+    }
+
+    /// the module a log call was made from, named after its source file
+    fn get_module(location: &'static Location<'static>) -> &'static str {
+        Path::new(location.file())
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("main")
+    }
+
+    /// joins the cells into one csv line, quoting the cells that need it
+    pub fn csv_line<T: AsRef<str>>(cells: &[T]) -> String {
+        let quoted: Vec<String> = cells
+            .iter()
+            .map(|cell| {
+                let cell = cell.as_ref();
+                if cell.contains([',', '"', '\n']) {
+                    format!("\"{}\"", cell.replace('"', "\"\""))
+                } else {
+                    cell.to_string()
                 }
+            })
+            .collect();
+        format!("{}\n", quoted.join(","))
+    }
+
+    /// lines up the rows under the header for a terminal, the first column left aligned and the rest right aligned
+    pub fn text_table<T: AsRef<str>>(header: &[&str], rows: &[Vec<T>]) -> String {
+        aligned_table(header, rows, 0, |_, _, cell| cell)
+    }
+
+    /// lines up the rows under the header for a terminal, the `left` column left aligned and the rest right
+    /// aligned. `paint` gets the row, the column and the padded cell, so it can wrap the cell in terminal colors
+    /// without the escape codes counting towards the width.
+    pub fn aligned_table<T: AsRef<str>>(
+        header: &[&str],
+        rows: &[Vec<T>],
+        left: usize,
+        paint: impl Fn(usize, usize, String) -> String,
+    ) -> String {
+        let mut widths: Vec<usize> = header.iter().map(|title| title.len()).collect();
+        for row in rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.as_ref().chars().count());
             }
-        });
+        }
+
+        let mut table = String::new();
+        let titles: Vec<String> = header
+            .iter()
+            .zip(&widths)
+            .map(|(title, width)| format!("{title:<width$}"))
+            .collect();
+        table.push_str(titles.join("  ").trim_end());
+        table.push('\n');
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        table.push_str(&rule.join("  "));
+        table.push('\n');
+        for (index, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(column, (cell, width))| {
+                    let padded = if column == left {
+                        format!("{:<width$}", cell.as_ref())
+                    } else {
+                        format!("{:>width$}", cell.as_ref())
+                    };
+                    paint(index, column, padded)
+                })
+                .collect();
+            table.push_str(cells.join("  ").trim_end());
+            table.push('\n');
+        }
+        table
     }
 }
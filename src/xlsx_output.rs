@@ -0,0 +1,230 @@
+pub mod xlsx_report {
+    use rust_xlsxwriter::{
+        ConditionalFormatCell, ConditionalFormatCellRule, Format, Workbook, Worksheet, XlsxError,
+    };
+
+    use crate::{
+        report::run_report::{get_symbol_rows, RunResults},
+        stock_simulation::stock_simulator::{
+            get_highest_x, get_thresholds, MostCommonResult, Thresholds,
+        },
+    };
+
+    const ALL_SYMBOLS_HEADER: [&str; 14] = [
+        "symbol",
+        "observations",
+        "simulations",
+        "25th",
+        "50th",
+        "75th",
+        "most_common",
+        "highest_low",
+        "total_span",
+        "weighted_span",
+        "most_common_rank",
+        "highest_low_rank",
+        "total_span_rank",
+        "weighted_span_rank",
+    ];
+
+    const TOP_X_HEADER: [&str; 7] = [
+        "rank",
+        "symbol",
+        "most_common",
+        "highest_low",
+        "total_span",
+        "weighted_span",
+        "simulations",
+    ];
+
+    /// Which way a metric is good, and so how its green, yellow and red cells are decided
+    enum Coloring {
+        /// green at or above the green threshold, red below the yellow threshold
+        HigherIsBetter(i32, i32),
+        /// green at or below the green threshold, red above the yellow threshold
+        LowerIsBetter(i32, i32),
+        /// green above zero, red below zero
+        Sign,
+    }
+
+    /// A workbook with every symbol, the top x and the run parameters on their own sheets. The metric columns
+    /// are colored with the same green, yellow and red thresholds as the html cards.
+    pub(crate) fn get_xlsx(results: &RunResults) -> Result<Vec<u8>, XlsxError> {
+        let mut workbook = Workbook::new();
+        let header = Format::new().set_bold();
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("All Symbols")?;
+        write_all_symbols(worksheet, results, &header)?;
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(format!("Top {}", results.metadata.top_x))?;
+        write_top_x(worksheet, results, &header)?;
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Run Parameters")?;
+        worksheet.write_row_with_format(0, 0, ["parameter", "value"], &header)?;
+        for (row, (name, value)) in results.metadata.parameters().into_iter().enumerate() {
+            worksheet.write(row as u32 + 1, 0, name)?;
+            worksheet.write(row as u32 + 1, 1, value)?;
+        }
+        worksheet.autofit();
+
+        // the header and disclaimer go on every printed page of every sheet
+        let header = results.metadata.header.as_deref().map(get_print_text);
+        let disclaimer = results.metadata.disclaimer.as_deref().map(get_print_text);
+        for worksheet in workbook.worksheets_mut() {
+            if let Some(header) = &header {
+                worksheet.set_header(format!("&C{header}"));
+            }
+            if let Some(disclaimer) = &disclaimer {
+                worksheet.set_footer(format!("&C{disclaimer}"));
+            }
+        }
+
+        workbook.save_to_buffer()
+    }
+
+    // an ampersand starts a control code in a page header or footer, two of them print one
+    fn get_print_text(text: &str) -> String {
+        text.replace('&', "&&")
+    }
+
+    fn write_all_symbols(
+        worksheet: &mut Worksheet,
+        results: &RunResults,
+        header: &Format,
+    ) -> Result<(), XlsxError> {
+        worksheet.write_row_with_format(0, 0, ALL_SYMBOLS_HEADER, header)?;
+        worksheet.set_freeze_panes(1, 1)?;
+
+        let rows = get_symbol_rows(&results.predictions);
+        for (index, row) in rows.iter().enumerate() {
+            let line = index as u32 + 1;
+            worksheet.write(line, 0, &row.symbol)?;
+            let numbers = [
+                row.observations as f64,
+                row.simulations as f64,
+                row.percentiles._25th as f64,
+                row.percentiles._50th as f64,
+                row.percentiles._75th as f64,
+                row.most_common as f64,
+                row.highest_low as f64,
+                row.total_span as f64,
+                row.weighted_span as f64,
+                row.most_common_rank as f64,
+                row.highest_low_rank as f64,
+                row.total_span_rank as f64,
+                row.weighted_span_rank as f64,
+            ];
+            worksheet.write_row(line, 1, numbers)?;
+        }
+        worksheet.autofit();
+
+        // every symbol is colored against the thresholds of all symbols rather than the top x
+        let all = get_highest_x(
+            results.predictions.len(),
+            &results.predictions,
+            Box::new(MostCommonResult {}),
+        );
+        add_coloring(worksheet, rows.len() as u32, &get_thresholds(&all), 6)
+    }
+
+    fn write_top_x(
+        worksheet: &mut Worksheet,
+        results: &RunResults,
+        header: &Format,
+    ) -> Result<(), XlsxError> {
+        worksheet.write_row_with_format(0, 0, TOP_X_HEADER, header)?;
+        worksheet.set_freeze_panes(1, 2)?;
+
+        for (index, pred) in results.top_predictions.iter().enumerate() {
+            let line = index as u32 + 1;
+            worksheet.write(line, 0, line)?;
+            worksheet.write(line, 1, &pred.symbol)?;
+            let numbers = [
+                pred.most_common,
+                pred.highest_low,
+                pred.total_span,
+                pred.weighted_span,
+            ];
+            worksheet.write_row(line, 2, numbers)?;
+            worksheet.write(line, 6, pred.simulations)?;
+        }
+        worksheet.autofit();
+
+        let thresholds = get_thresholds(&results.top_predictions);
+        add_coloring(
+            worksheet,
+            results.top_predictions.len() as u32,
+            &thresholds,
+            2,
+        )
+    }
+
+    /// Colors the most common, highest low, total span and weighted span columns that start at `first_col`
+    fn add_coloring(
+        worksheet: &mut Worksheet,
+        rows: u32,
+        thresholds: &Thresholds,
+        first_col: u16,
+    ) -> Result<(), XlsxError> {
+        if rows == 0 {
+            return Ok(());
+        }
+
+        let columns = [
+            Coloring::HigherIsBetter(thresholds.most_common_green, thresholds.most_common_yellow),
+            Coloring::HigherIsBetter(thresholds.highest_low_green, thresholds.highest_low_yellow),
+            Coloring::LowerIsBetter(thresholds.total_span_green, thresholds.total_span_yellow),
+            Coloring::Sign,
+        ];
+        for (offset, coloring) in columns.into_iter().enumerate() {
+            let col = first_col + offset as u16;
+            let (green, red) = match coloring {
+                Coloring::HigherIsBetter(green, yellow) => (
+                    ConditionalFormatCellRule::GreaterThanOrEqualTo(green),
+                    ConditionalFormatCellRule::LessThan(yellow),
+                ),
+                Coloring::LowerIsBetter(green, yellow) => (
+                    ConditionalFormatCellRule::LessThanOrEqualTo(green),
+                    ConditionalFormatCellRule::GreaterThan(yellow),
+                ),
+                Coloring::Sign => (
+                    ConditionalFormatCellRule::GreaterThan(0),
+                    ConditionalFormatCellRule::LessThan(0),
+                ),
+            };
+            // the rules are checked in order and stop at the first match, so whatever is left is yellow
+            let rules = [
+                (
+                    green,
+                    Format::new()
+                        .set_background_color("C6EFCE")
+                        .set_font_color("006100"),
+                ),
+                (
+                    red,
+                    Format::new()
+                        .set_background_color("FFC7CE")
+                        .set_font_color("9C0006"),
+                ),
+                (
+                    ConditionalFormatCellRule::Between(i32::MIN, i32::MAX),
+                    Format::new()
+                        .set_background_color("FFEB9C")
+                        .set_font_color("9C5700"),
+                ),
+            ];
+            for (rule, format) in rules {
+                let conditional = ConditionalFormatCell::new()
+                    .set_rule(rule)
+                    .set_format(format)
+                    .set_stop_if_true(true);
+                worksheet.add_conditional_format(1, col, rows, col, &conditional)?;
+            }
+        }
+
+        Ok(())
+    }
+}